@@ -1,11 +1,15 @@
 #!/bin/bash
-# Wipe data for a specific region from Elasticsearch
+# Wipe data for a specific region from Elasticsearch and ScyllaDB
+#
+# Places that another imported region still contains (extracts overlap at borders)
+# are kept; only that region is removed from their source files.
 #
 # Usage: ./wipe_region.sh <region_name> [options]
 #
 # Options:
-#   --url <url>       Custom Elasticsearch URL (default: http://localhost:9200)
-#   --index <name>    Index name (default: places)
+#   --url <url>         Custom Elasticsearch URL (default: http://localhost:9200)
+#   --index <name>      Index name (default: places)
+#   --scylla-url <url>  ScyllaDB URL (default: 127.0.0.1)
 #
 # Example: ./wipe_region.sh Albania
 
@@ -18,6 +22,7 @@ IMPORT_SCRIPT="${SCRIPT_DIR}/import_global.sh"
 REGION_NAME="$1"
 ES_URL="http://localhost:9200"
 INDEX_NAME="places"
+SCYLLA_URL="127.0.0.1"
 
 # Use ELASTICSEARCH_URL env var if set
 if [ -n "$ELASTICSEARCH_URL" ]; then
@@ -25,7 +30,7 @@ if [ -n "$ELASTICSEARCH_URL" ]; then
 fi
 
 if [ -z "$REGION_NAME" ]; then
-    echo "Usage: $0 <region_name> [--url <url>] [--index <name>]"
+    echo "Usage: $0 <region_name> [--url <url>] [--index <name>] [--scylla-url <url>]"
     echo "Example: $0 Albania"
     exit 1
 fi
@@ -43,6 +48,10 @@ while [[ $# -gt 0 ]]; do
             INDEX_NAME="$2"
             shift 2
             ;;
+        --scylla-url)
+            SCYLLA_URL="$2"
+            shift 2
+            ;;
         *)
             echo "Unknown option: $1"
             shift
@@ -54,6 +63,7 @@ echo "=== Cypress Wipe Region ==="
 echo "Region: $REGION_NAME"
 echo "Elasticsearch URL: $ES_URL"
 echo "Index: $INDEX_NAME"
+echo "ScyllaDB URL: $SCYLLA_URL"
 echo
 
 # Find region in import_global.sh to get filename
@@ -90,33 +100,18 @@ fi
 
 echo "Deleting documents with source_file matching: '$SOURCE_PATTERN'"
 
-# Perform delete by query
-QUERY=$(cat <<EOF
-{
-  "query": {
-    "wildcard": {
-      "source_file": {
-        "value": "$SOURCE_PATTERN"
-      }
-    }
-  }
-}
-EOF
-)
-
-# Use -s for silent, -S for show error
-RESPONSE=$(curl -s -S -X POST "$ES_URL/$INDEX_NAME/_delete_by_query?refresh&wait_for_completion=true" \
-    -H 'Content-Type: application/json' \
-    -d "$QUERY")
-
-if echo "$RESPONSE" | grep -q '"deleted"'; then
-    # Extract deleted count using grep/sed
-    DELETED=$(echo "$RESPONSE" | sed -n 's/.*"deleted":\([0-9]*\).*/\1/p')
-    echo "Successfully deleted $DELETED documents."
+# Ownership lives in ScyllaDB, so the deletion goes through the ingest binary
+INGEST_BIN="${PROJECT_DIR}/target/release/ingest"
+if [ -x "$INGEST_BIN" ]; then
+    INGEST=("$INGEST_BIN")
 else
-    echo "Error or no documents found:"
-    echo "$RESPONSE"
+    INGEST=(cargo run --release --quiet --manifest-path "${PROJECT_DIR}/Cargo.toml" --bin ingest --)
 fi
 
+"${INGEST[@]}" wipe-source "$SOURCE_PATTERN" \
+    --es-url "$ES_URL" \
+    --index "$INDEX_NAME" \
+    --scylla-url "$SCYLLA_URL"
+
 echo
 echo "Wipe complete."
@@ -3,6 +3,8 @@
 use anyhow::{Context, Result};
 use elasticsearch::http::request::JsonBody;
 use elasticsearch::BulkParts;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 use tokio::sync::mpsc;
 use tokio::task::JoinHandle;
 use tracing::{debug, error, info, warn};
@@ -13,16 +15,32 @@ use super::{EsClient, EsDocument};
 pub struct BulkIndexer<T: EsDocument> {
     sender: mpsc::Sender<T>,
     handle: JoinHandle<Result<(usize, usize)>>,
+    flushed: Arc<AtomicUsize>,
 }
 
 impl<T: EsDocument> BulkIndexer<T> {
     /// Create a new bulk indexer
     pub fn new(client: EsClient, batch_size: usize) -> Self {
         let (tx, rx) = mpsc::channel(batch_size * 2);
+        let flushed = Arc::new(AtomicUsize::new(0));
 
-        let handle = tokio::spawn(run_indexer(client, rx, batch_size));
+        let handle = tokio::spawn(run_indexer(client, rx, batch_size, Arc::clone(&flushed)));
 
-        Self { sender: tx, handle }
+        Self {
+            sender: tx,
+            handle,
+            flushed,
+        }
+    }
+
+    /// Counter of documents that have been persisted by Elasticsearch in completed bulk
+    /// requests.
+    ///
+    /// Documents are flushed in the order they were queued, so once this reaches `n`
+    /// the first `n` queued documents are persisted. It stops at the first bulk request
+    /// with rejected documents.
+    pub fn flushed_counter(&self) -> Arc<AtomicUsize> {
+        Arc::clone(&self.flushed)
     }
 
     /// Add a document to the indexing queue
@@ -53,6 +71,7 @@ async fn run_indexer<T: EsDocument>(
     client: EsClient,
     mut rx: mpsc::Receiver<T>,
     batch_size: usize,
+    flushed: Arc<AtomicUsize>,
 ) -> Result<(usize, usize)> {
     let mut buffer = Vec::with_capacity(batch_size);
     let mut total_indexed = 0;
@@ -66,6 +85,10 @@ async fn run_indexer<T: EsDocument>(
                 Ok((indexed, errors)) => {
                     total_indexed += indexed;
                     total_errors += errors;
+                    // Rejected documents are lost; checkpoints must not move past them
+                    if total_errors == 0 {
+                        flushed.fetch_add(indexed, Ordering::SeqCst);
+                    }
                 }
                 Err(e) => {
                     error!("Failed to flush batch: {}", e);
@@ -84,6 +107,9 @@ async fn run_indexer<T: EsDocument>(
         let (indexed, errors) = flush(&client, &mut buffer).await?;
         total_indexed += indexed;
         total_errors += errors;
+        if total_errors == 0 {
+            flushed.fetch_add(indexed, Ordering::SeqCst);
+        }
     }
 
     info!(
@@ -130,6 +156,17 @@ async fn flush<T: EsDocument>(client: &EsClient, buffer: &mut Vec<T>) -> Result<
         .await
         .context("Bulk request failed")?;
 
+    // A rejected request (too large, index closed, ...) persists none of the documents
+    let status = response.status_code();
+    if !status.is_success() {
+        let text = response.text().await.unwrap_or_default();
+        warn!(
+            "Bulk request of {} documents failed with status {}: {}",
+            count, status, text
+        );
+        return Ok((count, count));
+    }
+
     let response_body = response.json::<serde_json::Value>().await?;
     let mut batch_errors = 0;
 
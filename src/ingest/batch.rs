@@ -1,9 +1,11 @@
-use crate::config::{Config, RegionConfig};
-use crate::version::{calculate_file_hash, VersionDoc, VersionManager};
-use crate::Args;
+use super::checkpoint::Checkpoint;
+use super::config::{Config, RegionConfig};
+use super::version::{calculate_file_hash, VersionDoc, VersionManager};
+use super::{load_synonyms, Args};
+use crate::transform::TransformerChain;
 use anyhow::{Context, Result};
 use chrono::Utc;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::process::Command;
 use std::sync::Arc;
 use tokio::sync::mpsc;
@@ -17,11 +19,14 @@ struct PreparedRegion {
     import_start: chrono::DateTime<Utc>,
 }
 
+/// Import every region of the batch config, passing every place through `transformers`
 pub async fn run_batch(
     config_path: PathBuf,
     args: Args,
-    synonyms: Arc<crate::synonyms::SynonymService>,
+    transformers: TransformerChain,
 ) -> Result<()> {
+    let synonyms = Arc::new(load_synonyms()?);
+    let transformers = Arc::new(transformers);
     let config = Config::load_from_file(config_path)?;
     let version_manager = Arc::new(VersionManager::new(&config.global.es_url).await?);
 
@@ -115,7 +120,7 @@ pub async fn run_batch(
     while let Some(prepared) = rx.recv().await {
         info!("Starting ingest for {}", prepared.region.name);
 
-        let res = crate::run_single(prepared.args, synonyms.clone()).await;
+        let res = super::run_single(prepared.args, synonyms.clone(), transformers.clone()).await;
         if let Err(e) = res {
             error!("Ingest failed for {}: {:?}", prepared.region.name, e);
             continue;
@@ -195,11 +200,24 @@ async fn prepare_region(
     }
 
     // 3. Filter
-    let filtered_pbf = config.global.tmp_dir.join(format!(
-        "{}-filtered.osm.pbf",
+    let filtered_name = format!("{}-filtered.osm.pbf", filename.trim_end_matches(".osm.pbf"));
+    let filtered_pbf = config.global.tmp_dir.join(&filtered_name);
+    let admins_pbf = config.global.tmp_dir.join(format!(
+        "{}-admins.osm.pbf",
         filename.trim_end_matches(".osm.pbf")
     ));
 
+    // An interrupted import of this download is resumed from its checkpoint, which is
+    // only valid as long as the filtered files are left untouched
+    let resumable = Checkpoint::exists(&config.global.tmp_dir, &filtered_name)
+        && is_newer(&filtered_pbf, &raw_pbf);
+    if resumable {
+        info!(
+            "Found checkpoint for {}, reusing filtered files.",
+            region.name
+        );
+    }
+
     let script_dir = std::env::current_dir()?.join("scripts");
     let filter_script = script_dir.join("filter_osm.sh");
 
@@ -210,26 +228,26 @@ async fn prepare_region(
         anyhow::bail!("Filter script not found at {:?}", filter_script);
     }
 
-    info!("Filtering {}...", region.name);
-    let status = Command::new(&filter_script)
-        .arg(&raw_pbf)
-        .arg(&filtered_pbf)
-        .status()
-        .context("Failed to run filter script")?;
+    if !resumable {
+        info!("Filtering {}...", region.name);
+        let status = Command::new(&filter_script)
+            .arg(&raw_pbf)
+            .arg(&filtered_pbf)
+            .status()
+            .context("Failed to run filter script")?;
 
-    if !status.success() {
-        warn!("Filtering failed for {}. Skipping.", region.name);
-        return Ok(None);
+        if !status.success() {
+            warn!("Filtering failed for {}. Skipping.", region.name);
+            return Ok(None);
+        }
     }
 
     // Admin filter
-    let admins_pbf = config.global.tmp_dir.join(format!(
-        "{}-admins.osm.pbf",
-        filename.trim_end_matches(".osm.pbf")
-    ));
     let admin_script = script_dir.join("filter_admins.sh");
 
-    let admin_file_arg = if admin_script.exists() {
+    let admin_file_arg = if resumable && is_newer(&admins_pbf, &raw_pbf) {
+        Some(admins_pbf)
+    } else if admin_script.exists() {
         info!("Filtering admins for {}...", region.name);
         let status = Command::new(&admin_script)
             .arg(&raw_pbf)
@@ -250,8 +268,21 @@ async fn prepare_region(
     args.file = Some(filtered_pbf);
     args.admin_file = admin_file_arg;
     args.es_url = config.global.es_url.clone();
+    args.tmp_dir = config.global.tmp_dir.clone();
+    // Regions without a checkpoint keep the requested geometry store unless --checkpoint
+    args.resume |= resumable;
+    if let Some(languages) = region
+        .languages
+        .as_ref()
+        .or(config.global.languages.as_ref())
+    {
+        args.languages = languages.clone();
+    }
 
     if !is_first_region {
+        // The index was recreated for the first region and starts empty, so later
+        // regions must not skip places that are unchanged since their last import
+        args.force_rewrite |= args.create_index;
         args.create_index = false;
     }
     // If is_first_region is true, args.create_index remains whatever base_args has.
@@ -264,3 +295,12 @@ async fn prepare_region(
         import_start: Utc::now(),
     }))
 }
+
+/// Whether `path` exists and was modified after `than`
+fn is_newer(path: &Path, than: &Path) -> bool {
+    let modified = |p: &Path| std::fs::metadata(p).and_then(|m| m.modified()).ok();
+    match (modified(path), modified(than)) {
+        (Some(a), Some(b)) => a >= b,
+        _ => false,
+    }
+}
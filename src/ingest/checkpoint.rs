@@ -0,0 +1,270 @@
+//! Checkpoints for resumable imports.
+//!
+//! A checkpoint records how far an import of one source file has progressed, so that
+//! `ingest single --resume` (and `ingest batch`) can continue an interrupted run instead of
+//! starting over. Everything lives in `tmp_dir/checkpoints`:
+//!
+//! - `<source_file>.json`: the [`Checkpoint`] itself
+//! - `<source_file>.admins.bin`: the extracted admin boundaries
+//!
+//! The geometry store is persisted separately by the disk [`StoreMode`](crate::pip::StoreMode).
+//!
+//! Progress only advances once every document produced before a position has been flushed
+//! to Elasticsearch (and written to Scylla), so resuming never skips unsaved work.
+
+use std::collections::VecDeque;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::{info, warn};
+
+/// Persisted progress of an import of a single source file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Checkpoint {
+    pub source_file: String,
+
+    /// Fingerprint of the input file; a checkpoint for a different file is discarded
+    pub fingerprint: String,
+
+    /// Start of the original run. Kept across resumes so stale-document cleanup
+    /// removes only documents from previous imports.
+    pub import_start: DateTime<Utc>,
+
+    /// Number of OSM objects in the input, to skip the counting pass
+    #[serde(default)]
+    pub total_count: Option<u64>,
+
+    /// Admin boundaries have been written next to the checkpoint
+    #[serde(default)]
+    pub admins_extracted: bool,
+
+    /// Admin boundary places have been indexed
+    #[serde(default)]
+    pub admins_indexed: bool,
+
+    /// Number of OSM objects from the start of the input whose places are fully flushed
+    #[serde(default)]
+    pub objects_done: u64,
+
+    /// Number of merged roads whose places are fully flushed
+    #[serde(default)]
+    pub roads_done: usize,
+}
+
+/// A position reached by the main loop, passed through the pipeline in order with places
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Progress {
+    AdminsIndexed,
+    Objects(u64),
+    Roads(usize),
+}
+
+impl Checkpoint {
+    pub fn new(source_file: &str, fingerprint: String, import_start: DateTime<Utc>) -> Self {
+        Self {
+            source_file: source_file.to_string(),
+            fingerprint,
+            import_start,
+            total_count: None,
+            admins_extracted: false,
+            admins_indexed: false,
+            objects_done: 0,
+            roads_done: 0,
+        }
+    }
+
+    fn dir(tmp_dir: &Path) -> PathBuf {
+        tmp_dir.join("checkpoints")
+    }
+
+    pub fn path(tmp_dir: &Path, source_file: &str) -> PathBuf {
+        Self::dir(tmp_dir).join(format!("{}.json", source_file))
+    }
+
+    /// Location of the persisted admin boundaries
+    pub fn admins_path(tmp_dir: &Path, source_file: &str) -> PathBuf {
+        Self::dir(tmp_dir).join(format!("{}.admins.bin", source_file))
+    }
+
+    pub fn exists(tmp_dir: &Path, source_file: &str) -> bool {
+        Self::path(tmp_dir, source_file).exists()
+    }
+
+    /// Load the checkpoint for `source_file` if it matches `fingerprint`
+    pub fn load(tmp_dir: &Path, source_file: &str, fingerprint: &str) -> Result<Option<Self>> {
+        let path = Self::path(tmp_dir, source_file);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let data = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read checkpoint {}", path.display()))?;
+        let checkpoint: Checkpoint = match serde_json::from_str(&data) {
+            Ok(c) => c,
+            Err(e) => {
+                warn!("Ignoring unreadable checkpoint {}: {}", path.display(), e);
+                return Ok(None);
+            }
+        };
+
+        if checkpoint.fingerprint != fingerprint {
+            info!(
+                "Input {} changed since checkpoint was written, starting over",
+                source_file
+            );
+            Self::remove(tmp_dir, source_file)?;
+            return Ok(None);
+        }
+
+        Ok(Some(checkpoint))
+    }
+
+    /// Atomically write the checkpoint
+    pub fn save(&self, tmp_dir: &Path) -> Result<()> {
+        fs::create_dir_all(Self::dir(tmp_dir))?;
+        let path = Self::path(tmp_dir, &self.source_file);
+        let tmp = path.with_extension("json.tmp");
+        fs::write(&tmp, serde_json::to_vec_pretty(self)?)?;
+        fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    /// Remove the checkpoint and its admin boundaries after a completed import
+    pub fn remove(tmp_dir: &Path, source_file: &str) -> Result<()> {
+        for path in [
+            Self::path(tmp_dir, source_file),
+            Self::admins_path(tmp_dir, source_file),
+        ] {
+            if path.exists() {
+                fs::remove_file(&path)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn apply(&mut self, progress: Progress) {
+        match progress {
+            Progress::AdminsIndexed => self.admins_indexed = true,
+            Progress::Objects(n) => {
+                self.admins_indexed = true;
+                self.objects_done = self.objects_done.max(n);
+            }
+            Progress::Roads(n) => self.roads_done = self.roads_done.max(n),
+        }
+    }
+}
+
+/// Advances a [`Checkpoint`] as documents are flushed.
+///
+/// Progress markers are queued together with the number of documents sent to the bulk
+/// indexer before them, and applied once the indexer's flushed counter reaches that number.
+pub struct CheckpointTracker {
+    checkpoint: Checkpoint,
+    tmp_dir: PathBuf,
+    flushed: Arc<AtomicUsize>,
+    pending: VecDeque<(usize, Progress)>,
+    failed: bool,
+}
+
+impl CheckpointTracker {
+    pub fn new(checkpoint: Checkpoint, tmp_dir: PathBuf, flushed: Arc<AtomicUsize>) -> Self {
+        Self {
+            checkpoint,
+            tmp_dir,
+            flushed,
+            pending: VecDeque::new(),
+            failed: false,
+        }
+    }
+
+    /// Record that `progress` is reached once `docs` documents have been flushed
+    pub fn mark(&mut self, docs: usize, progress: Progress) {
+        if !self.failed {
+            self.pending.push_back((docs, progress));
+        }
+    }
+
+    /// Stop advancing: some documents before the pending markers were lost
+    pub fn fail(&mut self) {
+        self.failed = true;
+        self.pending.clear();
+    }
+
+    pub fn failed(&self) -> bool {
+        self.failed
+    }
+
+    /// Apply every marker whose documents are flushed and save the checkpoint if it moved
+    pub fn poll(&mut self) -> Result<()> {
+        let flushed = self.flushed.load(Ordering::SeqCst);
+        let mut advanced = false;
+
+        while let Some(&(docs, progress)) = self.pending.front() {
+            if docs > flushed {
+                break;
+            }
+            self.checkpoint.apply(progress);
+            self.pending.pop_front();
+            advanced = true;
+        }
+
+        if advanced {
+            self.checkpoint.save(&self.tmp_dir)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tracker_waits_for_flush() {
+        let dir = tempfile::tempdir().unwrap();
+        let flushed = Arc::new(AtomicUsize::new(0));
+        let checkpoint = Checkpoint::new("test.osm.pbf", "1-1".to_string(), Utc::now());
+        let mut tracker =
+            CheckpointTracker::new(checkpoint, dir.path().to_path_buf(), Arc::clone(&flushed));
+
+        tracker.mark(10, Progress::AdminsIndexed);
+        tracker.mark(25, Progress::Objects(1000));
+        tracker.mark(40, Progress::Objects(2000));
+
+        flushed.store(30, Ordering::SeqCst);
+        tracker.poll().unwrap();
+
+        let saved = Checkpoint::load(dir.path(), "test.osm.pbf", "1-1")
+            .unwrap()
+            .unwrap();
+        assert!(saved.admins_indexed);
+        assert_eq!(saved.objects_done, 1000);
+
+        // Nothing advances past a failure
+        tracker.fail();
+        flushed.store(40, Ordering::SeqCst);
+        tracker.poll().unwrap();
+        let saved = Checkpoint::load(dir.path(), "test.osm.pbf", "1-1")
+            .unwrap()
+            .unwrap();
+        assert_eq!(saved.objects_done, 1000);
+    }
+
+    #[test]
+    fn test_checkpoint_discarded_when_input_changes() {
+        let dir = tempfile::tempdir().unwrap();
+        Checkpoint::new("test.osm.pbf", "1-1".to_string(), Utc::now())
+            .save(dir.path())
+            .unwrap();
+
+        assert!(Checkpoint::load(dir.path(), "test.osm.pbf", "2-2")
+            .unwrap()
+            .is_none());
+        assert!(!Checkpoint::exists(dir.path(), "test.osm.pbf"));
+    }
+}
@@ -0,0 +1,208 @@
+//! Restricting an import to an area (`--bbox`, `--clip-polygon`).
+
+use std::str::FromStr;
+
+use anyhow::Result;
+use geo::{BoundingRect, Contains, Intersects, MultiPolygon, Point, Rect};
+use tracing::info;
+
+use crate::pip::geojson::read_multipolygon;
+
+use super::Args;
+
+/// Bounding box given as `minLon,minLat,maxLon,maxLat`.
+///
+/// `min_lon > max_lon` describes a box crossing the antimeridian.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bbox {
+    pub min_lon: f64,
+    pub min_lat: f64,
+    pub max_lon: f64,
+    pub max_lat: f64,
+}
+
+impl FromStr for Bbox {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parts: Vec<f64> = s
+            .split(',')
+            .map(|p| p.trim().parse::<f64>())
+            .collect::<Result<_, _>>()
+            .map_err(|_| format!("invalid bbox '{}', expected minLon,minLat,maxLon,maxLat", s))?;
+
+        let [min_lon, min_lat, max_lon, max_lat] = parts[..] else {
+            return Err(format!(
+                "invalid bbox '{}', expected minLon,minLat,maxLon,maxLat",
+                s
+            ));
+        };
+
+        if !(-180.0..=180.0).contains(&min_lon) || !(-180.0..=180.0).contains(&max_lon) {
+            return Err("bbox longitudes must be within -180..180".to_string());
+        }
+        if !(-90.0..=90.0).contains(&min_lat) || !(-90.0..=90.0).contains(&max_lat) {
+            return Err("bbox latitudes must be within -90..90".to_string());
+        }
+        if min_lat > max_lat {
+            return Err("bbox minLat must not be greater than maxLat".to_string());
+        }
+
+        Ok(Self {
+            min_lon,
+            min_lat,
+            max_lon,
+            max_lat,
+        })
+    }
+}
+
+impl Bbox {
+    fn contains(&self, lon: f64, lat: f64) -> bool {
+        let lon_inside = if self.min_lon <= self.max_lon {
+            lon >= self.min_lon && lon <= self.max_lon
+        } else {
+            lon >= self.min_lon || lon <= self.max_lon
+        };
+        lon_inside && lat >= self.min_lat && lat <= self.max_lat
+    }
+
+    /// The box as polygons, split in two at the antimeridian if needed
+    fn to_multipolygon(self) -> MultiPolygon<f64> {
+        let rect = |min_lon: f64, max_lon: f64| {
+            Rect::new((min_lon, self.min_lat), (max_lon, self.max_lat)).to_polygon()
+        };
+        if self.min_lon <= self.max_lon {
+            MultiPolygon(vec![rect(self.min_lon, self.max_lon)])
+        } else {
+            MultiPolygon(vec![rect(self.min_lon, 180.0), rect(-180.0, self.max_lon)])
+        }
+    }
+}
+
+/// Area an import is restricted to.
+///
+/// Places are kept when their center lies inside every given shape; admin boundaries are
+/// kept when they intersect, so hierarchies of places near the edge stay complete.
+pub struct ClipArea {
+    bbox: Option<(Bbox, MultiPolygon<f64>)>,
+    polygon: Option<(MultiPolygon<f64>, Rect<f64>)>,
+}
+
+impl ClipArea {
+    /// Build the clip area from `--bbox` / `--clip-polygon`, if either is set
+    pub fn from_args(args: &Args) -> Result<Option<Self>> {
+        let bbox = args.bbox.map(|b| (b, b.to_multipolygon()));
+
+        let polygon = match &args.clip_polygon {
+            Some(path) => {
+                let polygon = read_multipolygon(path)?;
+                let rect = polygon
+                    .bounding_rect()
+                    .ok_or_else(|| anyhow::anyhow!("{} has no extent", path.display()))?;
+                info!(
+                    "Clipping import to {} polygon(s) from {}",
+                    polygon.0.len(),
+                    path.display()
+                );
+                Some((polygon, rect))
+            }
+            None => None,
+        };
+
+        if let Some((b, _)) = &bbox {
+            info!(
+                "Clipping import to bbox {},{},{},{}",
+                b.min_lon, b.min_lat, b.max_lon, b.max_lat
+            );
+        }
+
+        if bbox.is_none() && polygon.is_none() {
+            return Ok(None);
+        }
+        Ok(Some(Self { bbox, polygon }))
+    }
+
+    /// Whether a place at this position belongs to the import
+    pub fn contains(&self, lon: f64, lat: f64) -> bool {
+        if let Some((bbox, _)) = &self.bbox {
+            if !bbox.contains(lon, lat) {
+                return false;
+            }
+        }
+        if let Some((polygon, rect)) = &self.polygon {
+            let point = Point::new(lon, lat);
+            if !rect.contains(&point) || !polygon.contains(&point) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Whether a boundary overlaps the area
+    pub fn intersects(&self, geometry: &MultiPolygon<f64>) -> bool {
+        if let Some((_, shape)) = &self.bbox {
+            if !shape.intersects(geometry) {
+                return false;
+            }
+        }
+        if let Some((polygon, rect)) = &self.polygon {
+            let overlaps_rect = geometry.bounding_rect().is_some_and(|r| r.intersects(rect));
+            if !overlaps_rect || !polygon.intersects(geometry) {
+                return false;
+            }
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo::polygon;
+
+    fn clip(bbox: &str) -> ClipArea {
+        let bbox: Bbox = bbox.parse().unwrap();
+        ClipArea {
+            bbox: Some((bbox, bbox.to_multipolygon())),
+            polygon: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_bbox() {
+        let bbox: Bbox = "-122.52,37.70,-122.35,37.83".parse().unwrap();
+        assert_eq!(bbox.min_lon, -122.52);
+        assert_eq!(bbox.max_lat, 37.83);
+
+        assert!("1,2,3".parse::<Bbox>().is_err());
+        assert!("0,10,1,5".parse::<Bbox>().is_err());
+        assert!("0,0,200,1".parse::<Bbox>().is_err());
+    }
+
+    #[test]
+    fn test_contains_and_intersects() {
+        let area = clip("8.4,47.3,8.6,47.45");
+        assert!(area.contains(8.54, 47.37));
+        assert!(!area.contains(7.44, 46.95));
+
+        // Canton-sized boundary that only partly overlaps the box is kept
+        let canton = MultiPolygon(vec![polygon![
+            (x: 8.3, y: 47.1), (x: 8.5, y: 47.1), (x: 8.5, y: 47.4), (x: 8.3, y: 47.4), (x: 8.3, y: 47.1),
+        ]]);
+        assert!(area.intersects(&canton));
+
+        let elsewhere = MultiPolygon(vec![polygon![
+            (x: 7.0, y: 46.0), (x: 7.5, y: 46.0), (x: 7.5, y: 46.5), (x: 7.0, y: 46.0),
+        ]]);
+        assert!(!area.intersects(&elsewhere));
+    }
+
+    #[test]
+    fn test_antimeridian_bbox() {
+        let area = clip("170,-20,-170,-10");
+        assert!(area.contains(178.0, -18.0));
+        assert!(area.contains(-175.0, -15.0));
+        assert!(!area.contains(0.0, -15.0));
+    }
+}
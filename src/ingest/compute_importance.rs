@@ -0,0 +1,514 @@
+//! `ingest compute-importance`: builds `wikimedia-importance.csv` from Wikipedia and
+//! Wikidata dumps instead of a precomputed download.
+//!
+//! Expected in `--dump-dir` (from dumps.wikimedia.org, gzipped or plain):
+//! - `wikidatawiki-<date>-wb_items_per_site.sql.gz`, the Wikidata item of each article
+//! - `<lang>wiki-<date>-pagelinks.sql.gz` per language, and for dumps since 2024, which
+//!   refer to link targets by ID, `<lang>wiki-<date>-linktarget.sql.gz`
+//!
+//! `--pageviews-dir` optionally adds page views: hourly `pageviews-<YYYYMMDD>-<HHMMSS>.gz`
+//! files or (decompressed) daily `pageviews-<YYYYMMDD>-user` files, limited to the days
+//! between `--from` and `--until`.
+//!
+//! An article scores its number of links from other articles on a log scale relative to
+//! the most linked article of its language, averaged with the same for its page views.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{bail, Context, Result};
+use chrono::NaiveDate;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use flate2::Compression;
+use tracing::{info, warn};
+
+#[derive(clap::Args, Debug, Clone)]
+pub struct ComputeImportanceArgs {
+    /// Directory with the pagelinks/linktarget and wb_items_per_site SQL dumps
+    #[arg(long)]
+    pub dump_dir: PathBuf,
+
+    /// Wikipedia languages to score, e.g. `en,de,fr`
+    #[arg(long, value_delimiter = ',', required = true)]
+    pub languages: Vec<String>,
+
+    /// Directory with pageviews files; without it articles are scored by links only
+    #[arg(long)]
+    pub pageviews_dir: Option<PathBuf>,
+
+    /// First day of page views to count (YYYY-MM-DD)
+    #[arg(long)]
+    pub from: Option<NaiveDate>,
+
+    /// Last day of page views to count (YYYY-MM-DD)
+    #[arg(long)]
+    pub until: Option<NaiveDate>,
+
+    /// Output TSV, gzipped when ending in `.gz`
+    #[arg(long, default_value = "wikimedia-importance.csv")]
+    pub output: PathBuf,
+}
+
+/// Articles of one Wikipedia
+struct Language {
+    code: String,
+    /// Database name, e.g. `enwiki` or `zh_yuewiki`
+    dbname: String,
+    /// Title (with underscores) to Wikidata item number
+    titles: HashMap<String, u32>,
+    /// Links and page views by item number
+    counts: HashMap<u32, (u64, u64)>,
+}
+
+pub fn run(args: &ComputeImportanceArgs) -> Result<()> {
+    let mut languages: Vec<Language> = args
+        .languages
+        .iter()
+        .map(|code| Language {
+            code: code.clone(),
+            dbname: format!("{}wiki", code.replace('-', "_")),
+            titles: HashMap::new(),
+            counts: HashMap::new(),
+        })
+        .collect();
+
+    let sitelinks = find_dump(&args.dump_dir, "wikidatawiki", "wb_items_per_site")?
+        .context("No wikidatawiki-*-wb_items_per_site.sql(.gz) in the dump directory")?;
+    info!("Reading sitelinks from {}", sitelinks.display());
+    for_each_row(&sitelinks, |row| {
+        // (ips_row_id, ips_item_id, ips_site_id, ips_site_page)
+        let (Some(item), Some(site), Some(title)) = (row.int(1), row.text(2), row.text(3)) else {
+            return;
+        };
+        if let (Some(language), Ok(item)) = (
+            languages.iter_mut().find(|l| l.dbname == site),
+            u32::try_from(item),
+        ) {
+            language.titles.insert(title.replace(' ', "_"), item);
+        }
+    })?;
+
+    for language in &mut languages {
+        info!(
+            "{}: {} articles with a Wikidata item",
+            language.code,
+            language.titles.len()
+        );
+        count_links(&args.dump_dir, language)?;
+    }
+
+    if let Some(dir) = &args.pageviews_dir {
+        count_views(dir, args.from, args.until, &mut languages)?;
+    }
+
+    let count = write_scores(&args.output, &languages, args.pageviews_dir.is_some())?;
+    info!("Wrote {} articles to {}", count, args.output.display());
+    Ok(())
+}
+
+fn count_links(dump_dir: &Path, language: &mut Language) -> Result<()> {
+    let pagelinks = find_dump(dump_dir, &language.dbname, "pagelinks")?.with_context(|| {
+        format!(
+            "No {}-*-pagelinks.sql(.gz) in the dump directory",
+            language.dbname
+        )
+    })?;
+    let linktarget = find_dump(dump_dir, &language.dbname, "linktarget")?;
+
+    // Link target ID to item
+    let mut targets: HashMap<u64, u32> = HashMap::new();
+    if let Some(path) = &linktarget {
+        info!("Reading link targets from {}", path.display());
+        for_each_row(path, |row| {
+            // (lt_id, lt_namespace, lt_title), articles only
+            if row.int(1) != Some(0) {
+                return;
+            }
+            if let (Some(id), Some(item)) =
+                (row.int(0), row.text(2).and_then(|t| language.titles.get(t)))
+            {
+                targets.insert(id, *item);
+            }
+        })?;
+    }
+
+    info!("Counting links in {}", pagelinks.display());
+    let mut needs_targets = false;
+    for_each_row(&pagelinks, |row| {
+        let item = match row.len() {
+            // (pl_from, pl_from_namespace, pl_target_id)
+            3 if row.int(1) == Some(0) => {
+                needs_targets = true;
+                row.int(2).and_then(|id| targets.get(&id))
+            }
+            // Before 2024: (pl_from, pl_namespace, pl_title, pl_from_namespace)
+            4 if row.int(1) == Some(0) && row.int(3) == Some(0) => {
+                row.text(2).and_then(|t| language.titles.get(t))
+            }
+            _ => None,
+        };
+        if let Some(item) = item {
+            language.counts.entry(*item).or_default().0 += 1;
+        }
+    })?;
+
+    if needs_targets && linktarget.is_none() {
+        bail!(
+            "{} refers to link targets by ID; {}-*-linktarget.sql(.gz) is needed as well",
+            pagelinks.display(),
+            language.dbname
+        );
+    }
+    Ok(())
+}
+
+fn count_views(
+    dir: &Path,
+    from: Option<NaiveDate>,
+    until: Option<NaiveDate>,
+    languages: &mut [Language],
+) -> Result<()> {
+    let mut files: Vec<(NaiveDate, PathBuf)> = Vec::new();
+    for entry in
+        std::fs::read_dir(dir).with_context(|| format!("Failed to read {}", dir.display()))?
+    {
+        let path = entry?.path();
+        let Some(day) = pageviews_day(&path) else {
+            continue;
+        };
+        if from.is_some_and(|from| day < from) || until.is_some_and(|until| day > until) {
+            continue;
+        }
+        files.push((day, path));
+    }
+    files.sort();
+    if files.is_empty() {
+        warn!("No pageviews files for the given days in {}", dir.display());
+    }
+
+    let mut line = Vec::new();
+    for (_, path) in files {
+        info!("Counting page views in {}", path.display());
+        let mut reader = BufReader::new(open(&path)?);
+        loop {
+            line.clear();
+            if reader.read_until(b'\n', &mut line)? == 0 {
+                break;
+            }
+            let Ok(text) = std::str::from_utf8(&line) else {
+                continue;
+            };
+            let mut fields = text.trim_end().split(' ');
+            let (Some(domain), Some(title)) = (fields.next(), fields.next()) else {
+                continue;
+            };
+            let (code, views) = match domain.strip_suffix(".wikipedia") {
+                // Daily: wiki, title, page ID, access method, views, hourly views
+                Some(code) => (code, fields.nth(2)),
+                // Hourly: domain (`en` desktop, `en.m` mobile Wikipedia), title, views, bytes
+                None => (domain.strip_suffix(".m").unwrap_or(domain), fields.next()),
+            };
+            let Some(views) = views.and_then(|v| v.parse::<u64>().ok()) else {
+                continue;
+            };
+            let Some(language) = languages.iter_mut().find(|l| l.code == code) else {
+                continue;
+            };
+            if let Some(item) = language.titles.get(title) {
+                language.counts.entry(*item).or_default().1 += views;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Day of a `pageviews-<YYYYMMDD>-...` file
+fn pageviews_day(path: &Path) -> Option<NaiveDate> {
+    let name = path.file_name()?.to_str()?;
+    let date = name.strip_prefix("pageviews-")?.get(..8)?;
+    NaiveDate::parse_from_str(date, "%Y%m%d").ok()
+}
+
+/// Write the scores in the format of `wikimedia-importance.csv`; returns the number of
+/// articles written
+fn write_scores(path: &Path, languages: &[Language], with_views: bool) -> Result<u64> {
+    let file = BufWriter::new(
+        File::create(path).with_context(|| format!("Failed to create {}", path.display()))?,
+    );
+    if path.extension().is_some_and(|e| e == "gz") {
+        let mut encoder = GzEncoder::new(file, Compression::default());
+        let count = write_rows(&mut encoder, languages, with_views)?;
+        encoder.finish()?.flush()?;
+        Ok(count)
+    } else {
+        let mut file = file;
+        let count = write_rows(&mut file, languages, with_views)?;
+        file.flush()?;
+        Ok(count)
+    }
+}
+
+fn write_rows(writer: impl Write, languages: &[Language], with_views: bool) -> Result<u64> {
+    let mut writer = csv::WriterBuilder::new()
+        .delimiter(b'\t')
+        .from_writer(writer);
+    writer.write_record(["language", "type", "title", "importance", "wikidata_id"])?;
+
+    let mut count = 0;
+    for language in languages {
+        let max_links = language.counts.values().map(|c| c.0).max().unwrap_or(0);
+        let max_views = language.counts.values().map(|c| c.1).max().unwrap_or(0);
+
+        let mut rows: Vec<(&str, u32, f64)> = language
+            .titles
+            .iter()
+            .filter_map(|(title, item)| {
+                let (links, views) = language.counts.get(item)?;
+                let score = if with_views {
+                    (log_share(*links, max_links) + log_share(*views, max_views)) / 2.0
+                } else {
+                    log_share(*links, max_links)
+                };
+                Some((title.as_str(), *item, score))
+            })
+            .collect();
+        rows.sort_by(|a, b| a.0.cmp(b.0));
+
+        for (title, item, score) in rows {
+            writer.write_record([
+                language.code.as_str(),
+                "a",
+                title,
+                &score.to_string(),
+                &format!("Q{}", item),
+            ])?;
+            count += 1;
+        }
+    }
+    writer.flush()?;
+    Ok(count)
+}
+
+/// `count` on a log scale from 0 to 1 at `max`
+fn log_share(count: u64, max: u64) -> f64 {
+    if max == 0 {
+        return 0.0;
+    }
+    (count as f64).ln_1p() / (max as f64).ln_1p()
+}
+
+/// Newest `<dbname>-<date>-<table>.sql(.gz)` in `dir`
+fn find_dump(dir: &Path, dbname: &str, table: &str) -> Result<Option<PathBuf>> {
+    let prefix = format!("{}-", dbname);
+    let suffix = format!("-{}.sql", table);
+    let mut dumps: Vec<PathBuf> = std::fs::read_dir(dir)
+        .with_context(|| format!("Failed to read {}", dir.display()))?
+        .filter_map(|entry| entry.ok().map(|e| e.path()))
+        .filter(|path| {
+            path.file_name()
+                .and_then(|n| n.to_str())
+                .is_some_and(|name| {
+                    let name = name.strip_suffix(".gz").unwrap_or(name);
+                    name.starts_with(&prefix) && name.ends_with(&suffix)
+                })
+        })
+        .collect();
+    dumps.sort();
+    Ok(dumps.pop())
+}
+
+fn open(path: &Path) -> Result<Box<dyn Read>> {
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    Ok(if path.extension().is_some_and(|e| e == "gz") {
+        Box::new(GzDecoder::new(file))
+    } else {
+        Box::new(file)
+    })
+}
+
+/// Fields of one row of a MySQL dump, unescaped
+#[derive(Default)]
+struct Row {
+    data: Vec<u8>,
+    /// End of each field in `data`
+    ends: Vec<usize>,
+}
+
+impl Row {
+    fn len(&self) -> usize {
+        self.ends.len()
+    }
+
+    fn field(&self, i: usize) -> Option<&[u8]> {
+        let end = *self.ends.get(i)?;
+        let start = if i == 0 { 0 } else { self.ends[i - 1] };
+        Some(&self.data[start..end])
+    }
+
+    fn text(&self, i: usize) -> Option<&str> {
+        std::str::from_utf8(self.field(i)?).ok()
+    }
+
+    fn int(&self, i: usize) -> Option<u64> {
+        self.text(i)?.parse().ok()
+    }
+}
+
+/// Call `f` with each row of the `INSERT INTO ... VALUES (...),(...);` statements of a
+/// MySQL dump
+fn for_each_row(path: &Path, mut f: impl FnMut(&Row)) -> Result<()> {
+    let mut reader = BufReader::new(open(path)?);
+    let mut line = Vec::new();
+    let mut row = Row::default();
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            return Ok(());
+        }
+        if !line.starts_with(b"INSERT INTO ") {
+            continue;
+        }
+        let Some(start) = line.windows(8).position(|w| w == b" VALUES ") else {
+            continue;
+        };
+        parse_values(&line[start + 8..], &mut row, &mut f)
+            .with_context(|| format!("Malformed INSERT statement in {}", path.display()))?;
+    }
+}
+
+fn parse_values(values: &[u8], row: &mut Row, f: &mut impl FnMut(&Row)) -> Result<()> {
+    let mut bytes = values.iter().copied();
+    loop {
+        match bytes.next() {
+            Some(b'(') => {}
+            Some(b',') => continue,
+            Some(b';' | b'\r' | b'\n') | None => return Ok(()),
+            Some(c) => bail!("unexpected {:?} between rows", c as char),
+        }
+
+        row.data.clear();
+        row.ends.clear();
+        loop {
+            let mut next = bytes.next();
+            if next == Some(b'\'') {
+                loop {
+                    match bytes.next() {
+                        Some(b'\\') => {
+                            let c = bytes.next().context("unterminated string")?;
+                            row.data.push(match c {
+                                b'n' => b'\n',
+                                b'r' => b'\r',
+                                b't' => b'\t',
+                                b'0' => 0,
+                                b'Z' => 0x1a,
+                                c => c,
+                            });
+                        }
+                        Some(b'\'') => break,
+                        Some(c) => row.data.push(c),
+                        None => bail!("unterminated string"),
+                    }
+                }
+                next = bytes.next();
+            } else {
+                // Numbers and NULL
+                while let Some(c) = next.filter(|c| *c != b',' && *c != b')') {
+                    row.data.push(c);
+                    next = bytes.next();
+                }
+            }
+            row.ends.push(row.data.len());
+
+            match next {
+                Some(b',') => {}
+                Some(b')') => break,
+                _ => bail!("unterminated row"),
+            }
+        }
+        f(row);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ingest::importance_index::{ImportanceAggregation, ImportanceIndex};
+
+    fn write_gz(path: &Path, content: &str) {
+        let mut encoder = GzEncoder::new(File::create(path).unwrap(), Compression::default());
+        encoder.write_all(content.as_bytes()).unwrap();
+        encoder.finish().unwrap();
+    }
+
+    #[test]
+    fn test_compute_importance() {
+        let dumps = tempfile::tempdir().unwrap();
+        let dir = dumps.path();
+        write_gz(
+            &dir.join("wikidatawiki-20240601-wb_items_per_site.sql.gz"),
+            "-- MySQL dump\n\
+             INSERT INTO `wb_items_per_site` VALUES (1,72,'enwiki','Zürich'),(2,72,'dewiki','Zürich'),\
+             (3,70,'enwiki','Bern'),(4,80,'enwiki','Don\\'t Panic'),(5,90,'enwiki','Basel'),(6,90,'frwiki','Bâle');\n",
+        );
+        // Current schema for en, the one before 2024 for de
+        std::fs::write(
+            dir.join("enwiki-20240601-linktarget.sql"),
+            "INSERT INTO `linktarget` VALUES (1,0,'Zürich'),(2,0,'Bern'),(3,0,'Don\\'t_Panic'),(4,1,'Basel');\n",
+        )
+        .unwrap();
+        std::fs::write(
+            dir.join("enwiki-20240601-pagelinks.sql"),
+            "INSERT INTO `pagelinks` VALUES (10,0,1),(11,0,1),(12,0,1),(13,0,2);\n\
+             INSERT INTO `pagelinks` VALUES (14,0,3),(15,1,2),(16,0,4);\n",
+        )
+        .unwrap();
+        write_gz(
+            &dir.join("dewiki-20240601-pagelinks.sql.gz"),
+            "INSERT INTO `pagelinks` VALUES (1,0,'Zürich',0),(2,0,'Zürich',2);\n",
+        );
+
+        let pageviews = tempfile::tempdir().unwrap();
+        std::fs::write(
+            pageviews.path().join("pageviews-20240101-120000"),
+            "en Zürich 100 0\nen.m Bern 8 0\nen.b Bern 1000 0\nde Zürich 5 0\n",
+        )
+        .unwrap();
+        // Before --from
+        std::fs::write(
+            pageviews.path().join("pageviews-20231231-120000"),
+            "en Bern 100000 0\n",
+        )
+        .unwrap();
+
+        let output = dumps.path().join("importance.csv.gz");
+        run(&ComputeImportanceArgs {
+            dump_dir: dir.to_path_buf(),
+            languages: vec!["en".to_string(), "de".to_string()],
+            pageviews_dir: Some(pageviews.path().to_path_buf()),
+            from: NaiveDate::from_ymd_opt(2024, 1, 1),
+            until: None,
+            output: output.clone(),
+        })
+        .unwrap();
+
+        let importance =
+            ImportanceIndex::open(&output, dumps.path(), ImportanceAggregation::Max).unwrap();
+        // Most links and views
+        assert_eq!(importance.get("Q72"), Some(1.0));
+        // 1 of 3 links, 8 of 100 views
+        let bern = (2f64.ln() / 4f64.ln() + 9f64.ln() / 101f64.ln()) / 2.0;
+        assert!((importance.get("Q70").unwrap() - bern).abs() < 1e-6);
+        // Only its talk page is linked
+        assert!(importance.get("Q90").is_none());
+
+        let mut text = String::new();
+        GzDecoder::new(File::open(&output).unwrap())
+            .read_to_string(&mut text)
+            .unwrap();
+        assert!(text.contains("en\ta\tDon't_Panic\t"));
+        assert!(text.contains("de\ta\tZürich\t1\tQ72"));
+    }
+}
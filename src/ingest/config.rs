@@ -13,12 +13,18 @@ pub struct Config {
 pub struct GlobalConfig {
     pub es_url: String,
     pub tmp_dir: PathBuf,
+    /// Name languages to keep for all regions (default: all)
+    #[serde(default)]
+    pub languages: Option<Vec<String>>,
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct RegionConfig {
     pub name: String,
     pub url: String,
+    /// Name languages to keep for this region, overriding the global list
+    #[serde(default)]
+    pub languages: Option<Vec<String>>,
 }
 
 impl Config {
@@ -0,0 +1,221 @@
+//! Languages implied by the untagged `name` tag.
+//!
+//! `name` is stored as `default` without a language. The language follows from where the
+//! place is: "Genève" is French because it lies in the canton of Geneva. The table in
+//! `schema/default_languages.toml` maps country and subdivision codes to languages; the
+//! most specific admin area of a place with an entry decides.
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use anyhow::{Context, Result};
+use geo::{BoundingRect, Contains, MultiPolygon, Point, Rect};
+
+use crate::models::{AdminHierarchy, AdminLevel, OsmType, Place};
+use crate::pip::{representative_point, AdminBoundary};
+
+const BUILTIN_TABLE: &str = include_str!("../../schema/default_languages.toml");
+
+/// Separators of combined names in multilingual areas ("Biel/Bienne", "Bruxelles - Brussel")
+const SEPARATORS: [&str; 3] = [" - ", " / ", "/"];
+
+pub struct DefaultLanguages {
+    /// ISO country / subdivision code -> languages
+    table: HashMap<String, Vec<String>>,
+    /// Admin area OSM ID -> ISO code, for areas with an entry in the table
+    area_codes: HashMap<i64, String>,
+    /// OSM IDs of all registered admin areas, whose names already have their language
+    boundaries: HashSet<i64>,
+}
+
+impl DefaultLanguages {
+    /// Load the table from a file, or the built-in one
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let table = match path {
+            Some(path) => {
+                let content = std::fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                toml::from_str(&content)
+                    .with_context(|| format!("Failed to parse {}", path.display()))?
+            }
+            None => toml::from_str(BUILTIN_TABLE).context("Failed to parse default languages")?,
+        };
+        Ok(Self {
+            table,
+            area_codes: HashMap::new(),
+            boundaries: HashSet::new(),
+        })
+    }
+
+    /// Remember the codes of the admin areas and add the implied language to their names.
+    ///
+    /// Areas without a code of their own (municipalities, districts) take the language of
+    /// the smallest coded area containing them.
+    pub fn register_boundaries(&mut self, boundaries: &mut [AdminBoundary]) {
+        // Coded areas, most specific (highest admin level) first
+        let mut coded: Vec<(&str, &MultiPolygon<f64>, Rect<f64>, AdminLevel)> = boundaries
+            .iter()
+            .filter_map(|b| {
+                let code = b.area.iso_code().filter(|c| self.table.contains_key(*c))?;
+                Some((code, &b.geometry, b.geometry.bounding_rect()?, b.area.level))
+            })
+            .collect();
+        coded.sort_by_key(|&(_, _, _, level)| std::cmp::Reverse(level));
+
+        let languages: Vec<Option<String>> = boundaries
+            .iter()
+            .map(|b| {
+                b.area
+                    .iso_code()
+                    .filter(|c| self.table.contains_key(*c))
+                    .or_else(|| {
+                        let point = representative_point(&b.geometry)?;
+                        coded
+                            .iter()
+                            .find(|(_, geometry, rect, _)| {
+                                contains_point(rect, &point) && geometry.contains(&point)
+                            })
+                            .map(|&(code, ..)| code)
+                    })
+                    .map(str::to_string)
+            })
+            .collect();
+
+        for (boundary, code) in boundaries.iter_mut().zip(languages) {
+            self.boundaries.insert(boundary.area.osm_id);
+            let Some(code) = code else {
+                continue;
+            };
+            if let Some(languages) = self.table.get(&code) {
+                apply(languages, &mut boundary.area.name);
+            }
+            if boundary.area.iso_code() == Some(code.as_str()) {
+                self.area_codes.insert(boundary.area.osm_id, code);
+            }
+        }
+    }
+
+    /// Languages of the most specific area in `hierarchy` with an entry in the table
+    pub fn languages_for(&self, hierarchy: &AdminHierarchy) -> Option<&[String]> {
+        [
+            &hierarchy.neighbourhood,
+            &hierarchy.borough,
+            &hierarchy.locality,
+            &hierarchy.local_admin,
+            &hierarchy.county,
+            &hierarchy.macro_county,
+            &hierarchy.region,
+            &hierarchy.macro_region,
+            &hierarchy.country,
+        ]
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| self.area_codes.get(&entry.id?))
+        .find_map(|code| self.table.get(code))
+        .map(Vec::as_slice)
+    }
+
+    /// Add the implied language of the untagged name for a place in `hierarchy`
+    pub fn apply(&self, hierarchy: &AdminHierarchy, names: &mut HashMap<String, String>) {
+        if let Some(languages) = self.languages_for(hierarchy) {
+            apply(languages, names);
+        }
+    }
+
+    /// Add the implied language of the untagged name of a place. Admin areas are left
+    /// alone: [`Self::register_boundaries`] named them by their own code, not their parent's.
+    pub fn apply_place(&self, place: &mut Place) {
+        if place.osm_type == OsmType::Relation && self.boundaries.contains(&place.osm_id) {
+            return;
+        }
+        self.apply(&place.parent, &mut place.name);
+    }
+}
+
+fn contains_point(rect: &Rect<f64>, point: &Point<f64>) -> bool {
+    let (min, max) = (rect.min(), rect.max());
+    point.x() >= min.x && point.x() <= max.x && point.y() >= min.y && point.y() <= max.y
+}
+
+/// Store the `default` name under its implied language(s)
+fn apply(languages: &[String], names: &mut HashMap<String, String>) {
+    let Some(default) = names.get("default").cloned() else {
+        return;
+    };
+
+    if let [language] = languages {
+        names.entry(language.clone()).or_insert(default);
+        return;
+    }
+
+    // Already tagged in one of the area's languages, so it is not a combined name
+    if languages
+        .iter()
+        .any(|l| names.get(l).is_some_and(|n| *n == default))
+    {
+        return;
+    }
+
+    for separator in SEPARATORS {
+        let parts: Vec<&str> = default.split(separator).map(str::trim).collect();
+        if parts.len() == languages.len() && parts.iter().all(|p| !p.is_empty()) {
+            for (language, part) in languages.iter().zip(parts) {
+                names
+                    .entry(language.clone())
+                    .or_insert_with(|| part.to_string());
+            }
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+        pairs
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+    }
+
+    fn langs(list: &[&str]) -> Vec<String> {
+        list.iter().map(|l| l.to_string()).collect()
+    }
+
+    #[test]
+    fn test_apply() {
+        // Single language: fill in, never overwrite
+        let mut n = names(&[("default", "Genève"), ("de", "Genf")]);
+        apply(&langs(&["fr"]), &mut n);
+        assert_eq!(n["fr"], "Genève");
+        let mut n = names(&[("default", "Genève"), ("fr", "Genève (ville)")]);
+        apply(&langs(&["fr"]), &mut n);
+        assert_eq!(n["fr"], "Genève (ville)");
+
+        // Combined names in multilingual areas
+        let mut n = names(&[("default", "Biel/Bienne")]);
+        apply(&langs(&["de", "fr"]), &mut n);
+        assert_eq!((n["de"].as_str(), n["fr"].as_str()), ("Biel", "Bienne"));
+        let mut n = names(&[("default", "Bruxelles - Brussel")]);
+        apply(&langs(&["fr", "nl"]), &mut n);
+        assert_eq!(
+            (n["fr"].as_str(), n["nl"].as_str()),
+            ("Bruxelles", "Brussel")
+        );
+
+        // Anything else in a multilingual area is left alone
+        let mut n = names(&[("default", "Sion")]);
+        apply(&langs(&["fr", "de"]), &mut n);
+        assert_eq!(n.len(), 1);
+    }
+
+    #[test]
+    fn test_builtin_table() {
+        let defaults = DefaultLanguages::load(None).unwrap();
+        assert_eq!(defaults.table["CH-GE"], ["fr"]);
+        assert_eq!(defaults.table["CA-QC"], ["fr"]);
+        assert_eq!(defaults.table["BE-BRU"].len(), 2);
+    }
+}
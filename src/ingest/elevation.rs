@@ -0,0 +1,170 @@
+//! Elevation from SRTM / Copernicus `.hgt` tiles (`--dem-dir`).
+//!
+//! A tile covers one degree square and is named after its south-west corner
+//! (`N47E008.hgt`). It holds a square grid of big-endian 16-bit heights in metres,
+//! 1201 (3 arc seconds) or 3601 (1 arc second) samples a side, rows from north to south.
+//! Tiles are memory-mapped when first needed.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use anyhow::{bail, Result};
+use memmap2::Mmap;
+use tracing::warn;
+
+/// Marks samples without data (water, radar shadow)
+const VOID: i16 = -32768;
+
+struct Tile {
+    data: Mmap,
+    /// Samples per side
+    size: usize,
+}
+
+impl Tile {
+    fn open(path: &Path) -> Result<Self> {
+        let file = File::open(path)?;
+        // SAFETY: tiles are read-only input files that are not modified during an import
+        let data = unsafe { Mmap::map(&file)? };
+        let size = ((data.len() / 2) as f64).sqrt() as usize;
+        if size < 2 || size * size * 2 != data.len() {
+            bail!("{} is not a square grid of heights", path.display());
+        }
+        Ok(Self { data, size })
+    }
+
+    fn height(&self, row: usize, col: usize) -> Option<f64> {
+        let offset = (row * self.size + col) * 2;
+        let height = i16::from_be_bytes([self.data[offset], self.data[offset + 1]]);
+        (height != VOID).then_some(f64::from(height))
+    }
+
+    /// Height at a position within the tile (fractions of a degree east and south of its
+    /// north-west corner), interpolated between the four surrounding samples
+    fn sample(&self, east: f64, south: f64) -> Option<f64> {
+        let last = (self.size - 1) as f64;
+        let x = (east * last).clamp(0.0, last);
+        let y = (south * last).clamp(0.0, last);
+        let (col, row) = (x.floor() as usize, y.floor() as usize);
+        let (col1, row1) = ((col + 1).min(self.size - 1), (row + 1).min(self.size - 1));
+        let (dx, dy) = (x - col as f64, y - row as f64);
+
+        let corners = [
+            (self.height(row, col), (1.0 - dx) * (1.0 - dy)),
+            (self.height(row, col1), dx * (1.0 - dy)),
+            (self.height(row1, col), (1.0 - dx) * dy),
+            (self.height(row1, col1), dx * dy),
+        ];
+
+        // Void samples are left out, the others weighted up
+        let (sum, weight) = corners
+            .iter()
+            .filter_map(|(height, weight)| height.map(|h| (h * weight, *weight)))
+            .fold((0.0, 0.0), |(s, w), (h, weight)| (s + h, w + weight));
+        (weight > 0.0).then(|| sum / weight)
+    }
+}
+
+/// Opened tiles by south-west corner; `None` for tiles that are missing
+type TileCache = HashMap<(i32, i32), Option<Arc<Tile>>>;
+
+/// Directory of `.hgt` tiles
+pub struct ElevationTiles {
+    dir: PathBuf,
+    tiles: Mutex<TileCache>,
+}
+
+impl ElevationTiles {
+    pub fn new(dir: &Path) -> Result<Self> {
+        if !dir.is_dir() {
+            bail!("Elevation tile directory {} does not exist", dir.display());
+        }
+        Ok(Self {
+            dir: dir.to_path_buf(),
+            tiles: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Elevation in metres at a point, rounded to decimetres; `None` without a tile or
+    /// data there
+    pub fn elevation(&self, lon: f64, lat: f64) -> Option<f64> {
+        let (lon0, lat0) = (lon.floor() as i32, lat.floor() as i32);
+        let tile = self.tile(lon0, lat0)?;
+        let height = tile.sample(lon - f64::from(lon0), f64::from(lat0 + 1) - lat)?;
+        Some((height * 10.0).round() / 10.0)
+    }
+
+    fn tile(&self, lon: i32, lat: i32) -> Option<Arc<Tile>> {
+        let mut tiles = self.tiles.lock().unwrap_or_else(|e| e.into_inner());
+        tiles
+            .entry((lon, lat))
+            .or_insert_with(|| {
+                let name = tile_name(lon, lat);
+                let path = [name.clone(), name.to_lowercase()]
+                    .into_iter()
+                    .map(|name| self.dir.join(name))
+                    .find(|path| path.exists())?;
+                match Tile::open(&path) {
+                    Ok(tile) => Some(Arc::new(tile)),
+                    Err(e) => {
+                        warn!("Skipping elevation tile {}: {}", path.display(), e);
+                        None
+                    }
+                }
+            })
+            .clone()
+    }
+}
+
+/// `N47E008.hgt` for the tile whose south-west corner is at 8°E 47°N
+fn tile_name(lon: i32, lat: i32) -> String {
+    format!(
+        "{}{:02}{}{:03}.hgt",
+        if lat < 0 { 'S' } else { 'N' },
+        lat.unsigned_abs(),
+        if lon < 0 { 'W' } else { 'E' },
+        lon.unsigned_abs()
+    )
+}
+
+/// Elevation from an OSM `ele` tag ("1234", "1234 m", "1234.5m"); other units are not
+/// understood
+pub fn parse_ele(value: &str) -> Option<f64> {
+    let value = value.trim();
+    let number = value
+        .strip_suffix('m')
+        .map(str::trim_end)
+        .unwrap_or(value)
+        .replace(',', ".");
+    number.parse::<f64>().ok().filter(|e| e.is_finite())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_elevation() {
+        let dir = tempfile::tempdir().unwrap();
+        // 3x3 samples, north row first; one void sample
+        let heights: [i16; 9] = [400, 500, 600, 300, 400, 500, VOID, 300, 400];
+        let bytes: Vec<u8> = heights.iter().flat_map(|h| h.to_be_bytes()).collect();
+        std::fs::write(dir.path().join("N47E008.hgt"), bytes).unwrap();
+
+        let tiles = ElevationTiles::new(dir.path()).unwrap();
+        assert_eq!(tiles.elevation(8.5, 47.5), Some(400.0));
+        assert_eq!(tiles.elevation(8.0, 47.999_999), Some(400.0));
+        assert_eq!(tiles.elevation(8.75, 47.5), Some(450.0));
+        // Void samples are left out
+        assert_eq!(tiles.elevation(8.25, 47.25), Some(333.3));
+        assert_eq!(tiles.elevation(8.0, 47.0), None);
+        // No tile
+        assert_eq!(tiles.elevation(9.5, 47.5), None);
+
+        assert_eq!(tile_name(-1, -34), "S34W001.hgt");
+        assert_eq!(parse_ele("1234 m"), Some(1234.0));
+        assert_eq!(parse_ele("3000 ft"), None);
+    }
+}
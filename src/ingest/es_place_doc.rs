@@ -1,14 +1,18 @@
 use chrono::{DateTime, Utc};
 use serde::Serialize;
+use std::collections::BTreeSet;
 
-use cypress::elasticsearch::EsDocument;
-use cypress::models::{Address, AdminHierarchy, GeoBbox, GeoPoint, Layer, OsmType, Place};
+use crate::elasticsearch::EsDocument;
+use crate::models::{Address, AdminHierarchy, GeoBbox, GeoPoint, Layer, OsmType, Place};
+use crate::text::{cjk, housenumber, phonetic, street, transliterate};
 
 /// Normalized Place document for Elasticsearch (excludes `name` map)
 #[derive(Debug, Clone, Serialize)]
 pub struct EsPlaceDoc {
     pub source_id: String,
     pub source_file: String,
+    /// All source files containing this place, see `ScyllaClient::get_place_sources`
+    pub source_files: Vec<String>,
     pub import_timestamp: DateTime<Utc>,
     pub osm_type: OsmType,
     pub osm_id: i64,
@@ -21,10 +25,19 @@ pub struct EsPlaceDoc {
     pub categories: Vec<String>,
     // name field excluded
     pub name_all: String,
+    /// Latin transliterations of names in other scripts ("Москва" -> "Moskva")
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub name_transliterated: String,
+    /// Characters and character pairs of CJK names, Traditional folded to Simplified
+    #[serde(skip_serializing_if = "String::is_empty")]
+    pub name_cjk: String,
+    /// Phonetic keys of the name words, see `crate::text::phonetic`
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub name_phonetic: Vec<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub phrase: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub address: Option<Address>,
+    pub address: Option<EsAddress>,
     pub center_point: GeoPoint,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub bbox: Option<GeoBbox>,
@@ -42,6 +55,7 @@ impl From<&Place> for EsPlaceDoc {
         Self {
             source_id: place.source_id.clone(),
             source_file: place.source_file.clone(),
+            source_files: vec![place.source_file.clone()],
             import_timestamp: place.import_timestamp,
             osm_type: place.osm_type,
             osm_id: place.osm_id,
@@ -50,11 +64,80 @@ impl From<&Place> for EsPlaceDoc {
             layer: place.layer,
             categories: place.categories.clone(),
             name_all: place.name_all.clone(),
+            name_transliterated: transliterate::names(&place.name).join(" "),
+            name_cjk: cjk_ngrams(place),
+            name_phonetic: phonetic::names(&place.name),
             phrase: place.phrase.clone(),
-            address: place.address.clone(),
+            address: place.address.as_ref().map(|address| {
+                let country = place.parent.country.as_ref();
+                let languages =
+                    street::name_languages(&place.name, country.and_then(|c| c.alpha2.as_deref()));
+                EsAddress::new(address, &languages)
+            }),
             center_point: place.center_point,
             bbox: place.bbox.clone(),
             parent: place.parent.clone(),
         }
     }
 }
+
+fn cjk_ngrams(place: &Place) -> String {
+    let ngrams: BTreeSet<String> = place
+        .name
+        .values()
+        .filter_map(|name| cjk::ngrams(name))
+        .collect();
+    ngrams.into_iter().collect::<Vec<_>>().join(" ")
+}
+
+/// Address as indexed for matching
+#[derive(Debug, Clone, Serialize)]
+pub struct EsAddress {
+    /// Canonical house numbers, with lists and ranges expanded ("12-14" -> "12-14", "12", "14")
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub housenumber: Vec<String>,
+    /// Numeric parts of the house numbers, for ranking by distance
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub housenumber_number: Vec<u32>,
+    /// Street with the decompounded forms appended ("Bahnhofstr." -> "bahnhof strasse")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub street: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub postcode: Option<String>,
+    /// The postcode was inferred from nearby addresses, not tagged
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub postcode_inferred: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub city: Option<String>,
+}
+
+impl EsAddress {
+    /// Index an address whose street name is in one of `languages`
+    pub fn new(address: &Address, languages: &[&str]) -> Self {
+        let housenumbers = address
+            .housenumber
+            .as_deref()
+            .map(housenumber::expand)
+            .unwrap_or_default();
+
+        let mut housenumber_number: Vec<u32> =
+            housenumbers.iter().filter_map(|h| h.number).collect();
+        housenumber_number.sort_unstable();
+        housenumber_number.dedup();
+
+        let street = address.street.as_ref().map(|name| {
+            let mut forms = vec![name.clone()];
+            forms.extend(street::variants(name, languages));
+            forms.join(" ")
+        });
+
+        Self {
+            housenumber: housenumbers.into_iter().map(|h| h.canonical).collect(),
+            housenumber_number,
+            street,
+            postcode: address.postcode.clone(),
+            postcode_inferred: address.postcode_inferred,
+            city: address.city.clone(),
+        }
+    }
+}
@@ -1,66 +1,179 @@
 use anyhow::{Context, Result};
-use csv::ReaderBuilder;
-use flate2::read::GzDecoder;
+use osmpbfreader::{OsmId, OsmObj, Tags};
+use serde::Deserialize;
 use std::collections::HashMap;
-use std::fs::File;
-use std::io::Read;
 use std::path::Path;
 use tracing::info;
 
-/// Load importance scores from Wikimedia CSV/SQL dump
-pub fn load_importance(path: &Path) -> Result<HashMap<String, f64>> {
-    info!("Loading importance data from {}", path.display());
+use crate::input::OsmInput;
 
-    let file = File::open(path).context("Failed to open importance file")?;
-    let reader: Box<dyn Read> = if path.extension().map_or(false, |e| e == "gz") {
-        Box::new(GzDecoder::new(file))
-    } else {
-        Box::new(file)
-    };
+const BUILTIN_WEIGHTS: &str = include_str!("../../schema/importance_weights.toml");
+
+/// Population giving the full population weight
+const FULL_POPULATION: f64 = 10_000_000.0;
+
+/// Translations giving the full translation weight
+const FULL_TRANSLATIONS: f64 = 50.0;
+
+/// Routes giving the full route weight
+const FULL_ROUTES: f64 = 20.0;
+
+/// Weights of the OSM signals of importance, see `schema/importance_weights.toml`
+#[derive(Debug, Clone, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct ImportanceWeights {
+    pub population: f64,
+    pub wikipedia: f64,
+    pub capital: f64,
+    pub admin_level: f64,
+    pub road_class: f64,
+    pub translations: f64,
+    pub routes: f64,
+}
+
+impl ImportanceWeights {
+    /// Load the weights from a file, or the built-in ones
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        match path {
+            Some(path) => {
+                let content = std::fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                toml::from_str(&content)
+                    .with_context(|| format!("Failed to parse {}", path.display()))
+            }
+            None => toml::from_str(BUILTIN_WEIGHTS).context("Failed to parse importance weights"),
+        }
+    }
+}
+
+/// Importance of places from their OSM tags and the routes serving them
+pub struct OsmImportance {
+    weights: ImportanceWeights,
+    /// Route relations each stop or platform is a member of
+    routes: HashMap<OsmId, u32>,
+}
 
-    //schema
-
-    //language        type    title   importance      wikidata_id
-    //en      a       !!!     0.41523351747267634     Q371
-    //en      a       $4.50_Theater_Company   0.42547726432748295     Q56348256
-    //en      a       ¥$      0.40816634963515963     Q124493747
-    //en      a       01099   0.2869812649426343      Q106604851
-    //en      a       013     0.3807705141182751      Q2319846
-    //en      a       015B    0.354703308323595       Q12579912
-    //en      a       01.AI   0.31461790426364866     Q130692307
-    //en      a       01_Communique   0.07194870560319186     Q4545554
-    //en      a       01_Distribution 0.45833613469028417     Q1554656
-
-    let mut csv_reader = ReaderBuilder::new()
-        .has_headers(true)
-        .delimiter(b'\t')
-        .from_reader(reader);
-
-    let mut map = HashMap::new();
-    let headers = csv_reader.headers()?.clone();
-
-    // Find column indices
-    let importance_idx = headers
-        .iter()
-        .position(|h| h == "importance")
-        .context("Column 'importance' not found")?;
-    let wikidata_idx = headers
-        .iter()
-        .position(|h| h == "wikidata_id")
-        .context("Column 'wikidata_id' not found")?;
-
-    for result in csv_reader.records() {
-        let record = result?;
-        let wikidata_id = &record[wikidata_idx];
-        let importance_str = &record[importance_idx];
-
-        if let Ok(importance) = importance_str.parse::<f64>() {
-            map.insert(wikidata_id.to_string(), importance);
+impl OsmImportance {
+    /// Set up scoring; counts the routes of each stop in `input`, if given and routes have
+    /// a weight
+    pub fn new(weights: ImportanceWeights, input: Option<&OsmInput>) -> Result<Self> {
+        let routes = match input {
+            Some(input) if weights.routes > 0.0 => count_routes(input)?,
+            _ => HashMap::new(),
+        };
+        Ok(Self { weights, routes })
+    }
+
+    /// Importance of an object, between 0 and 1
+    pub fn score(&self, tags: &Tags, id: Option<OsmId>) -> f64 {
+        let w = &self.weights;
+        let routes = id.and_then(|id| self.routes.get(&id)).copied().unwrap_or(0);
+
+        let importance = calculate_default_importance(tags)
+            + w.population * population_signal(tags)
+            + w.wikipedia * wikipedia_signal(tags)
+            + w.capital * capital_signal(tags)
+            + w.admin_level * admin_level_signal(tags)
+            + w.road_class * road_class_signal(tags)
+            + w.translations * log_signal(translations(tags), FULL_TRANSLATIONS)
+            + w.routes * log_signal(f64::from(routes), FULL_ROUTES);
+        importance.clamp(0.0, 1.0)
+    }
+}
+
+/// Number of route relations each stop and platform is a member of
+fn count_routes(input: &OsmInput) -> Result<HashMap<OsmId, u32>> {
+    info!("Counting public transport routes per stop...");
+    let mut routes: HashMap<OsmId, u32> = HashMap::new();
+    for obj in input.objects()? {
+        let Ok(OsmObj::Relation(relation)) = obj else {
+            continue;
+        };
+        if !relation.tags.contains("type", "route") {
+            continue;
+        }
+
+        let mut stops: Vec<OsmId> = relation
+            .refs
+            .iter()
+            .filter(|r| r.role.starts_with("stop") || r.role.starts_with("platform"))
+            .map(|r| r.member)
+            .collect();
+        // A stop served twice by a route (both directions in one relation) counts once
+        stops.sort();
+        stops.dedup();
+        for stop in stops {
+            *routes.entry(stop).or_default() += 1;
         }
     }
+    info!("Found {} stops served by routes", routes.len());
+    Ok(routes)
+}
 
-    info!("Loaded {} importance scores", map.len());
-    Ok(map)
+/// `population` tag, log scale ("3,600,000" and "3 600 000" are understood)
+fn population_signal(tags: &Tags) -> f64 {
+    let Some(population) = tags.get("population") else {
+        return 0.0;
+    };
+    let digits: String = population
+        .trim()
+        .chars()
+        .take_while(|c| c.is_ascii_digit() || matches!(c, ',' | '.' | ' ' | '\''))
+        .filter(char::is_ascii_digit)
+        .collect();
+    match digits.parse::<f64>() {
+        Ok(population) if population >= 1.0 => {
+            (population.log10() / FULL_POPULATION.log10()).min(1.0)
+        }
+        _ => 0.0,
+    }
+}
+
+fn wikipedia_signal(tags: &Tags) -> f64 {
+    if tags.contains_key("wikipedia") || tags.contains_key("wikidata") {
+        1.0
+    } else {
+        0.0
+    }
+}
+
+fn capital_signal(tags: &Tags) -> f64 {
+    match tags.get("capital").map(|c| c.as_str()) {
+        Some("yes" | "2") => 1.0,
+        Some("3" | "4" | "5" | "6") => 0.5,
+        _ => 0.0,
+    }
+}
+
+fn admin_level_signal(tags: &Tags) -> f64 {
+    match tags.get("admin_level").and_then(|l| l.parse::<u8>().ok()) {
+        Some(level @ 2..=11) => f64::from(11 - level) / 9.0,
+        _ => 0.0,
+    }
+}
+
+fn road_class_signal(tags: &Tags) -> f64 {
+    match tags.get("highway").map(|h| h.as_str()) {
+        Some("motorway" | "trunk") => 1.0,
+        Some("primary") => 0.8,
+        Some("secondary") => 0.6,
+        Some("tertiary") => 0.4,
+        Some("residential" | "unclassified") => 0.2,
+        Some(
+            "service" | "living_street" | "pedestrian" | "road" | "track" | "path" | "footway"
+            | "cycleway",
+        ) => 0.1,
+        _ => 0.0,
+    }
+}
+
+fn translations(tags: &Tags) -> f64 {
+    tags.keys().filter(|k| k.starts_with("name:")).count() as f64
+}
+
+/// `count` on a log scale, 1 from `full` on
+fn log_signal(count: f64, full: f64) -> f64 {
+    (count.ln_1p() / full.ln_1p()).min(1.0)
 }
 
 /// Calculate default importance based on feature type (OSM tags)
@@ -140,3 +253,42 @@ pub fn calculate_default_importance(tags: &osmpbfreader::Tags) -> f64 {
     // place=house or amenity=* etc.
     0.01
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tags(pairs: &[(&str, &str)]) -> Tags {
+        let mut tags = Tags::new();
+        for (k, v) in pairs {
+            tags.insert((*k).into(), (*v).into());
+        }
+        tags
+    }
+
+    #[test]
+    fn test_score() {
+        let scorer = OsmImportance {
+            weights: ImportanceWeights::load(None).unwrap(),
+            routes: HashMap::from([(OsmId::Node(osmpbfreader::NodeId(1)), 12)]),
+        };
+
+        let city = tags(&[
+            ("place", "city"),
+            ("population", "3,600,000"),
+            ("capital", "yes"),
+            ("wikidata", "Q64"),
+        ]);
+        let town = tags(&[("place", "city"), ("population", "120000")]);
+        let hamlet = tags(&[("place", "hamlet")]);
+        assert!(scorer.score(&city, None) > scorer.score(&town, None));
+        assert!(scorer.score(&town, None) > scorer.score(&hamlet, None));
+        assert!(scorer.score(&city, None) <= 1.0);
+
+        // Stops served by many routes come first
+        let stop = tags(&[("highway", "bus_stop"), ("name", "Bahnhof")]);
+        let busy = OsmId::Node(osmpbfreader::NodeId(1));
+        let quiet = OsmId::Node(osmpbfreader::NodeId(2));
+        assert!(scorer.score(&stop, Some(busy)) > scorer.score(&stop, Some(quiet)));
+    }
+}
@@ -0,0 +1,313 @@
+//! Wikimedia importance by Wikidata item, as a memory-mapped table.
+//!
+//! `wikimedia-importance.csv` holds one row per Wikipedia article, tens of millions across
+//! languages. It is converted once into `<tmp-dir>/importance/<file>.<aggregation>.bin`:
+//! (item number, score) records sorted by item, the scores of an item's articles combined
+//! by an [`ImportanceAggregation`]. The table is rebuilt when the file changes, and single
+//! imports and all regions of a batch share it.
+
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use csv::ReaderBuilder;
+use flate2::read::GzDecoder;
+use memmap2::Mmap;
+use serde::{Deserialize, Serialize};
+use tracing::info;
+
+use crate::pip::file_fingerprint;
+
+/// How the scores of an item's articles in different languages are combined
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportanceAggregation {
+    /// Highest score of any language
+    #[default]
+    Max,
+    /// Average over the languages with an article
+    Mean,
+    /// Sum over the languages, capped at 1 (favours items known in many languages)
+    Sum,
+}
+
+impl ImportanceAggregation {
+    fn as_str(self) -> &'static str {
+        match self {
+            ImportanceAggregation::Max => "max",
+            ImportanceAggregation::Mean => "mean",
+            ImportanceAggregation::Sum => "sum",
+        }
+    }
+
+    fn combine(self, scores: &[Record]) -> f32 {
+        let sum: f64 = scores.iter().map(|r| f64::from(r.score)).sum();
+        match self {
+            ImportanceAggregation::Max => scores.iter().map(|r| r.score).fold(0.0, f32::max),
+            ImportanceAggregation::Mean => (sum / scores.len() as f64) as f32,
+            ImportanceAggregation::Sum => sum.min(1.0) as f32,
+        }
+    }
+}
+
+impl std::str::FromStr for ImportanceAggregation {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "max" => Ok(ImportanceAggregation::Max),
+            "mean" => Ok(ImportanceAggregation::Mean),
+            "sum" => Ok(ImportanceAggregation::Sum),
+            _ => Err(format!(
+                "unknown importance aggregation: {} (max|mean|sum)",
+                s
+            )),
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct Record {
+    /// Wikidata item number (64 for Q64)
+    item: u32,
+    score: f32,
+}
+
+/// Written next to a table once it is complete
+#[derive(Debug, Serialize, Deserialize)]
+struct IndexMeta {
+    /// [`file_fingerprint`] of the TSV the table was built from
+    fingerprint: String,
+    aggregation: ImportanceAggregation,
+    items: usize,
+}
+
+/// Sorted table of importance scores by Wikidata item
+pub struct ImportanceIndex {
+    /// `None` for a file without scores, which cannot be mapped
+    mmap: Option<Mmap>,
+}
+
+impl ImportanceIndex {
+    /// Open the table of an importance TSV, building it first when it is missing or the
+    /// TSV has changed
+    pub fn open(path: &Path, tmp_dir: &Path, aggregation: ImportanceAggregation) -> Result<Self> {
+        let dir = tmp_dir.join("importance");
+        std::fs::create_dir_all(&dir)
+            .with_context(|| format!("Failed to create {}", dir.display()))?;
+        let name = format!(
+            "{}.{}",
+            path.file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or("importance"),
+            aggregation.as_str()
+        );
+        let table = dir.join(format!("{}.bin", name));
+        let meta_path = dir.join(format!("{}.json", name));
+        let fingerprint = file_fingerprint(path)?;
+
+        let meta = std::fs::read_to_string(&meta_path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<IndexMeta>(&content).ok())
+            .filter(|meta| {
+                meta.fingerprint == fingerprint && meta.aggregation == aggregation && table.exists()
+            });
+        let items = match meta {
+            Some(meta) => {
+                info!(
+                    "Using importance table {} ({} items)",
+                    table.display(),
+                    meta.items
+                );
+                meta.items
+            }
+            None => {
+                let _ = std::fs::remove_file(&meta_path);
+                let items = build(path, &table, aggregation)?;
+                let tmp = meta_path.with_extension("json.tmp");
+                std::fs::write(
+                    &tmp,
+                    serde_json::to_string_pretty(&IndexMeta {
+                        fingerprint,
+                        aggregation,
+                        items,
+                    })?,
+                )?;
+                std::fs::rename(&tmp, &meta_path)?;
+                items
+            }
+        };
+
+        if items == 0 {
+            return Ok(Self { mmap: None });
+        }
+        let file =
+            File::open(&table).with_context(|| format!("Failed to open {}", table.display()))?;
+        // SAFETY: the table is only replaced by renaming a new file over it
+        let mmap = unsafe { Mmap::map(&file)? };
+        Ok(Self { mmap: Some(mmap) })
+    }
+
+    /// Score of a Wikidata item, e.g. "Q64"
+    pub fn get(&self, qid: &str) -> Option<f64> {
+        let item: u32 = qid.strip_prefix('Q')?.parse().ok()?;
+        let records = self.records();
+        let i = records.binary_search_by_key(&item, |r| r.item).ok()?;
+        Some(f64::from(records[i].score))
+    }
+
+    fn records(&self) -> &[Record] {
+        let Some(mmap) = &self.mmap else {
+            return &[];
+        };
+        // Safety: mmaps are page aligned and the table is written as whole records
+        unsafe {
+            std::slice::from_raw_parts(
+                mmap.as_ptr() as *const Record,
+                mmap.len() / std::mem::size_of::<Record>(),
+            )
+        }
+    }
+}
+
+/// Convert an importance TSV (columns `importance` and `wikidata_id`, optionally gzipped)
+/// into a table; returns the number of items
+fn build(path: &Path, table: &Path, aggregation: ImportanceAggregation) -> Result<usize> {
+    info!(
+        "Building importance table from {} ({} across languages)",
+        path.display(),
+        aggregation.as_str()
+    );
+
+    let file = File::open(path).context("Failed to open importance file")?;
+    let reader: Box<dyn Read> = if path.extension().is_some_and(|e| e == "gz") {
+        Box::new(GzDecoder::new(file))
+    } else {
+        Box::new(file)
+    };
+
+    //schema
+
+    //language        type    title   importance      wikidata_id
+    //en      a       !!!     0.41523351747267634     Q371
+    //en      a       $4.50_Theater_Company   0.42547726432748295     Q56348256
+    //en      a       ¥$      0.40816634963515963     Q124493747
+
+    let mut csv_reader = ReaderBuilder::new()
+        .has_headers(true)
+        .delimiter(b'\t')
+        .from_reader(reader);
+    let headers = csv_reader.headers()?.clone();
+
+    // Find column indices
+    let importance_idx = headers
+        .iter()
+        .position(|h| h == "importance")
+        .context("Column 'importance' not found")?;
+    let wikidata_idx = headers
+        .iter()
+        .position(|h| h == "wikidata_id")
+        .context("Column 'wikidata_id' not found")?;
+
+    // 8 bytes per article instead of an owned string each
+    let mut articles: Vec<Record> = Vec::new();
+    for result in csv_reader.records() {
+        let record = result?;
+        let item = record[wikidata_idx]
+            .strip_prefix('Q')
+            .and_then(|n| n.parse::<u32>().ok());
+        let score = record[importance_idx].parse::<f32>().ok();
+        if let (Some(item), Some(score)) = (item, score) {
+            articles.push(Record { item, score });
+        }
+    }
+    info!("Read {} articles", articles.len());
+    articles.sort_unstable_by_key(|r| r.item);
+
+    let tmp = table.with_extension("bin.tmp");
+    let mut writer = BufWriter::new(
+        File::create(&tmp).with_context(|| format!("Failed to create {}", tmp.display()))?,
+    );
+    let mut items = 0;
+    let mut start = 0;
+    while start < articles.len() {
+        let item = articles[start].item;
+        let end = start
+            + articles[start..]
+                .iter()
+                .take_while(|r| r.item == item)
+                .count();
+        let score = aggregation.combine(&articles[start..end]);
+        writer.write_all(&item.to_ne_bytes())?;
+        writer.write_all(&score.to_ne_bytes())?;
+        items += 1;
+        start = end;
+    }
+    writer.flush()?;
+    drop(writer);
+    std::fs::rename(&tmp, table)?;
+
+    info!("Built importance table of {} items", items);
+    Ok(items)
+}
+
+/// Path of the importance TSV: `--importance-file`, or `wikimedia-importance.csv` in the
+/// working directory if there is one
+pub fn importance_file(arg: Option<&Path>) -> Option<PathBuf> {
+    match arg {
+        Some(path) => Some(path.to_path_buf()),
+        None => {
+            let path = PathBuf::from("wikimedia-importance.csv");
+            path.exists().then_some(path)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_index() {
+        let dir = tempfile::tempdir().unwrap();
+        let tsv = dir.path().join("wikimedia-importance.csv");
+        std::fs::write(
+            &tsv,
+            "language\ttype\ttitle\timportance\twikidata_id\n\
+             en\ta\tZürich\t0.5\tQ72\n\
+             de\ta\tZürich\t0.7\tQ72\n\
+             en\ta\tBern\t0.2\tQ70\n\
+             fr\ta\tBâle\t0.1\tQ78\n\
+             de\ta\tBasel\t0.8\tQ78\n\
+             en\ta\tBroken\tx\tQ1\n",
+        )
+        .unwrap();
+
+        let score =
+            |index: &ImportanceIndex, qid: &str| index.get(qid).map(|s| (s * 1e6).round() / 1e6);
+
+        let max = ImportanceIndex::open(&tsv, dir.path(), ImportanceAggregation::Max).unwrap();
+        assert_eq!(max.records().len(), 3);
+        assert_eq!(score(&max, "Q72"), Some(0.7));
+        assert_eq!(score(&max, "Q70"), Some(0.2));
+        assert_eq!(score(&max, "Q1"), None);
+        assert_eq!(score(&max, "Q2"), None);
+        assert_eq!(score(&max, "nope"), None);
+
+        let mean = ImportanceIndex::open(&tsv, dir.path(), ImportanceAggregation::Mean).unwrap();
+        assert_eq!(score(&mean, "Q72"), Some(0.6));
+        let sum = ImportanceIndex::open(&tsv, dir.path(), ImportanceAggregation::Sum).unwrap();
+        assert_eq!(score(&sum, "Q78"), Some(0.9));
+        assert_eq!(score(&sum, "Q72"), Some(1.0));
+
+        // Reopened from the table
+        let again = ImportanceIndex::open(&tsv, dir.path(), ImportanceAggregation::Max).unwrap();
+        assert!(dir
+            .path()
+            .join("importance/wikimedia-importance.csv.max.json")
+            .exists());
+        assert_eq!(score(&again, "Q78"), Some(0.8));
+    }
+}
@@ -0,0 +1,64 @@
+//! Limiting which language variants of names are kept (`--languages`).
+
+use std::collections::{HashMap, HashSet};
+
+/// Set of languages whose `name:*` variants are kept.
+///
+/// Keys without a language (`default`, `alt_name`, `old_name`, ...) are always kept.
+/// Languages match on their primary subtag, so `zh` keeps `zh-Hans` and `zh_pinyin`.
+#[derive(Debug, Clone)]
+pub struct LanguageFilter {
+    languages: HashSet<String>,
+}
+
+impl LanguageFilter {
+    /// Build a filter from a language list; an empty list keeps everything
+    pub fn new(languages: &[String]) -> Option<Self> {
+        let languages: HashSet<String> = languages
+            .iter()
+            .map(|l| l.trim().to_lowercase())
+            .filter(|l| !l.is_empty())
+            .collect();
+
+        (!languages.is_empty()).then_some(Self { languages })
+    }
+
+    /// Whether a name key (`de`, `alt_name:fr`, `default`, ...) is kept
+    pub fn keeps(&self, key: &str) -> bool {
+        let lang = match key.split_once(':') {
+            Some((_, lang)) => lang,
+            None if key == "default" || key.contains("_name") => return true,
+            None => key,
+        };
+
+        let lang = lang.to_lowercase();
+        let primary = lang.split(['-', '_']).next().unwrap_or(&lang);
+        self.languages.contains(&lang) || self.languages.contains(primary)
+    }
+
+    /// Drop name variants in languages that are not kept
+    pub fn apply(&self, names: &mut HashMap<String, String>) {
+        names.retain(|key, _| self.keeps(key));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keeps() {
+        let filter = LanguageFilter::new(&["de".to_string(), "FR".to_string()]).unwrap();
+
+        assert!(filter.keeps("default"));
+        assert!(filter.keeps("alt_name"));
+        assert!(filter.keeps("de"));
+        assert!(filter.keeps("fr"));
+        assert!(filter.keeps("de-CH"));
+        assert!(filter.keeps("alt_name:de"));
+        assert!(!filter.keeps("it"));
+        assert!(!filter.keeps("official_name:ru"));
+
+        assert!(LanguageFilter::new(&[]).is_none());
+    }
+}
@@ -0,0 +1,1934 @@
+//! OSM PBF ingest pipeline.
+//!
+//! Parses OSM data, extracts places, performs PIP lookups,
+//! and indexes into Elasticsearch. The `ingest` binary is a thin CLI over [`run`] and
+//! [`run_batch`]; call them directly to import with custom [`TransformerChain`]s.
+
+mod batch;
+mod checkpoint;
+mod clip;
+mod compute_importance;
+mod config;
+mod default_languages;
+mod elevation;
+mod es_place_doc;
+mod importance;
+mod importance_index;
+mod languages;
+mod ownership;
+mod postcodes;
+mod streets;
+mod synonyms;
+mod version;
+mod way_merger;
+
+pub use self::batch::run_batch;
+pub use self::compute_importance::{run as compute_importance, ComputeImportanceArgs};
+use self::es_place_doc::EsPlaceDoc;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use clap::Parser;
+use geo::BoundingRect;
+use indicatif::{ProgressBar, ProgressStyle};
+use tokio::sync::mpsc;
+use tracing::{error, info, warn};
+
+use crate::discord::DiscordWebhook;
+use crate::elasticsearch::{create_index, BulkIndexer, EsClient};
+use crate::input::OsmInput;
+use crate::models::normalized::NormalizedPlace;
+use crate::models::{Address, AdminLevel, GeoBbox, GeoPoint, Layer, OsmType, Place};
+use crate::pip::{
+    extract_admin_boundaries, file_fingerprint, load_boundaries, multipolygon_bbox,
+    representative_point, save_boundaries, AdminSpatialIndex, GeometryResolver, NodeIndexMode,
+    PipService, StoreMode, StoreOptions, TimezoneIndex,
+};
+use crate::scylla::ScyllaClient;
+use crate::transform::TransformerChain;
+use crate::wikidata::WikidataFetcher;
+
+use self::checkpoint::{Checkpoint, CheckpointTracker, Progress};
+use self::clip::{Bbox, ClipArea};
+use self::default_languages::DefaultLanguages;
+use self::elevation::{parse_ele, ElevationTiles};
+use self::importance::{ImportanceWeights, OsmImportance};
+use self::importance_index::{importance_file, ImportanceAggregation, ImportanceIndex};
+use self::languages::LanguageFilter;
+use self::postcodes::{locality_key, PostcodeIndex};
+use self::streets::{StreetIndex, StreetIndexBuilder, UnmatchedStreets};
+use self::synonyms::SynonymService;
+use self::way_merger::{way_line, MergedRoad, WayMerger};
+
+#[derive(Parser, Debug, Clone)]
+pub struct Args {
+    /// OSM file to import: .osm.pbf, .osm / .osm.gz (XML) or .opl (required for single mode, ignored in batch mode)
+    #[arg(short, long)]
+    pub file: Option<PathBuf>,
+
+    /// Optional pre-filtered admin boundary file
+    #[arg(long)]
+    pub admin_file: Option<PathBuf>,
+
+    /// Elasticsearch URL
+    #[arg(long, default_value = "http://localhost:9200")]
+    pub es_url: String,
+
+    /// Elasticsearch index name
+    #[arg(long, default_value = "places")]
+    pub index: String,
+
+    /// ScyllaDB URL
+    #[arg(long, default_value = "127.0.0.1")]
+    pub scylla_url: String,
+
+    /// Fetch supplemental labels from Wikidata
+    #[arg(long)]
+    pub wikidata: bool,
+
+    /// Delete places of this file that the import did not see (previous import's leftovers)
+    #[arg(long)]
+    pub refresh: bool,
+
+    /// Create/recreate index before import
+    #[arg(long)]
+    pub create_index: bool,
+
+    /// Rewrite every place, even when its content is unchanged since the last import
+    #[arg(long)]
+    pub force_rewrite: bool,
+
+    /// Batch size for bulk indexing
+    #[arg(long, default_value = "500")]
+    pub batch_size: usize,
+
+    /// Path to wikimedia-importance.csv (optional)
+    #[arg(long)]
+    pub importance_file: Option<PathBuf>,
+
+    /// How the importance of an item's articles in different languages is combined: "max", "mean" or "sum" (capped at 1)
+    #[arg(long, default_value = "max")]
+    pub importance_aggregation: ImportanceAggregation,
+
+    /// TOML file of the weights of OSM tags in importance (default: built-in schema/importance_weights.toml)
+    #[arg(long)]
+    pub importance_weights: Option<PathBuf>,
+
+    /// Count the public transport routes serving each stop for its importance (takes an extra pass over the input)
+    #[arg(long)]
+    pub count_routes: bool,
+
+    /// Discord webhook URL for notifications (optional)
+    #[arg(long)]
+    pub discord_webhook: Option<String>,
+
+    /// Merge adjacent road ways with the same name to reduce disk space
+    #[arg(long, default_value = "true")]
+    pub merge_roads: bool,
+
+    /// Give venues and streets without addr:postcode the postcode of nearby addresses (takes an extra pass over the input)
+    #[arg(long)]
+    pub infer_postcodes: bool,
+
+    /// timezone-boundary-builder GeoJSON (combined.geojson) to give every place its time zone
+    #[arg(long)]
+    pub timezones: Option<PathBuf>,
+
+    /// Directory of SRTM/Copernicus .hgt tiles to sample the elevation of places without an ele tag
+    #[arg(long)]
+    pub dem_dir: Option<PathBuf>,
+
+    /// Link addresses to the street named in addr:street and report those without one (takes an extra pass over the input)
+    #[arg(long)]
+    pub link_streets: bool,
+
+    /// Force fresh download of PBF files even if they exist
+    #[arg(long)]
+    pub force_download: bool,
+
+    /// Geometry store: "memory", or "disk" for memory-mapped files in tmp_dir (planet-scale imports)
+    #[arg(long, default_value = "memory")]
+    pub geometry_store: StoreMode,
+
+    /// Node coordinate index: "sparse" (sorted table) or "dense" (indexed by node ID)
+    #[arg(long, default_value = "sparse")]
+    pub node_index: NodeIndexMode,
+
+    /// Directory for on-disk ingest data (overridden by tmp_dir in batch mode)
+    #[arg(long, default_value = "./data")]
+    pub tmp_dir: PathBuf,
+
+    /// Record progress in tmp_dir so an interrupted import can be resumed (uses the disk geometry store)
+    #[arg(long)]
+    pub checkpoint: bool,
+
+    /// Resume from the checkpoint in tmp_dir if there is one (implies --checkpoint; batch mode resumes regions with a checkpoint)
+    #[arg(long)]
+    pub resume: bool,
+
+    /// Only import places inside this box: minLon,minLat,maxLon,maxLat
+    #[arg(long, allow_hyphen_values = true)]
+    pub bbox: Option<Bbox>,
+
+    /// Only import places inside the polygons of this GeoJSON file
+    #[arg(long)]
+    pub clip_polygon: Option<PathBuf>,
+
+    /// Only keep name variants in these languages, e.g. de,fr,it,en (default: all; overridden by config in batch mode)
+    #[arg(long, value_delimiter = ',')]
+    pub languages: Vec<String>,
+
+    /// TOML table of the languages implied by the untagged `name` per country/subdivision code (default: built-in schema/default_languages.toml)
+    #[arg(long)]
+    pub default_languages: Option<PathBuf>,
+}
+
+impl Args {
+    fn checkpointing(&self) -> bool {
+        self.checkpoint || self.resume
+    }
+}
+
+/// Objects between checkpoint markers in the main loop
+const CHECKPOINT_INTERVAL: u64 = 100_000;
+
+/// Merged roads between checkpoint markers
+const ROAD_CHECKPOINT_INTERVAL: usize = 10_000;
+
+/// Message from the main loop to the processing pipeline
+enum PipelineMessage {
+    Place(Box<Place>),
+    /// Every place before this message has been produced
+    Progress(Progress),
+}
+
+pub async fn run_reset(es_url: &str) -> Result<()> {
+    use self::version::VersionManager;
+
+    info!("Connecting to Elasticsearch at {}...", es_url);
+    let version_manager = VersionManager::new(es_url).await?;
+
+    info!("Resetting version history...");
+    version_manager.reset().await?;
+
+    info!("Version history reset complete.");
+    Ok(())
+}
+
+pub async fn run_wipe_source(
+    pattern: &str,
+    es_url: &str,
+    index: &str,
+    scylla_url: &str,
+) -> Result<()> {
+    let es_client = EsClient::new(es_url, index)
+        .await
+        .context("Failed to connect to Elasticsearch")?;
+    let scylla_client = ScyllaClient::new(scylla_url).await?;
+
+    info!("Removing places of source files matching '{}'...", pattern);
+    ownership::release_matching(&es_client, &scylla_client, pattern).await?;
+    Ok(())
+}
+
+/// Load the name synonyms from `schema/synonyms`
+fn load_synonyms() -> Result<SynonymService> {
+    let mut synonym_service = SynonymService::new();
+    // Try to load from standard locations
+    if Path::new("schema/synonyms").exists() {
+        synonym_service.load_from_dir("schema/synonyms")?;
+    } else if Path::new("../schema/synonyms").exists() {
+        synonym_service.load_from_dir("../schema/synonyms")?;
+    } else {
+        warn!("Could not find schema/synonyms directory. Running without synonyms.");
+    }
+    Ok(synonym_service)
+}
+
+/// Import a single OSM file, passing every place through `transformers` (see
+/// [`crate::transform`]) before it is written
+pub async fn run(args: Args, transformers: TransformerChain) -> Result<()> {
+    run_single(args, Arc::new(load_synonyms()?), Arc::new(transformers)).await
+}
+
+async fn run_single(
+    args: Args,
+    synonyms: Arc<SynonymService>,
+    transformers: Arc<TransformerChain>,
+) -> Result<()> {
+    let file_path = args
+        .file
+        .clone()
+        .ok_or_else(|| anyhow::anyhow!("Input file is required for single import"))?;
+
+    info!("Cypress Ingest Pipeline");
+    info!("File: {}", file_path.display());
+
+    // Connect to Elasticsearch
+    let es_client = EsClient::new(&args.es_url, &args.index)
+        .await
+        .context("Failed to connect to Elasticsearch")?;
+
+    if !es_client.health_check().await? {
+        anyhow::bail!("Elasticsearch cluster is not healthy");
+    }
+    info!("Connected to Elasticsearch");
+
+    // Connect to ScyllaDB
+    let scylla_client = ScyllaClient::new(&args.scylla_url).await?;
+    info!("Connected to ScyllaDB");
+    let scylla_client = Arc::new(scylla_client);
+
+    // Initialize Discord webhook
+    let discord = args
+        .discord_webhook
+        .as_ref()
+        .map(|url| DiscordWebhook::new(url.clone()));
+
+    // Get source file name for tracking
+    let source_file = file_path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("unknown.osm.pbf")
+        .to_string();
+
+    if let Some(ref dw) = discord {
+        let _ = dw
+            .send_notification(
+                "Ingestion Started",
+                &format!("Starting ingestion for: **{}**", source_file),
+                true,
+            )
+            .await;
+    }
+
+    let clip_area = ClipArea::from_args(&args)?;
+    let languages = LanguageFilter::new(&args.languages);
+    if !args.languages.is_empty() {
+        info!("Keeping names in languages: {}", args.languages.join(","));
+    }
+
+    // Pick up where an interrupted import left off
+    let mut checkpoint = if args.checkpointing() {
+        let fingerprint = file_fingerprint(&file_path)?;
+        let resumed = if args.resume {
+            Checkpoint::load(&args.tmp_dir, &source_file, &fingerprint)?
+        } else {
+            None
+        };
+        Some(match resumed {
+            Some(c) => {
+                info!(
+                    "Resuming from checkpoint: {} objects and {} merged roads already imported",
+                    c.objects_done, c.roads_done
+                );
+                c
+            }
+            None => {
+                Checkpoint::remove(&args.tmp_dir, &source_file)?;
+                Checkpoint::new(&source_file, fingerprint, Utc::now())
+            }
+        })
+    } else {
+        None
+    };
+    let resuming = checkpoint.as_ref().is_some_and(|c| c.admins_extracted);
+
+    // Create index if requested (never while resuming, that would drop the imported part)
+    if args.create_index && !resuming {
+        create_index(&es_client, true).await?;
+    }
+
+    let import_start = checkpoint
+        .as_ref()
+        .map(|c| c.import_start)
+        .unwrap_or_else(Utc::now);
+
+    // A new index starts empty, so unchanged places have to be written as well
+    let skip_unchanged = !args.force_rewrite && !args.create_index;
+    if !skip_unchanged {
+        info!("Writing all places, including unchanged ones");
+    }
+
+    // Load importance data
+    let importance_index = match importance_file(args.importance_file.as_deref()) {
+        Some(path) => Some(ImportanceIndex::open(
+            &path,
+            &args.tmp_dir,
+            args.importance_aggregation,
+        )?),
+        None => {
+            warn!("No importance file found. Ranking by importance from OSM tags only.");
+            None
+        }
+    };
+
+    // Open input file(s)
+    let input = OsmInput::open(&file_path)?;
+    let admin_input = args.admin_file.as_ref().map(OsmInput::open).transpose()?;
+
+    // Build GeometryResolver(s)
+    let (admin_resolver, _place_resolver_source) = if let Some(admin_input) = &admin_input {
+        let admin_path = admin_input.path();
+        info!(
+            "Building admin geometry index from: {}",
+            admin_path.display()
+        );
+        let resolver = GeometryResolver::open_or_build(
+            admin_input,
+            |tags| {
+                tags.contains("boundary", "administrative")
+                    && tags.contains_key("admin_level")
+                    && matches!(
+                        tags.get("type").map(|v| v.as_str()),
+                        Some("boundary") | Some("multipolygon")
+                    )
+            },
+            &store_options(&args, admin_path, "admin")?,
+        )?;
+        (resolver, None)
+    } else {
+        // Use main file for both
+        info!("Building geometry index from main file...");
+        let resolver = GeometryResolver::open_or_build(
+            &input,
+            // Roads are merged from their ways, so their geometry is needed as well
+            |tags| determine_layer(tags).is_some() || is_road_way(tags),
+            &store_options(&args, &file_path, "places")?,
+        )?;
+        (resolver, Some(&file_path))
+    };
+
+    if let Some(ref dw) = discord {
+        let _ = dw
+            .send_notification(
+                "Geometry Index Built",
+                &format!("Geometry index building complete for: **{}**", source_file),
+                true,
+            )
+            .await;
+    }
+
+    let mut default_languages = DefaultLanguages::load(args.default_languages.as_deref())?;
+    let enrichers = Enrichers {
+        timezones: args
+            .timezones
+            .as_deref()
+            .map(TimezoneIndex::load)
+            .transpose()?,
+        elevation: args
+            .dem_dir
+            .as_deref()
+            .map(ElevationTiles::new)
+            .transpose()?,
+    };
+
+    // Extract admin boundaries using admin_resolver
+    // Create spatial index immediately to avoid holding Vec<AdminBoundary>
+    let spatial_index = {
+        let boundary_input = admin_input.as_ref().unwrap_or(&input);
+        info!(
+            "Extracting admin boundaries from: {}",
+            boundary_input.path().display()
+        );
+        let mut boundaries = match checkpoint.as_mut() {
+            Some(c) if c.admins_extracted => {
+                info!("Loading admin boundaries from checkpoint");
+                load_boundaries(&Checkpoint::admins_path(&args.tmp_dir, &source_file))?
+            }
+            c => {
+                let boundaries = extract_admin_boundaries(boundary_input, &admin_resolver)?;
+                if let Some(c) = c {
+                    save_boundaries(
+                        &Checkpoint::admins_path(&args.tmp_dir, &source_file),
+                        &boundaries,
+                    )?;
+                    c.admins_extracted = true;
+                    c.save(&args.tmp_dir)?;
+                }
+                boundaries
+            }
+        };
+
+        default_languages.register_boundaries(&mut boundaries);
+
+        if let Some(ref timezones) = enrichers.timezones {
+            for boundary in boundaries.iter_mut() {
+                boundary.area.timezone = representative_point(&boundary.geometry)
+                    .and_then(|p| timezones.lookup(p.x(), p.y()))
+                    .map(str::to_string);
+            }
+        }
+
+        if let Some(ref filter) = languages {
+            for boundary in boundaries.iter_mut() {
+                filter.apply(&mut boundary.area.name);
+            }
+        }
+
+        // Keep every boundary touching the clip area so hierarchies near its edge stay complete
+        if let Some(ref clip) = clip_area {
+            let before = boundaries.len();
+            boundaries.retain(|b| clip.intersects(&b.geometry));
+            info!(
+                "Kept {} of {} admin boundaries intersecting the clip area",
+                boundaries.len(),
+                before
+            );
+        }
+
+        if let Some(ref dw) = discord {
+            let _ = dw
+                .send_notification(
+                    "Admin Boundaries Extracted",
+                    &format!(
+                        "Extracted **{}** admin boundaries for: **{}**",
+                        boundaries.len(),
+                        source_file
+                    ),
+                    true,
+                )
+                .await;
+        }
+
+        AdminSpatialIndex::build(boundaries)
+    };
+
+    let pip_service = Arc::new(PipService::new(spatial_index));
+    let spatial_index_ref = pip_service.index(); // Access underlying index
+
+    info!(
+        "PIP service ready with {} boundaries",
+        spatial_index_ref.len()
+    );
+
+    // Initialize Wikidata fetcher if enabled
+    let wikidata = if args.wikidata {
+        Some(WikidataFetcher::new())
+    } else {
+        None
+    };
+
+    // Prepare resolver for places
+    let place_resolver = if args.admin_file.is_some() {
+        info!("Building place geometry index from main file...");
+        Arc::new(GeometryResolver::open_or_build(
+            &input,
+            // Roads are merged from their ways, so their geometry is needed as well
+            |tags| determine_layer(tags).is_some() || is_road_way(tags),
+            &store_options(&args, &file_path, "places")?,
+        )?)
+    } else {
+        Arc::new(admin_resolver)
+    };
+
+    let postcode_index = if args.infer_postcodes {
+        Some(build_postcode_index(
+            &input,
+            &source_file,
+            &place_resolver,
+            &pip_service,
+        )?)
+    } else {
+        None
+    };
+
+    let mut way_merger = if args.merge_roads {
+        Some(WayMerger::new(Arc::clone(&place_resolver)))
+    } else {
+        None
+    };
+
+    // Streets have to be known before the addresses on them, so linking collects (and
+    // merges) them in an extra pass
+    let mut merged_roads = None;
+    let street_index = if args.link_streets {
+        let (index, roads) = build_street_index(
+            &input,
+            &source_file,
+            &place_resolver,
+            &pip_service,
+            &clip_area,
+            way_merger.take(),
+        )?;
+        merged_roads = roads;
+        Some(index)
+    } else {
+        None
+    };
+    let unmatched_streets = street_index
+        .as_ref()
+        .map(|_| UnmatchedStreets::create(&args.tmp_dir, &source_file, resuming))
+        .transpose()?;
+    let mut stages = PlaceStages {
+        pip: &pip_service,
+        postcodes: postcode_index.as_ref(),
+        streets: street_index.as_ref(),
+        unmatched_streets,
+        enrichers: &enrichers,
+        inferred_postcodes: 0,
+    };
+
+    // Importance from OSM tags, where the importance file has no score
+    let osm_importance = OsmImportance::new(
+        ImportanceWeights::load(args.importance_weights.as_deref())?,
+        args.count_routes.then_some(&input),
+    )?;
+
+    // Re-open file for place extraction (count first)
+    // Note: Counting is expensive on large files, maybe skip?
+    // User code had it, we'll keep it but it adds a pass.
+    let total_count = match checkpoint.as_ref().and_then(|c| c.total_count) {
+        Some(count) => count,
+        None => {
+            info!("Counting objects...");
+            let mut total_count = 0u64;
+            for obj in input.objects()? {
+                if obj.is_ok() {
+                    total_count += 1;
+                }
+            }
+            if let Some(c) = checkpoint.as_mut() {
+                c.total_count = Some(total_count);
+                c.save(&args.tmp_dir)?;
+            }
+            total_count
+        }
+    };
+    info!("Total OSM objects: {}", total_count);
+
+    // Create progress bar
+    let pb = ProgressBar::new(total_count);
+    pb.set_style(
+        ProgressStyle::default_bar()
+            .template(
+                "{spinner:.green} [{elapsed_precise}] [{bar:40.cyan/blue}] {pos}/{len} ({per_sec})",
+            )?
+            .progress_chars("#>-"),
+    );
+
+    // Create bulk indexer (starts background task)
+    let indexer = BulkIndexer::new(es_client.clone(), args.batch_size);
+
+    // Progress already covered by the checkpoint
+    let checkpointing = checkpoint.is_some();
+    let (admins_indexed, objects_done, roads_done) = checkpoint
+        .as_ref()
+        .map(|c| (c.admins_indexed, c.objects_done, c.roads_done))
+        .unwrap_or_default();
+    let tracker = checkpoint
+        .map(|c| CheckpointTracker::new(c, args.tmp_dir.clone(), indexer.flushed_counter()));
+
+    // Create pipeline channel
+    let (tx, rx) = mpsc::channel::<PipelineMessage>(2000);
+
+    // Spawn processing pipeline
+    let pipeline_handle = tokio::spawn(run_processing_pipeline(
+        rx,
+        wikidata,
+        scylla_client.clone(),
+        indexer.sender_clone(),
+        PipelineOptions {
+            batch_size: args.batch_size,
+            languages: languages.clone(),
+            default_languages,
+            synonyms,
+            import_id: import_start.timestamp_millis(),
+            skip_unchanged,
+        },
+        tracker,
+    ));
+
+    // Index Admin Boundaries first
+    if admins_indexed {
+        info!("Administrative boundaries already indexed, skipping");
+    } else {
+        info!(
+            "Indexing {} administrative boundaries...",
+            spatial_index_ref.len()
+        );
+    }
+
+    // Use iterator from spatial_index to avoid looking at "boundaries" Vec (which is gone)
+    for boundary in spatial_index_ref.boundaries().filter(|_| !admins_indexed) {
+        let center = representative_point(&boundary.geometry).map(|p| GeoPoint {
+            lat: p.y(),
+            lon: p.x(),
+        });
+        let bbox = boundary
+            .bbox()
+            .map(|(min_x, min_y, max_x, max_y)| GeoBbox::new(min_x, min_y, max_x, max_y));
+
+        if let Some(center) = center {
+            // Map AdminLevel to specific Layer
+            let layer = admin_level_to_layer(boundary.area.level);
+
+            let mut place = Place::new(
+                OsmType::Relation,
+                boundary.area.osm_id,
+                layer,
+                center,
+                &source_file,
+            );
+            place.name = boundary.area.name.clone();
+            place.wikidata_id = boundary.area.wikidata_id.clone();
+            place.bbox = bbox;
+
+            // PIP lookup for admin hierarchy (limit to higher levels)
+            let hierarchy = pip_service.lookup(
+                place.center_point.lon,
+                place.center_point.lat,
+                Some(boundary.area.level),
+            );
+            place.parent = hierarchy;
+
+            // Assign importance
+            // BUMP IMPORTANCE for Admin Relations
+            let default_importance = match boundary.area.level {
+                AdminLevel::Country => 1.0,
+                AdminLevel::MacroRegion => 0.95,
+                AdminLevel::Region => 0.9,
+                AdminLevel::MacroCounty => 0.85,
+                AdminLevel::County => 0.8,
+                AdminLevel::LocalAdmin => 0.75,
+                AdminLevel::Locality => 0.7,
+                AdminLevel::Borough => 0.65,
+                AdminLevel::Neighbourhood => 0.6,
+            };
+
+            place.importance = Some(default_importance);
+
+            // Overwrite with externally provided importance if available and higher?
+            // Usually internal manual boost should win for fundamental admin types,
+            // but let's allow external to override ONLY if it's very high?
+            // Or just check if wiki importance exists.
+            if let Some(ref index) = importance_index {
+                if let Some(ref qid) = place.wikidata_id {
+                    if let Some(score) = index.get(qid) {
+                        place.importance = Some(score);
+                    }
+                }
+            }
+
+            let level = Some(boundary.area.level);
+            if !send_place(&tx, &transformers, &mut stages, place, level).await? {
+                error!("Pipeline receiver dropped encountered during admin indexing");
+                break;
+            }
+        }
+    }
+
+    if checkpointing && !admins_indexed {
+        let _ = tx
+            .send(PipelineMessage::Progress(Progress::AdminsIndexed))
+            .await;
+    }
+
+    info!("Processing OSM objects...");
+
+    // Process each OSM object
+    let mut position = 0u64;
+    let mut clipped = 0u64;
+    for obj_result in input.objects()? {
+        pb.inc(1);
+
+        if checkpointing && position > 0 && position.is_multiple_of(CHECKPOINT_INTERVAL) {
+            let _ = tx
+                .send(PipelineMessage::Progress(Progress::Objects(position)))
+                .await;
+        }
+        position += 1;
+
+        let obj = match obj_result {
+            Ok(o) => o,
+            Err(e) => {
+                warn!("Error reading OSM object: {}", e);
+                continue;
+            }
+        };
+
+        // If merging enabled and this is a road way, collect it (unless the street pass
+        // already did)
+        if args.merge_roads {
+            if let osmpbfreader::OsmObj::Way(ref way) = obj {
+                if is_road_way(&way.tags) {
+                    if let Some(ref mut merger) = way_merger {
+                        add_road(merger, way);
+                    }
+                    continue; // Don't process this way now
+                }
+            }
+        }
+
+        // Places up to the checkpoint are already imported (roads above are still collected,
+        // merged roads are only emitted at the end)
+        if position <= objects_done {
+            continue;
+        }
+
+        // Try to extract a place from this object (non-roads or when merging disabled)
+        if let Some(mut place) = extract_place(&obj, &source_file, &place_resolver)? {
+            if !in_clip_area(&clip_area, &place) {
+                clipped += 1;
+                continue;
+            }
+            place.importance = Some(osm_importance.score(obj.tags(), Some(obj.id())));
+
+            // PIP lookup for admin hierarchy
+            let hierarchy =
+                pip_service.lookup(place.center_point.lon, place.center_point.lat, None);
+            place.parent = hierarchy;
+
+            // Collect Wikidata ID is redundant here as we moved it to process_batch,
+            // BUT we still need to assign importance if we have it locally?
+            // Or can we move importance lookup to pipeline too?
+            // Importance map is available here. Pipeline doesn't have it.
+            // Let's keep importance assignment here.
+
+            if let Some(ref qid) = place.wikidata_id {
+                if let Some(ref index) = importance_index {
+                    if let Some(score) = index.get(qid) {
+                        place.importance = Some(score);
+                    }
+                }
+            }
+
+            if !send_place(&tx, &transformers, &mut stages, place, None).await? {
+                error!("Pipeline receiver dropped");
+                break;
+            }
+        }
+    }
+
+    if checkpointing {
+        let _ = tx
+            .send(PipelineMessage::Progress(Progress::Objects(position)))
+            .await;
+    }
+
+    pb.finish_with_message("Processing complete");
+
+    // Process merged roads if enabled
+    if let Some(mut merged_roads) = merged_roads.or_else(|| way_merger.map(WayMerger::merge)) {
+        info!("Processing merged roads...");
+        // Stable order so checkpoint positions stay valid across runs
+        merged_roads.sort_by_key(|road| road.way_ids[0]);
+        let merged_count = merged_roads.len();
+
+        for (i, merged_road) in merged_roads.into_iter().enumerate() {
+            if checkpointing && i > 0 && i.is_multiple_of(ROAD_CHECKPOINT_INTERVAL) {
+                let _ = tx.send(PipelineMessage::Progress(Progress::Roads(i))).await;
+            }
+            if i < roads_done {
+                continue;
+            }
+
+            if let Some(mut place) = merged_road.to_place(&source_file) {
+                // Extract tags
+                extract_tags(&mut place, &merged_road.tags);
+
+                // Filter out items without name or address
+                if place.name.is_empty() && place.address.is_none() {
+                    continue;
+                }
+
+                if !in_clip_area(&clip_area, &place) {
+                    clipped += 1;
+                    continue;
+                }
+
+                // Calculate importance
+                place.importance = Some(osm_importance.score(&merged_road.tags, None));
+
+                // PIP lookup for admin hierarchy
+                let hierarchy =
+                    pip_service.lookup(place.center_point.lon, place.center_point.lat, None);
+                place.parent = hierarchy;
+
+                if let Some(ref qid) = place.wikidata_id {
+                    if let Some(ref index) = importance_index {
+                        if let Some(score) = index.get(qid) {
+                            place.importance = Some(score);
+                        }
+                    }
+                }
+
+                if !send_place(&tx, &transformers, &mut stages, place, None).await? {
+                    error!("Pipeline receiver dropped during merged roads");
+                    break;
+                }
+            }
+        }
+
+        if checkpointing {
+            let _ = tx
+                .send(PipelineMessage::Progress(Progress::Roads(merged_count)))
+                .await;
+        }
+    }
+
+    if clip_area.is_some() {
+        info!("Dropped {} places outside the clip area", clipped);
+    }
+    if postcode_index.is_some() {
+        info!(
+            "Inferred the postcode of {} places",
+            stages.inferred_postcodes
+        );
+    }
+    let unmatched_streets = match stages.unmatched_streets {
+        Some(unmatched) => {
+            let (count, path) = unmatched.finish()?;
+            info!(
+                "{} addresses name a street that was not found nearby, see {}",
+                count,
+                path.display()
+            );
+            Some(count)
+        }
+        None => None,
+    };
+
+    // Close channel by dropping sender
+    drop(tx);
+
+    // Wait for pipeline to finish
+    let tracker = match pipeline_handle.await {
+        Ok(tracker) => tracker,
+        Err(e) => {
+            error!("Pipeline task failed: {}", e);
+            None
+        }
+    };
+
+    // Finish indexing
+    let (indexed, errors) = indexer.finish().await?;
+
+    info!("Indexed {} documents ({} errors)", indexed, errors);
+
+    // Everything is flushed now; keep the checkpoint only if part of the import was lost
+    if let Some(mut tracker) = tracker {
+        tracker.poll()?;
+        // Documents rejected by Elasticsearch are only recovered by resuming
+        if errors > 0 {
+            tracker.fail();
+        }
+        if tracker.failed() {
+            anyhow::bail!(
+                "Some places failed to import; rerun with --resume to continue from the last checkpoint"
+            );
+        }
+    }
+
+    // Refresh: delete stale documents
+    if args.refresh {
+        info!("Deleting stale documents from previous import...");
+        ownership::release_stale(
+            &es_client,
+            &scylla_client,
+            &source_file,
+            import_start.timestamp_millis(),
+        )
+        .await?;
+    }
+
+    if checkpointing {
+        Checkpoint::remove(&args.tmp_dir, &source_file)?;
+        // Only a store asked for with --geometry-store disk is kept for later runs
+        if args.geometry_store != StoreMode::Disk {
+            drop(place_resolver);
+            let inputs = [
+                (args.admin_file.as_ref(), "admin"),
+                (Some(&file_path), "places"),
+            ];
+            for (path, purpose) in inputs {
+                if let Some(dir) = path.map(|p| store_dir(&args, p, purpose)) {
+                    if dir.exists() {
+                        std::fs::remove_dir_all(&dir).with_context(|| {
+                            format!("Failed to remove geometry store {}", dir.display())
+                        })?;
+                    }
+                }
+            }
+        }
+    }
+
+    // Final stats
+    let doc_count = es_client.doc_count().await?;
+    info!("Total documents in index: {}", doc_count);
+
+    if let Some(ref dw) = discord {
+        let mut message = format!("Successfully indexed **{}** documents (with **{}** errors) for **{}**.\nTotal documents in index: **{}**", indexed, errors, source_file, doc_count);
+        if let Some(count) = unmatched_streets {
+            message.push_str(&format!(
+                "\nAddresses with an unknown street: **{}**",
+                count
+            ));
+        }
+        let _ = dw
+            .send_notification("Ingestion Complete", &message, true)
+            .await;
+    }
+
+    Ok(())
+}
+
+/// Data looked up at each place's center point
+struct Enrichers {
+    timezones: Option<TimezoneIndex>,
+    elevation: Option<ElevationTiles>,
+}
+
+impl Enrichers {
+    fn apply(&self, place: &mut Place) {
+        let GeoPoint { lon, lat } = place.center_point;
+        if let Some(ref timezones) = self.timezones {
+            place.timezone = timezones.lookup(lon, lat).map(str::to_string);
+        }
+        // A tagged `ele` wins
+        if let (None, Some(tiles)) = (place.elevation, &self.elevation) {
+            place.elevation = tiles.elevation(lon, lat);
+        }
+    }
+}
+
+/// Fields derived from a place's location and address. They are filled in after the
+/// transformers, which may move a place or change its address.
+struct PlaceStages<'a> {
+    pip: &'a PipService,
+    postcodes: Option<&'a PostcodeIndex>,
+    streets: Option<&'a StreetIndex>,
+    unmatched_streets: Option<UnmatchedStreets>,
+    enrichers: &'a Enrichers,
+    inferred_postcodes: u64,
+}
+
+impl PlaceStages<'_> {
+    /// `center` is where the place was before the transformers; admin areas only look up
+    /// the levels above `admin_level`
+    fn apply(
+        &mut self,
+        place: &mut Place,
+        center: GeoPoint,
+        admin_level: Option<AdminLevel>,
+    ) -> Result<()> {
+        let GeoPoint { lon, lat } = place.center_point;
+        if lon != center.lon || lat != center.lat {
+            place.parent = self.pip.lookup(lon, lat, admin_level);
+        }
+        if let Some(index) = self.postcodes {
+            self.inferred_postcodes += u64::from(index.apply(place));
+        }
+        if let (Some(index), Some(unmatched)) = (self.streets, &mut self.unmatched_streets) {
+            if place.layer == Layer::Address && !index.link(place) {
+                unmatched.add(place)?;
+            }
+        }
+        self.enrichers.apply(place);
+        Ok(())
+    }
+}
+
+/// Run a place through the transformers and queue the results for writing; returns false
+/// once the pipeline is gone
+async fn send_place(
+    tx: &mpsc::Sender<PipelineMessage>,
+    transformers: &TransformerChain,
+    stages: &mut PlaceStages<'_>,
+    place: Place,
+    admin_level: Option<AdminLevel>,
+) -> Result<bool> {
+    let center = place.center_point;
+    for mut place in transformers.apply(place) {
+        stages.apply(&mut place, center, admin_level)?;
+        if tx
+            .send(PipelineMessage::Place(Box::new(place)))
+            .await
+            .is_err()
+        {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+/// Queue a road way for merging, grouped by name and highway type
+fn add_road(merger: &mut WayMerger, way: &osmpbfreader::Way) {
+    // Normalize name to generate merge key for grouping, but pass ORIGINAL tags
+    // This ensures the official name in Scylla is the original one
+    let merge_key = if let Some(name) = way.tags.get("name") {
+        if let Some(highway) = way.tags.get("highway") {
+            Some(format!("{}|{}", name, highway))
+        } else {
+            None
+        }
+    } else {
+        None
+    };
+
+    merger.add_road(
+        way.id,
+        way.tags.clone(),
+        way.nodes.iter().map(|n| n.0).collect(),
+        merge_key,
+    );
+}
+
+/// Index the streets for linking addresses to them (an extra pass over the input). With
+/// a `merger`, road ways are collected and merged here and the merged roads returned.
+fn build_street_index(
+    input: &OsmInput,
+    source_file: &str,
+    resolver: &Arc<GeometryResolver>,
+    pip_service: &PipService,
+    clip_area: &Option<ClipArea>,
+    mut merger: Option<WayMerger>,
+) -> Result<(StreetIndex, Option<Vec<MergedRoad>>)> {
+    info!("Collecting streets...");
+    let mut builder = StreetIndexBuilder::default();
+    let mut add_street = |place: &mut Place, lines: &[geo::LineString<f64>]| {
+        if !in_clip_area(clip_area, place) {
+            return;
+        }
+        place.parent = pip_service.lookup(place.center_point.lon, place.center_point.lat, None);
+        builder.add(place, lines);
+    };
+
+    for obj in input.objects()? {
+        let Ok(obj) = obj else {
+            continue;
+        };
+        let osmpbfreader::OsmObj::Way(ref way) = obj else {
+            continue;
+        };
+        if !is_road_way(&way.tags) {
+            continue;
+        }
+        if let Some(ref mut merger) = merger {
+            add_road(merger, way);
+            continue;
+        }
+
+        if let Some(mut place) = extract_place(&obj, source_file, resolver)? {
+            if place.layer == Layer::Street {
+                let nodes: Vec<i64> = way.nodes.iter().map(|n| n.0).collect();
+                add_street(&mut place, &[way_line(resolver, &nodes)]);
+            }
+        }
+    }
+
+    let merged_roads = merger.map(WayMerger::merge);
+    for road in merged_roads.iter().flatten() {
+        if let Some(mut place) = road.to_place(source_file) {
+            extract_tags(&mut place, &road.tags);
+            add_street(&mut place, &road.line_strings);
+        }
+    }
+
+    let index = builder.build();
+    info!("Indexed {} streets", index.size());
+    Ok((index, merged_roads))
+}
+
+/// Index the address points carrying a postcode, to infer the postcode of other places
+/// near them (an extra pass over the input)
+fn build_postcode_index(
+    input: &OsmInput,
+    source_file: &str,
+    resolver: &Arc<GeometryResolver>,
+    pip_service: &PipService,
+) -> Result<PostcodeIndex> {
+    info!("Collecting address postcodes...");
+    let mut points = Vec::new();
+    for obj in input.objects()? {
+        let Ok(obj) = obj else {
+            continue;
+        };
+        if !obj.tags().contains_key("addr:postcode") {
+            continue;
+        }
+        let Some(place) = extract_place(&obj, source_file, resolver)? else {
+            continue;
+        };
+        if place.layer != Layer::Address {
+            continue;
+        }
+        let Some(postcode) = place.address.and_then(|a| a.postcode) else {
+            continue;
+        };
+
+        let GeoPoint { lon, lat } = place.center_point;
+        let parent = pip_service.lookup(lon, lat, None);
+        points.push((lon, lat, locality_key(&parent), postcode));
+    }
+
+    let index = PostcodeIndex::build(points);
+    info!("Indexed {} address postcodes", index.size());
+    Ok(index)
+}
+
+fn in_clip_area(clip_area: &Option<ClipArea>, place: &Place) -> bool {
+    clip_area
+        .as_ref()
+        .is_none_or(|clip| clip.contains(place.center_point.lon, place.center_point.lat))
+}
+
+/// Geometry store options for an input file.
+///
+/// Disk stores live in [`store_dir`] and are reused on later runs as long as the input
+/// file is unchanged.
+fn store_options(args: &Args, path: &Path, purpose: &str) -> Result<StoreOptions> {
+    // Resuming relies on the geometry store surviving the interrupted run
+    let mode = if args.checkpointing() {
+        StoreMode::Disk
+    } else {
+        args.geometry_store
+    };
+
+    Ok(StoreOptions {
+        mode,
+        node_index: args.node_index,
+        dir: Some(store_dir(args, path, purpose)),
+        fingerprint: Some(file_fingerprint(path)?),
+    })
+}
+
+/// Directory of the disk geometry store of an input file: `tmp_dir/geometry/<file>-<purpose>`
+fn store_dir(args: &Args, path: &Path, purpose: &str) -> PathBuf {
+    let file_name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or("unknown.osm.pbf");
+    args.tmp_dir
+        .join("geometry")
+        .join(format!("{}-{}", file_name, purpose))
+}
+
+/// Settings shared by every batch of the processing pipeline
+struct PipelineOptions {
+    batch_size: usize,
+    languages: Option<LanguageFilter>,
+    default_languages: DefaultLanguages,
+    synonyms: Arc<SynonymService>,
+    /// Identifies this import of the source file in ScyllaDB (import start, in ms)
+    import_id: i64,
+    /// Skip places whose content is unchanged since an earlier import
+    skip_unchanged: bool,
+}
+
+impl PipelineOptions {
+    /// Fill in what follows from the final names of a place
+    fn apply_names(&self, place: &mut Place) {
+        self.default_languages.apply_place(place);
+        if let Some(ref filter) = self.languages {
+            filter.apply(&mut place.name);
+        }
+        place.synonyms = self.synonyms.place_synonyms(place);
+        // Refresh name_all with the final names
+        place.sanitize();
+    }
+}
+
+async fn run_processing_pipeline(
+    mut rx: mpsc::Receiver<PipelineMessage>,
+    wikidata: Option<WikidataFetcher>,
+    scylla: Arc<ScyllaClient>,
+    indexer_tx: mpsc::Sender<EsPlaceDoc>,
+    options: PipelineOptions,
+    mut tracker: Option<CheckpointTracker>,
+) -> Option<CheckpointTracker> {
+    let mut buffer = Vec::with_capacity(options.batch_size);
+    // Documents handed to the bulk indexer so far
+    let mut sent = 0usize;
+
+    while let Some(message) = rx.recv().await {
+        let progress = match message {
+            PipelineMessage::Place(place) => {
+                buffer.push(*place);
+                None
+            }
+            PipelineMessage::Progress(progress) => Some(progress),
+        };
+
+        // Unchanged places are not sent to the indexer, so the document count of a
+        // progress marker is only known once the places before it are processed
+        if buffer.len() >= options.batch_size || (progress.is_some() && !buffer.is_empty()) {
+            match process_buffer(&mut buffer, &wikidata, &options, &scylla, &indexer_tx).await {
+                Ok(count) => sent += count,
+                Err(e) => {
+                    error!("Error processing batch: {}", e);
+                    if let Some(ref mut tracker) = tracker {
+                        tracker.fail();
+                    }
+                }
+            }
+            buffer.clear();
+
+            if let Some(ref mut tracker) = tracker {
+                if let Err(e) = tracker.poll() {
+                    warn!("Failed to save checkpoint: {}", e);
+                }
+            }
+        }
+
+        if let (Some(progress), Some(tracker)) = (progress, tracker.as_mut()) {
+            tracker.mark(sent, progress);
+        }
+    }
+
+    // Process remaining
+    if !buffer.is_empty() {
+        if let Err(e) = process_buffer(&mut buffer, &wikidata, &options, &scylla, &indexer_tx).await
+        {
+            error!("Error processing final batch: {}", e);
+            if let Some(ref mut tracker) = tracker {
+                tracker.fail();
+            }
+        }
+    }
+
+    tracker
+}
+
+/// Write a batch of places to ScyllaDB and the indexer; returns the number of documents
+/// sent to the indexer
+async fn process_buffer(
+    places: &mut [Place],
+    wikidata: &Option<WikidataFetcher>,
+    options: &PipelineOptions,
+    scylla: &ScyllaClient,
+    indexer_tx: &mpsc::Sender<EsPlaceDoc>,
+) -> Result<usize> {
+    // 1. Fetch Wikidata
+    if let Some(wd) = wikidata {
+        let qids: Vec<String> = places
+            .iter()
+            .filter_map(|p| p.wikidata_id.clone())
+            .collect();
+
+        if !qids.is_empty() {
+            wd.fetch_batch(&qids).await?;
+
+            for place in places.iter_mut() {
+                if let Some(ref qid) = place.wikidata_id {
+                    wd.merge_labels(qid, &mut place.name);
+                }
+            }
+        }
+    }
+
+    // 2. Names, now including the Wikidata labels
+    for place in places.iter_mut() {
+        options.apply_names(place);
+    }
+
+    // 3. Scylla Upsert (parallel) & Indexer Send
+    // Stored hashes and sources of the whole batch, looked up at once
+    let ids: Vec<&str> = places.iter().map(|p| p.source_id.as_str()).collect();
+    let (stored_hashes, stored_sources) = futures::try_join!(
+        scylla.get_place_hashes(&ids),
+        scylla.get_places_sources(&ids)
+    )?;
+
+    // Mark the places as seen by this import; stale detection relies on it
+    let mut ids_by_file: HashMap<&str, Vec<&str>> = HashMap::new();
+    for place in places.iter() {
+        ids_by_file
+            .entry(place.source_file.as_str())
+            .or_default()
+            .push(place.source_id.as_str());
+    }
+    for (source_file, ids) in ids_by_file {
+        scylla
+            .mark_places_seen(&ids, source_file, options.import_id)
+            .await?;
+    }
+
+    // Prepare all upsert futures for parallel execution
+    let upsert_futures: Vec<_> = places
+        .iter()
+        .map(|place| async {
+            let id = &place.source_id;
+            let content_hash = place.content_hash();
+            let sources = stored_sources.get(id).cloned().unwrap_or_default();
+
+            // Places first written earlier in this same import are rewritten: after an
+            // interruption they may not have reached Elasticsearch
+            let unchanged = options.skip_unchanged
+                && stored_hashes.get(id) == Some(&content_hash)
+                && sources
+                    .get(&place.source_file)
+                    .is_some_and(|&seen| seen < options.import_id);
+            if unchanged {
+                return Ok(None);
+            }
+
+            // Upsert Admin Areas (Scylla)
+            upsert_admin_areas(place, scylla).await?;
+
+            // All source files containing the place (extracts overlap at borders)
+            let mut source_files: Vec<String> = sources.into_keys().collect();
+            if !source_files.contains(&place.source_file) {
+                source_files.push(place.source_file.clone());
+            }
+            source_files.sort();
+
+            // Upsert Normalized Place (Scylla)
+            let mut normalized = NormalizedPlace::from_place(place.clone());
+            normalized.source_files = source_files.clone();
+            let json_data = serde_json::to_string(&normalized)?;
+            scylla
+                .upsert_place(&normalized.source_id, &json_data, content_hash)
+                .await?;
+
+            Ok::<_, anyhow::Error>(Some(source_files))
+        })
+        .collect();
+
+    // Execute all ScyllaDB writes in parallel
+    let results = futures::future::join_all(upsert_futures).await;
+
+    // Check for errors
+    let source_files = results.into_iter().collect::<Result<Vec<_>>>()?;
+
+    // Send to Indexer (sequential - channel ordering)
+    let mut sent = 0;
+    for (place, source_files) in places.iter().zip(source_files) {
+        let Some(source_files) = source_files else {
+            continue;
+        };
+        let mut doc = EsPlaceDoc::from(place);
+        doc.source_files = source_files;
+        indexer_tx.send(doc).await?;
+        sent += 1;
+    }
+
+    Ok(sent)
+}
+
+/// Check if an OSM way is a road that should be considered for merging
+fn is_road_way(tags: &osmpbfreader::Tags) -> bool {
+    if let Some(highway) = tags.get("highway") {
+        // Only roads with names can be merged
+        if !tags.contains_key("name") {
+            return false;
+        }
+
+        // Check if this is a street-type highway (not motorways or links)
+        matches!(
+            highway.as_str(),
+            "residential"
+                | "primary"
+                | "secondary"
+                | "tertiary"
+                | "unclassified"
+                | "service"
+                | "living_street"
+                | "pedestrian"
+                | "track"
+                | "road"
+                | "footway"
+                | "cycleway"
+                | "path"
+        )
+    } else {
+        false
+    }
+}
+
+/// Extract a Place from an OSM object if it's relevant
+fn extract_place(
+    obj: &osmpbfreader::OsmObj,
+    source_file: &str,
+    resolver: &Arc<GeometryResolver>,
+) -> Result<Option<Place>> {
+    use osmpbfreader::OsmObj;
+
+    match obj {
+        OsmObj::Node(node) => {
+            if let Some(layer) = determine_layer(&node.tags) {
+                // Strict Country Check: Nodes cannot be countries
+                if layer == Layer::Country {
+                    return Ok(None);
+                }
+
+                let center = GeoPoint {
+                    lat: node.lat(),
+                    lon: node.lon(),
+                };
+                let mut place = Place::new(OsmType::Node, node.id.0, layer, center, source_file);
+                extract_tags(&mut place, &node.tags);
+
+                // Filter out items without name or address
+                if place.name.is_empty() && place.address.is_none() {
+                    return Ok(None);
+                }
+
+                Ok(Some(place))
+            } else {
+                Ok(None)
+            }
+        }
+        OsmObj::Way(way) => {
+            if let Some(layer) = determine_layer(&way.tags) {
+                // Strict Country Check: Ways cannot be countries
+                if layer == Layer::Country {
+                    return Ok(None);
+                }
+
+                // Resolve geometry
+                if let Some((lon, lat)) = resolver.resolve_representative_point(way.id) {
+                    // FILTER: Skip ways that are admin boundaries
+                    // We only want relations for administrative areas to avoid clutter / duplicate borders
+
+                    // Explicitly check for border_type or boundary=administrative or country adjacency tags to catch any stragglers
+                    if way.tags.contains_key("border_type")
+                        || way.tags.contains("boundary", "administrative")
+                        || way.tags.contains_key("left:country")
+                        || way.tags.contains_key("right:country")
+                    {
+                        return Ok(None);
+                    }
+
+                    if is_admin_layer(layer) {
+                        return Ok(None);
+                    }
+
+                    let center = GeoPoint { lat, lon };
+                    let mut place = Place::new(OsmType::Way, way.id.0, layer, center, source_file);
+                    extract_tags(&mut place, &way.tags);
+
+                    // Filter out items without name or address
+                    if place.name.is_empty() && place.address.is_none() {
+                        return Ok(None);
+                    }
+
+                    // Optional: Add Bbox
+                    if let Some(poly) = resolver.resolve_way(way.id) {
+                        if let Some(rect) = poly.bounding_rect() {
+                            place.bbox = Some(GeoBbox::new(
+                                rect.min().x,
+                                rect.min().y,
+                                rect.max().x,
+                                rect.max().y,
+                            ));
+                        }
+                    }
+
+                    Ok(Some(place))
+                } else {
+                    // warn!("Could not resolve geometry for way {}", way.id.0);
+                    Ok(None)
+                }
+            } else {
+                Ok(None)
+            }
+        }
+        OsmObj::Relation(rel) => {
+            // Check layers/relevance
+            if let Some(layer) = determine_layer(&rel.tags) {
+                // Strict Country Check for Relations
+                if layer == Layer::Country {
+                    let tags = &rel.tags;
+                    let is_boundary = tags.get("type").map(|s| s.as_str()) == Some("boundary");
+                    let is_admin =
+                        tags.get("boundary").map(|s| s.as_str()) == Some("administrative");
+                    let has_iso = tags.contains_key("ISO3166-1")
+                        || tags.contains_key("ISO3166-1:alpha2")
+                        || tags.contains_key("ISO3166-1:alpha3");
+
+                    if !is_boundary || !is_admin || !has_iso {
+                        return Ok(None);
+                    }
+                }
+
+                // Removed explicit skip for Layer::Admin to allow places like archipelagos to be indexed
+                // even if they are not picked up by extract_admin_boundaries.
+
+                // Resolve multipolygon geometry
+                if let Some(multi_poly) = resolver.resolve_relation(rel.id) {
+                    // Representative point (guaranteed inside the multipolygon)
+                    if let Some(point) = representative_point(&multi_poly) {
+                        let center = GeoPoint {
+                            lat: point.y(),
+                            lon: point.x(),
+                        };
+
+                        let mut place =
+                            Place::new(OsmType::Relation, rel.id.0, layer, center, source_file);
+                        extract_tags(&mut place, &rel.tags);
+
+                        // Filter out items without name or address
+                        if place.name.is_empty() && place.address.is_none() {
+                            return Ok(None);
+                        }
+
+                        // Calculate Bbox (antimeridian-aware)
+                        if let Some((min_lon, min_lat, max_lon, max_lat)) =
+                            multipolygon_bbox(&multi_poly)
+                        {
+                            place.bbox = Some(GeoBbox::new(min_lon, min_lat, max_lon, max_lat));
+                        }
+
+                        Ok(Some(place))
+                    } else {
+                        Ok(None)
+                    }
+                } else {
+                    Ok(None)
+                }
+            } else {
+                Ok(None)
+            }
+        }
+    }
+}
+
+// Skipping determine_layer...
+
+/// Extract all relevant tags from OSM object
+fn extract_tags(place: &mut Place, tags: &osmpbfreader::Tags) {
+    for (key, value) in tags.iter() {
+        let key_str = key.as_str();
+
+        // Names
+        if key_str == "name" {
+            place.add_name("default", value.to_string());
+        } else if let Some(lang) = key_str.strip_prefix("name:") {
+            // Japanese romaji are also tagged with the legacy suffix `ja_rm`
+            let lang = if lang == "ja_rm" { "ja-Latn" } else { lang };
+            if is_valid_lang_code(lang) {
+                place.add_name(lang, value.to_string());
+            }
+        } else {
+            // Check for alternate names
+            let name_variants = [
+                "alt_name",
+                "old_name",
+                "official_name",
+                "short_name",
+                "int_name",
+                "nat_name",
+                "reg_name",
+                "loc_name",
+            ];
+
+            for variant in &name_variants {
+                if key_str == *variant {
+                    place.add_name(variant, value.to_string());
+                    break;
+                } else if let Some(suffix) = key_str
+                    .strip_prefix(variant)
+                    .and_then(|s| s.strip_prefix(':'))
+                {
+                    if is_valid_lang_code(suffix) {
+                        place.add_name(key_str, value.to_string());
+                    }
+                    break;
+                }
+            }
+        }
+
+        // Wikidata
+        if key_str == "wikidata" || key_str == "brand:wikidata" {
+            place.wikidata_id = Some(value.to_string());
+        }
+        // Address components
+        else if key_str == "addr:housenumber" {
+            place
+                .address
+                .get_or_insert_with(Address::default)
+                .housenumber = Some(value.to_string());
+        } else if key_str == "addr:street" {
+            place.address.get_or_insert_with(Address::default).street = Some(value.to_string());
+        } else if key_str == "addr:postcode" {
+            place.address.get_or_insert_with(Address::default).postcode = Some(value.to_string());
+        } else if key_str == "addr:city" {
+            place.address.get_or_insert_with(Address::default).city = Some(value.to_string());
+        } else if key_str == "ele" {
+            place.elevation = parse_ele(value);
+        }
+        // Categories (POI types)
+        else if [
+            "amenity",
+            "shop",
+            "tourism",
+            "leisure",
+            "cuisine",
+            "building",
+            "historic",
+            "office",
+            "railway",
+            "public_transport",
+            "aeroway",
+            "station",
+        ]
+        .contains(&key_str)
+        {
+            place.add_category(key_str, value);
+        }
+    }
+}
+
+fn is_valid_lang_code(lang: &str) -> bool {
+    // Basic check for 2-3 letter codes or simple region codes
+    lang.len() >= 2 && lang.len() <= 10 && lang.chars().all(|c| c.is_alphabetic() || c == '-')
+}
+
+fn determine_layer(tags: &osmpbfreader::Tags) -> Option<Layer> {
+    if let Some(place) = tags.get("place") {
+        match place.as_str() {
+            "country" => Some(Layer::Country),
+            "state" | "region" | "province" => Some(Layer::Region),
+            "district" | "county" => Some(Layer::County),
+            "municipality" | "city" | "town" | "village" => Some(Layer::Locality),
+            "borough" => Some(Layer::Borough),
+            "suburb" | "quarter" | "neighbourhood" => Some(Layer::Neighbourhood),
+            "hamlet" | "isolated_dwelling" | "farm" => Some(Layer::Locality),
+
+            "island" | "archipelago" => Some(Layer::Admin), // Treat as generic admin
+            _ => None,
+        }
+    } else if let Some(admin_level) = tags.get("admin_level") {
+        // Only if boundary=administrative
+        if tags.contains("boundary", "administrative") {
+            if let Ok(level) = admin_level.parse::<u8>() {
+                if let Some(admin_level_enum) = AdminLevel::from_osm_level(level) {
+                    if admin_level_enum == AdminLevel::Country {
+                        // Strict check: Must have ISO3166-1 tag to be a country
+                        let has_iso = tags.contains_key("ISO3166-1")
+                            || tags.contains_key("ISO3166-1:alpha2")
+                            || tags.contains_key("ISO3166-1:alpha3");
+
+                        if !has_iso {
+                            return None;
+                        }
+                    }
+                    return Some(admin_level_to_layer(admin_level_enum));
+                }
+            }
+            Some(Layer::Admin)
+        } else {
+            None
+        }
+    } else {
+        // Venues/Addresses
+        if tags.contains_key("addr:housenumber") && tags.contains_key("addr:street") {
+            Some(Layer::Address)
+        } else if tags.contains_key("amenity")
+            || tags.contains_key("shop")
+            || tags.contains_key("tourism")
+            || tags.contains_key("leisure")
+        {
+            Some(Layer::Venue)
+        } else {
+            None
+        }
+    }
+}
+
+fn admin_level_to_layer(level: AdminLevel) -> Layer {
+    match level {
+        AdminLevel::Country => Layer::Country,
+        AdminLevel::MacroRegion => Layer::MacroRegion,
+        AdminLevel::Region => Layer::Region,
+        AdminLevel::MacroCounty => Layer::MacroCounty,
+        AdminLevel::County => Layer::County,
+        AdminLevel::LocalAdmin => Layer::LocalAdmin,
+        AdminLevel::Locality => Layer::Locality,
+        AdminLevel::Borough => Layer::Borough,
+        AdminLevel::Neighbourhood => Layer::Neighbourhood,
+    }
+}
+
+fn is_admin_layer(layer: Layer) -> bool {
+    matches!(
+        layer,
+        Layer::Admin
+            | Layer::Country
+            | Layer::MacroRegion
+            | Layer::Region
+            | Layer::MacroCounty
+            | Layer::County
+            | Layer::LocalAdmin
+            | Layer::Locality
+            | Layer::Borough
+            | Layer::Neighbourhood
+    )
+}
+
+async fn upsert_admin_areas(place: &Place, scylla: &ScyllaClient) -> Result<()> {
+    let parents = [
+        place.parent.country.as_ref(),
+        place.parent.macro_region.as_ref(),
+        place.parent.region.as_ref(),
+        place.parent.macro_county.as_ref(),
+        place.parent.county.as_ref(),
+        place.parent.local_admin.as_ref(),
+        place.parent.locality.as_ref(),
+        place.parent.borough.as_ref(),
+        place.parent.neighbourhood.as_ref(),
+    ];
+
+    for parent in parents.iter().flatten() {
+        if let Some(id) = parent.id {
+            let source_id = format!("relation/{}", id);
+            // Use to_scylla_json() to preserve full multilingual names for ScyllaDB
+            let json_data = parent.to_scylla_json()?;
+            scylla.upsert_admin_area(&source_id, &json_data).await?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::input::fixture;
+
+    /// Run a fixture through every stage of an import short of ScyllaDB and Elasticsearch.
+    /// Returns the places and the number of addresses with an unknown street.
+    async fn ingest_fixture(name: &str, transformers: &TransformerChain) -> (Vec<Place>, u64) {
+        let tmp_dir = tempfile::tempdir().unwrap();
+        let input = OsmInput::open(fixture(name)).unwrap();
+        let resolver = Arc::new(
+            GeometryResolver::build(&input, |tags| {
+                determine_layer(tags).is_some() || is_road_way(tags)
+            })
+            .unwrap(),
+        );
+
+        let mut default_languages = DefaultLanguages::load(None).unwrap();
+        let mut boundaries = extract_admin_boundaries(&input, &resolver).unwrap();
+        default_languages.register_boundaries(&mut boundaries);
+        let pip_service = PipService::new(AdminSpatialIndex::build(boundaries));
+
+        let postcode_index = build_postcode_index(&input, name, &resolver, &pip_service).unwrap();
+        let merger = WayMerger::new(Arc::clone(&resolver));
+        let (street_index, merged_roads) =
+            build_street_index(&input, name, &resolver, &pip_service, &None, Some(merger)).unwrap();
+        let enrichers = Enrichers {
+            timezones: None,
+            elevation: None,
+        };
+        let mut stages = PlaceStages {
+            pip: &pip_service,
+            postcodes: Some(&postcode_index),
+            streets: Some(&street_index),
+            unmatched_streets: Some(UnmatchedStreets::create(tmp_dir.path(), name, false).unwrap()),
+            enrichers: &enrichers,
+            inferred_postcodes: 0,
+        };
+
+        // Roads are only emitted merged, after the other objects
+        let mut places = Vec::new();
+        for obj in input.objects().unwrap() {
+            let obj = obj.unwrap();
+            if obj.way().is_some_and(|way| is_road_way(&way.tags)) {
+                continue;
+            }
+            places.extend(extract_place(&obj, name, &resolver).unwrap());
+        }
+        for road in merged_roads.unwrap() {
+            let mut place = road.to_place(name).unwrap();
+            extract_tags(&mut place, &road.tags);
+            places.push(place);
+        }
+
+        let (tx, mut rx) = mpsc::channel(100);
+        for mut place in places {
+            place.parent = pip_service.lookup(place.center_point.lon, place.center_point.lat, None);
+            assert!(send_place(&tx, transformers, &mut stages, place, None)
+                .await
+                .unwrap());
+        }
+        drop(tx);
+
+        let options = PipelineOptions {
+            batch_size: 100,
+            languages: None,
+            default_languages,
+            synonyms: Arc::new(SynonymService::new()),
+            import_id: 0,
+            skip_unchanged: false,
+        };
+        let mut places = Vec::new();
+        while let Some(message) = rx.recv().await {
+            if let PipelineMessage::Place(mut place) = message {
+                options.apply_names(&mut place);
+                places.push(*place);
+            }
+        }
+        let (unmatched, _) = stages.unmatched_streets.unwrap().finish().unwrap();
+        (places, unmatched)
+    }
+
+    #[tokio::test]
+    async fn test_ingest_fixtures() {
+        // Move the café next to the address and the street out of Zürich
+        let transformers = TransformerChain::new().with(|mut place: Place| {
+            match place.source_id.as_str() {
+                "node/10" => {
+                    place.center_point = GeoPoint {
+                        lat: 47.4,
+                        lon: 8.501,
+                    }
+                }
+                "way/200" => {
+                    place.center_point = GeoPoint {
+                        lat: 47.9,
+                        lon: 8.9,
+                    }
+                }
+                _ => {}
+            }
+            vec![place]
+        });
+
+        let (places, unmatched) = ingest_fixture("village.opl", &transformers).await;
+        let (xml_places, _) = ingest_fixture("village.osm", &transformers).await;
+        let ids = |places: &[Place]| {
+            let mut ids: Vec<String> = places.iter().map(|p| p.source_id.clone()).collect();
+            ids.sort();
+            ids
+        };
+        assert_eq!(ids(&places), ids(&xml_places));
+        assert_eq!(
+            ids(&places),
+            [
+                "node/10",
+                "node/11",
+                "relation/1000",
+                "relation/1001",
+                "way/200"
+            ]
+        );
+        let place = |id: &str| places.iter().find(|p| p.source_id == id).unwrap();
+        let locality = |place: &Place| place.parent.locality.as_ref().and_then(|l| l.name.clone());
+
+        let cafe = place("node/10");
+        assert_eq!(cafe.name["default"], "Café Odeon");
+        assert!(cafe.name_all.contains(&"Odeon Cafe".to_string()));
+        assert_eq!(locality(cafe).as_deref(), Some("Zürich"));
+        // Inferred at its new location from the address next to it
+        let address = cafe.address.as_ref().unwrap();
+        assert_eq!(address.postcode.as_deref(), Some("8001"));
+        assert!(address.postcode_inferred);
+
+        // Looked up again after the move
+        let street = place("way/200");
+        assert_eq!(locality(street), None);
+        let country = street.parent.country.as_ref().unwrap();
+        assert_eq!(country.alpha2.as_deref(), Some("CH"));
+
+        // The only Bahnhofstrasse is several kilometres from the address
+        assert_eq!(place("node/11").street_id, None);
+        assert_eq!(unmatched, 1);
+
+        let doc = EsPlaceDoc::from(place("node/11"));
+        assert_eq!(doc.address.unwrap().housenumber, ["12"]);
+    }
+}
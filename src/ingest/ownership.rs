@@ -0,0 +1,231 @@
+//! Document ownership across overlapping source files.
+//!
+//! Regional extracts overlap at borders, so the same place can be imported from several
+//! files. ScyllaDB's `place_source_imports` table holds the source files containing each
+//! place, together with the last import of each file that saw it; Elasticsearch documents
+//! carry a copy of the file names in `source_files`. A source file releases its places on
+//! refresh (those not seen in the current import) or when its region is wiped, and a
+//! place is only deleted once no source file contains it anymore.
+//!
+//! The `source_files` copy in Elasticsearch is refreshed whenever the place is written
+//! again, so it may still list a file that has released the place in the meantime. It is
+//! only used to find candidates; ScyllaDB decides.
+
+use anyhow::Result;
+use elasticsearch::{DeleteByQueryParts, SearchParts};
+use futures::stream::{self, StreamExt, TryStreamExt};
+use serde_json::{json, Value};
+use tracing::info;
+
+use crate::elasticsearch::EsClient;
+use crate::scylla::ScyllaClient;
+
+const PAGE_SIZE: usize = 1000;
+
+/// Places of a page looked up in ScyllaDB at the same time
+const CONCURRENCY: usize = 64;
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ReleaseStats {
+    /// Documents no source file contains anymore
+    pub deleted: usize,
+    /// Documents kept because another source file still contains them
+    pub kept: usize,
+}
+
+/// Release the places of `source_file` that import `import_id` did not see
+pub async fn release_stale(
+    es: &EsClient,
+    scylla: &ScyllaClient,
+    source_file: &str,
+    import_id: i64,
+) -> Result<ReleaseStats> {
+    let query = json!({
+        "bool": {
+            "should": [
+                { "term": { "source_files": source_file } },
+                { "term": { "source_file": source_file } }
+            ],
+            "minimum_should_match": 1
+        }
+    });
+
+    release_documents(es, scylla, query, |file, seen| {
+        file == source_file && seen != import_id
+    })
+    .await
+}
+
+/// Release all places of the source files matching a wildcard pattern (`*`, `?`)
+pub async fn release_matching(
+    es: &EsClient,
+    scylla: &ScyllaClient,
+    pattern: &str,
+) -> Result<ReleaseStats> {
+    let query = json!({
+        "bool": {
+            "should": [
+                { "wildcard": { "source_files": { "value": pattern } } },
+                { "wildcard": { "source_file": { "value": pattern } } }
+            ],
+            "minimum_should_match": 1
+        }
+    });
+
+    release_documents(es, scylla, query, |file, _| wildcard_match(pattern, file)).await
+}
+
+/// Remove the source files accepted by `releases` (called with the file name and the
+/// import that last saw the place in it) from every document matching `query`, and
+/// delete the documents left without a source file.
+async fn release_documents(
+    es: &EsClient,
+    scylla: &ScyllaClient,
+    query: Value,
+    releases: impl Fn(&str, i64) -> bool,
+) -> Result<ReleaseStats> {
+    let mut stats = ReleaseStats::default();
+    let mut search_after: Option<Value> = None;
+
+    loop {
+        let mut body = json!({
+            "query": query,
+            "size": PAGE_SIZE,
+            "_source": false,
+            "sort": [{ "source_id": "asc" }]
+        });
+        if let Some(after) = search_after.take() {
+            body["search_after"] = after;
+        }
+
+        let response = es
+            .client()
+            .search(SearchParts::Index(&[&es.index_name]))
+            .body(body)
+            .send()
+            .await?
+            .error_for_status_code()?;
+        let body = response.json::<Value>().await?;
+        let hits = body["hits"]["hits"].as_array().cloned().unwrap_or_default();
+        if hits.is_empty() {
+            break;
+        }
+        search_after = hits.last().map(|hit| hit["sort"].clone());
+
+        // Look up the page's places concurrently; a refresh visits every place of the file
+        let ids: Vec<&str> = hits.iter().filter_map(|hit| hit["_id"].as_str()).collect();
+        let releases = &releases;
+        let owned: Vec<(&str, bool)> = stream::iter(ids)
+            .map(|id| async move {
+                let owned = release_place(scylla, id, releases).await?;
+                Ok::<_, anyhow::Error>((id, owned))
+            })
+            .buffer_unordered(CONCURRENCY)
+            .try_collect()
+            .await?;
+
+        let mut unowned = Vec::new();
+        for (id, owned) in owned {
+            if owned {
+                stats.kept += 1;
+            } else {
+                unowned.push(id.to_string());
+            }
+        }
+
+        if !unowned.is_empty() {
+            es.client()
+                .delete_by_query(DeleteByQueryParts::Index(&[&es.index_name]))
+                .body(json!({ "query": { "ids": { "values": unowned } } }))
+                .send()
+                .await?
+                .error_for_status_code()?;
+            stats.deleted += unowned.len();
+        }
+
+        if hits.len() < PAGE_SIZE {
+            break;
+        }
+    }
+
+    info!(
+        "Deleted {} documents, kept {} still contained in other source files",
+        stats.deleted, stats.kept
+    );
+    Ok(stats)
+}
+
+/// Remove the source files accepted by `releases` from a place, deleting it from ScyllaDB
+/// when none is left; returns whether another source file still contains it
+async fn release_place(
+    scylla: &ScyllaClient,
+    id: &str,
+    releases: &impl Fn(&str, i64) -> bool,
+) -> Result<bool> {
+    let owners = scylla.get_place_sources(id).await?;
+    let released: Vec<String> = owners
+        .iter()
+        .filter(|(file, seen)| releases(file, **seen))
+        .map(|(file, _)| file.clone())
+        .collect();
+    // Places written before ownership was tracked have no source files
+    let remaining = if owners.is_empty() || released.len() == owners.len() {
+        0
+    } else if released.is_empty() {
+        owners.len()
+    } else {
+        scylla.remove_place_sources(id, &released).await?.len()
+    };
+
+    if remaining == 0 {
+        scylla.delete_place(id).await?;
+    }
+    Ok(remaining > 0)
+}
+
+/// Elasticsearch-style wildcard match: `*` matches any sequence, `?` one character
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+
+    let (mut p, mut t) = (0, 0);
+    // Position after the last `*` and the text position it currently absorbs up to
+    let mut backtrack: Option<(usize, usize)> = None;
+    while t < text.len() {
+        if p < pattern.len() && (pattern[p] == '?' || pattern[p] == text[t]) {
+            p += 1;
+            t += 1;
+        } else if p < pattern.len() && pattern[p] == '*' {
+            p += 1;
+            backtrack = Some((p, t));
+        } else if let Some((star_p, star_t)) = backtrack {
+            p = star_p;
+            t = star_t + 1;
+            backtrack = Some((star_p, star_t + 1));
+        } else {
+            return false;
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match(
+            "germany-latest*",
+            "germany-latest-filtered.osm.pbf"
+        ));
+        assert!(wildcard_match("*albania*", "albania-latest.osm.pbf"));
+        assert!(wildcard_match("a?c", "abc"));
+        assert!(wildcard_match("*", ""));
+        assert!(!wildcard_match(
+            "germany-latest*",
+            "switzerland-latest.osm.pbf"
+        ));
+        assert!(!wildcard_match("*a*b", "ba"));
+    }
+}
@@ -0,0 +1,135 @@
+//! Postcode inference for places without `addr:postcode`.
+//!
+//! Usually only address points carry a postcode. Venues and streets without one take the
+//! postcode most common among the nearest address points of the same locality, and are
+//! marked with `postcode_inferred`.
+
+use rstar::primitives::GeomWithData;
+use rstar::RTree;
+
+use crate::models::{Address, AdminHierarchy, Layer, Place};
+
+/// Address points that vote on a postcode
+const NEIGHBOURS: usize = 5;
+
+/// Address points further away than this (in metres) do not vote
+const MAX_DISTANCE_M: f64 = 1000.0;
+
+pub const METRES_PER_DEGREE: f64 = 111_320.0;
+
+/// Address point: position (see [`project`]) with its locality and postcode
+type PostcodePoint = GeomWithData<[f64; 2], (Option<i64>, String)>;
+
+/// R-tree of the address points carrying a postcode
+pub struct PostcodeIndex {
+    tree: RTree<PostcodePoint>,
+}
+
+impl PostcodeIndex {
+    /// Build the index from `(lon, lat, locality, postcode)` of address points, see
+    /// [`locality_key`]
+    pub fn build(points: Vec<(f64, f64, Option<i64>, String)>) -> Self {
+        let points = points
+            .into_iter()
+            .map(|(lon, lat, locality, postcode)| {
+                PostcodePoint::new(project(lon, lat), (locality, postcode))
+            })
+            .collect();
+        Self {
+            tree: RTree::bulk_load(points),
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.tree.size()
+    }
+
+    /// Postcode most common among the nearest address points of `locality`; ties go to
+    /// the postcode of the nearest point
+    pub fn infer(&self, lon: f64, lat: f64, locality: Option<i64>) -> Option<&str> {
+        let max_distance_2 = (MAX_DISTANCE_M / METRES_PER_DEGREE).powi(2);
+
+        // (postcode, votes), in order of the nearest point
+        let mut votes: Vec<(&str, usize)> = Vec::new();
+        let neighbours = self
+            .tree
+            .nearest_neighbor_iter_with_distance_2(&project(lon, lat))
+            .take_while(|(_, distance_2)| *distance_2 <= max_distance_2)
+            .filter(|(point, _)| point.data.0 == locality)
+            .take(NEIGHBOURS);
+        for (point, _) in neighbours {
+            let postcode = point.data.1.as_str();
+            match votes.iter_mut().find(|(p, _)| *p == postcode) {
+                Some((_, count)) => *count += 1,
+                None => votes.push((postcode, 1)),
+            }
+        }
+
+        // max_by_key returns the last maximum, so search from the farthest
+        votes
+            .into_iter()
+            .rev()
+            .max_by_key(|(_, count)| *count)
+            .map(|(postcode, _)| postcode)
+    }
+
+    /// Give a venue, street or address without a postcode the inferred one; returns
+    /// whether it got one
+    pub fn apply(&self, place: &mut Place) -> bool {
+        if !matches!(place.layer, Layer::Venue | Layer::Street | Layer::Address)
+            || place.address.as_ref().is_some_and(|a| a.postcode.is_some())
+        {
+            return false;
+        }
+
+        let Some(postcode) = self.infer(
+            place.center_point.lon,
+            place.center_point.lat,
+            locality_key(&place.parent),
+        ) else {
+            return false;
+        };
+        let address = place.address.get_or_insert_with(Address::default);
+        address.postcode = Some(postcode.to_string());
+        address.postcode_inferred = true;
+        true
+    }
+}
+
+/// Locality a place is in for postcode voting: its locality, or its municipality outside
+/// of localities
+pub fn locality_key(parent: &AdminHierarchy) -> Option<i64> {
+    parent
+        .locality
+        .as_ref()
+        .or(parent.local_admin.as_ref())
+        .and_then(|entry| entry.id)
+}
+
+/// Degrees of latitude, with longitude scaled to the same length, so nearby distances
+/// compare correctly away from the equator
+pub fn project(lon: f64, lat: f64) -> [f64; 2] {
+    [lon * lat.to_radians().cos(), lat]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_infer() {
+        let index = PostcodeIndex::build(vec![
+            (8.5400, 47.3700, Some(1), "8001".to_string()),
+            (8.5402, 47.3701, Some(1), "8001".to_string()),
+            (8.5398, 47.3699, Some(1), "8002".to_string()),
+            (8.5401, 47.3700, Some(2), "8003".to_string()),
+        ]);
+
+        assert_eq!(index.infer(8.5401, 47.3700, Some(1)), Some("8001"));
+        // Only points of the same locality vote
+        assert_eq!(index.infer(8.5401, 47.3700, Some(2)), Some("8003"));
+        assert_eq!(index.infer(8.5401, 47.3700, Some(3)), None);
+        // Nor those too far away
+        assert_eq!(index.infer(8.6, 47.3700, Some(1)), None);
+    }
+}
@@ -0,0 +1,287 @@
+//! Linking address points to their street documents.
+//!
+//! Addresses only carry `addr:street`. [`StreetIndex`] finds the nearest street (as
+//! written to the index, so a merged road when roads are merged) of the same locality
+//! whose name matches it, allowing for abbreviations and typos ("Bahnhofstr",
+//! "Bahnhofstrase"). Addresses without a match are listed in the ingest report.
+
+use std::collections::HashSet;
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+use geo::LineString;
+use rstar::primitives::{GeomWithData, Line};
+use rstar::RTree;
+
+use crate::models::Place;
+use crate::text::street;
+
+use super::postcodes::{locality_key, project, METRES_PER_DEGREE};
+
+/// Streets further away from an address than this (in metres) are not considered
+const MAX_DISTANCE_M: f64 = 300.0;
+
+/// Street segment with the index of its street
+type StreetSegment = GeomWithData<Line<[f64; 2]>, usize>;
+
+struct Street {
+    id: String,
+    locality: Option<i64>,
+    /// [`street::keys`] of all its names
+    keys: Vec<String>,
+}
+
+/// Collects streets, see [`StreetIndex`]
+#[derive(Default)]
+pub struct StreetIndexBuilder {
+    streets: Vec<Street>,
+    segments: Vec<StreetSegment>,
+}
+
+impl StreetIndexBuilder {
+    /// Add a street place (with its admin hierarchy) and its geometry
+    pub fn add(&mut self, place: &Place, lines: &[LineString<f64>]) {
+        let mut keys: Vec<String> = place.name.values().flat_map(|n| street::keys(n)).collect();
+        keys.sort();
+        keys.dedup();
+        if keys.is_empty() {
+            return;
+        }
+
+        let street = self.streets.len();
+        for line in lines {
+            for segment in line.lines() {
+                let from = project(segment.start.x, segment.start.y);
+                let to = project(segment.end.x, segment.end.y);
+                self.segments
+                    .push(StreetSegment::new(Line::new(from, to), street));
+            }
+        }
+        self.streets.push(Street {
+            id: place.source_id.clone(),
+            locality: locality_key(&place.parent),
+            keys,
+        });
+    }
+
+    pub fn build(self) -> StreetIndex {
+        StreetIndex {
+            streets: self.streets,
+            tree: RTree::bulk_load(self.segments),
+        }
+    }
+}
+
+/// R-tree of street segments
+pub struct StreetIndex {
+    streets: Vec<Street>,
+    tree: RTree<StreetSegment>,
+}
+
+impl StreetIndex {
+    pub fn size(&self) -> usize {
+        self.streets.len()
+    }
+
+    /// ID of the nearest street of `locality` named like `name`
+    pub fn find(&self, lon: f64, lat: f64, locality: Option<i64>, name: &str) -> Option<&str> {
+        let keys = street::keys(name);
+        if keys.is_empty() {
+            return None;
+        }
+        let max_distance_2 = (MAX_DISTANCE_M / METRES_PER_DEGREE).powi(2);
+
+        self.tree
+            .nearest_neighbor_iter_with_distance_2(&project(lon, lat))
+            .take_while(|(_, distance_2)| *distance_2 <= max_distance_2)
+            .map(|(segment, _)| &self.streets[segment.data])
+            .filter(|street| street.locality == locality)
+            .find(|street| {
+                street
+                    .keys
+                    .iter()
+                    .any(|a| keys.iter().any(|b| street::keys_match(a, b)))
+            })
+            .map(|street| street.id.as_str())
+    }
+
+    /// Set the street of an address; returns `false` for an address whose street is not
+    /// found
+    pub fn link(&self, place: &mut Place) -> bool {
+        let Some(name) = place.address.as_ref().and_then(|a| a.street.as_deref()) else {
+            return true;
+        };
+        place.street_id = self
+            .find(
+                place.center_point.lon,
+                place.center_point.lat,
+                locality_key(&place.parent),
+                name,
+            )
+            .map(str::to_string);
+        place.street_id.is_some()
+    }
+}
+
+/// Addresses whose `addr:street` matches no street, written to
+/// `<tmp-dir>/reports/<file>.unmatched-streets.tsv`
+pub struct UnmatchedStreets {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    count: u64,
+    /// Addresses listed by an interrupted import. The objects after its last checkpoint
+    /// are processed again when resuming and must not be listed twice.
+    listed: HashSet<String>,
+}
+
+impl UnmatchedStreets {
+    /// Start the report, or continue the one of an interrupted import when `resume`
+    pub fn create(tmp_dir: &Path, source_file: &str, resume: bool) -> Result<Self> {
+        let dir = tmp_dir.join("reports");
+        std::fs::create_dir_all(&dir)?;
+        let path = dir.join(format!("{}.unmatched-streets.tsv", source_file));
+        let append = resume && path.exists();
+        let listed: HashSet<String> = if append {
+            let file =
+                File::open(&path).with_context(|| format!("Failed to open {}", path.display()))?;
+            BufReader::new(file)
+                .lines()
+                .skip(1)
+                .map(|line| Ok(line?.split('\t').next().unwrap_or_default().to_string()))
+                .collect::<Result<_>>()?
+        } else {
+            HashSet::new()
+        };
+        let file = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .append(append)
+            .truncate(!append)
+            .open(&path)
+            .with_context(|| format!("Failed to create {}", path.display()))?;
+        let mut writer = BufWriter::new(file);
+        if !append {
+            writeln!(writer, "id\tstreet\tlon\tlat")?;
+        }
+        Ok(Self {
+            path,
+            writer,
+            count: listed.len() as u64,
+            listed,
+        })
+    }
+
+    pub fn add(&mut self, place: &Place) -> Result<()> {
+        let street = place
+            .address
+            .as_ref()
+            .and_then(|a| a.street.as_deref())
+            .unwrap_or_default();
+        if self.listed.contains(&place.source_id) {
+            return Ok(());
+        }
+        writeln!(
+            self.writer,
+            "{}\t{}\t{}\t{}",
+            place.source_id,
+            street.replace(['\t', '\n'], " "),
+            place.center_point.lon,
+            place.center_point.lat
+        )?;
+        self.count += 1;
+        Ok(())
+    }
+
+    /// Number of addresses listed (including those of an interrupted import) and the
+    /// report file
+    pub fn finish(mut self) -> Result<(u64, PathBuf)> {
+        self.writer.flush()?;
+        Ok((self.count, self.path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{Address, GeoPoint, Layer, OsmType};
+
+    fn place(layer: Layer, lon: f64, lat: f64) -> Place {
+        Place::new(
+            OsmType::Way,
+            1,
+            layer,
+            GeoPoint { lat, lon },
+            "test.osm.pbf",
+        )
+    }
+
+    #[test]
+    fn test_link() {
+        let mut builder = StreetIndexBuilder::default();
+        let mut street = place(Layer::Street, 8.540, 47.370);
+        street.add_name("default", "Bahnhofstrasse".to_string());
+        builder.add(
+            &street,
+            &[LineString::from(vec![(8.539, 47.370), (8.541, 47.370)])],
+        );
+        let mut other = place(Layer::Street, 8.540, 47.3705);
+        other.source_id = "way/2".to_string();
+        other.add_name("default", "Bahnhofplatz".to_string());
+        builder.add(
+            &other,
+            &[LineString::from(vec![(8.539, 47.3705), (8.541, 47.3705)])],
+        );
+        let index = builder.build();
+
+        let address = |name: &str, lat: f64| {
+            let mut address = place(Layer::Address, 8.5401, lat);
+            address.address = Some(Address {
+                street: Some(name.to_string()),
+                ..Default::default()
+            });
+            address
+        };
+
+        // The nearest street is Bahnhofplatz, but the name decides
+        let mut a = address("Bahnhofstr.", 47.3704);
+        assert!(index.link(&mut a));
+        assert_eq!(a.street_id.as_deref(), Some("way/1"));
+
+        let mut b = address("Bahnhofstrase", 47.3701);
+        assert!(index.link(&mut b));
+        assert_eq!(b.street_id.as_deref(), Some("way/1"));
+
+        let mut c = address("Seestrasse", 47.3701);
+        assert!(!index.link(&mut c));
+        assert!(c.street_id.is_none());
+
+        // Too far away
+        let mut d = address("Bahnhofstrasse", 47.38);
+        assert!(!index.link(&mut d));
+    }
+
+    #[test]
+    fn test_unmatched_resume() {
+        let dir = tempfile::tempdir().unwrap();
+        let address = |id: &str| {
+            let mut address = place(Layer::Address, 8.54, 47.37);
+            address.source_id = id.to_string();
+            address
+        };
+
+        let mut report = UnmatchedStreets::create(dir.path(), "test.osm.pbf", false).unwrap();
+        report.add(&address("node/1")).unwrap();
+        report.add(&address("node/2")).unwrap();
+        report.finish().unwrap();
+
+        // node/2 came after the last checkpoint and is processed again
+        let mut report = UnmatchedStreets::create(dir.path(), "test.osm.pbf", true).unwrap();
+        report.add(&address("node/2")).unwrap();
+        report.add(&address("node/3")).unwrap();
+        let (count, path) = report.finish().unwrap();
+        assert_eq!(count, 3);
+        assert_eq!(std::fs::read_to_string(path).unwrap().lines().count(), 4);
+    }
+}
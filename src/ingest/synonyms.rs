@@ -1,23 +1,59 @@
 use anyhow::{Context, Result};
 use regex::Regex;
-use std::collections::HashMap;
+use std::collections::{BTreeSet, HashMap};
 use std::fs;
 use std::path::Path;
 use tracing::{info, warn};
 use walkdir::WalkDir;
 
+use crate::models::{Layer, Place};
+use crate::text::street;
+
+/// Upper bound on expanded variants per name (token alternatives multiply)
+const MAX_VARIANTS: usize = 8;
+
+/// Synonym groups from one set of files
+#[derive(Debug, Clone, Default)]
+struct SynonymTable {
+    /// Each group lists its canonical form first
+    groups: Vec<Vec<String>>,
+    /// Token -> group it can be replaced with
+    index: HashMap<String, usize>,
+}
+
+impl SynonymTable {
+    fn add_group(&mut self, members: Vec<String>, replaceable: &[String]) {
+        let id = self.groups.len();
+        for token in replaceable {
+            self.index.insert(token.clone(), id);
+        }
+        self.groups.push(members);
+    }
+
+    fn group(&self, token: &str) -> Option<&[String]> {
+        self.index.get(token).map(|&id| self.groups[id].as_slice())
+    }
+
+    fn len(&self) -> usize {
+        self.index.len()
+    }
+}
+
 /// Manages synonym mappings for text normalization/expansion.
-#[derive(Debug, Clone)]
+///
+/// Files named after a language (`streets/de.txt`) only apply to names in that language;
+/// other files (`custom_street.txt`) apply to all names. Country codes only apply to
+/// countries.
+#[derive(Debug, Clone, Default)]
 pub struct SynonymService {
-    /// Token -> Normalized Text replacement
-    replacements: HashMap<String, String>,
+    /// Language -> synonyms (`None` for files without a language)
+    tables: HashMap<Option<String>, SynonymTable>,
+    country_codes: SynonymTable,
 }
 
 impl SynonymService {
     pub fn new() -> Self {
-        Self {
-            replacements: HashMap::new(),
-        }
+        Self::default()
     }
 
     /// Load synonyms from a directory, recursively.
@@ -52,88 +88,277 @@ impl SynonymService {
                 continue;
             }
 
-            self.load_file(path)?;
+            let table = if path.components().any(|c| c.as_os_str() == "country_codes") {
+                &mut self.country_codes
+            } else {
+                self.tables.entry(file_language(path)).or_default()
+            };
+            load_file(table, path)?;
         }
 
-        info!("Loaded {} synonym mappings", self.replacements.len());
+        let count: usize = self.tables.values().map(SynonymTable::len).sum();
+        info!(
+            "Loaded {} synonym mappings in {} languages",
+            count + self.country_codes.len(),
+            self.tables.keys().flatten().count()
+        );
         Ok(())
     }
 
-    fn load_file(&mut self, path: &Path) -> Result<()> {
-        let content = fs::read_to_string(path)
-            .with_context(|| format!("Failed to read synonym file: {}", path.display()))?;
+    /// Variants of a name with synonyms applied: the normalized form (every known token
+    /// replaced by its canonical form) followed by expansions with the other
+    /// alternatives. Empty if no token has a synonym.
+    pub fn variants(&self, text: &str, languages: &[&str], country: bool) -> Vec<String> {
+        let tokens = self.tokens(text, languages, country);
+        if tokens
+            .iter()
+            .all(|(_, alternatives)| alternatives.len() == 1)
+        {
+            return Vec::new();
+        }
+
+        let mut variants = vec![String::new()];
+        for (_, alternatives) in &tokens {
+            variants = variants
+                .iter()
+                .flat_map(|prefix| {
+                    alternatives.iter().map(move |token| {
+                        if prefix.is_empty() {
+                            token.clone()
+                        } else {
+                            format!("{} {}", prefix, token)
+                        }
+                    })
+                })
+                .take(MAX_VARIANTS)
+                .collect();
+        }
 
-        // Regexes for parsing
-        // We compile them here for simplicity, or could move to struct/lazy_static if perf critical
-        let comment_regex = Regex::new(r"#.*").unwrap();
-        let whitespace_regex = Regex::new(r"\s+").unwrap();
-        let arrow_regex = Regex::new(r"=>").unwrap();
-        let comma_regex = Regex::new(r",").unwrap();
+        let original: Vec<&str> = tokens.iter().map(|(token, _)| token.as_str()).collect();
+        let original = original.join(" ");
+        variants.retain(|v| *v != original);
+        variants
+    }
 
-        for line in content.lines() {
-            // Clean line: remove comments, trim, lowercase
-            let line = comment_regex.replace(line, "");
-            let line = line.trim().to_lowercase();
+    /// Synonym variants of all names of a place, in the language of each name. Street
+    /// names also get their decompounded forms ("Bahnhofstraße" -> "bahnhof strasse").
+    pub fn place_synonyms(&self, place: &Place) -> Vec<String> {
+        let country = place.layer == Layer::Country;
+        let mut synonyms = BTreeSet::new();
 
-            if line.is_empty() {
-                continue;
+        if place.layer == Layer::Street {
+            let country = place.parent.country.as_ref();
+            let languages =
+                street::name_languages(&place.name, country.and_then(|c| c.alpha2.as_deref()));
+            for name in place.name.values() {
+                synonyms.extend(street::variants(name, &languages));
             }
+        }
 
-            // Squash double spaces
-            let line = whitespace_regex.replace_all(&line, " ");
-
-            // Parse
-            if line.contains("=>") {
-                // explicit mapping: left => right
-                let parts: Vec<&str> = arrow_regex.split(&line).collect();
-                if parts.len() == 2 {
-                    let lefts: Vec<&str> = comma_regex.split(parts[0]).collect();
-                    let rights: Vec<&str> = comma_regex.split(parts[1]).collect();
-
-                    // Assume first item on right is canonical
-                    if let Some(target) = rights.first() {
-                        let target = target.trim().to_string();
-                        for src in lefts {
-                            let src = src.trim();
-                            if !src.is_empty() && src != target {
-                                self.replacements.insert(src.to_string(), target.clone());
-                            }
-                        }
-                    }
-                }
+        for (key, name) in &place.name {
+            // The untagged name is in the languages it is also tagged with
+            let languages: Vec<&str> = if key == "default" {
+                place
+                    .name
+                    .iter()
+                    .filter(|(k, n)| *k != "default" && *n == name)
+                    .map(|(k, _)| primary_language(k))
+                    .collect()
             } else {
-                // equivalent list: "a, b, c" => map b->a, c->a
-                let parts: Vec<&str> = comma_regex.split(&line).collect();
-                if let Some(canon) = parts.first() {
-                    let canon = canon.trim().to_string();
-                    for variant in parts.iter().skip(1) {
-                        let variant = variant.trim();
-                        if !variant.is_empty() && *variant != canon {
-                            self.replacements.insert(variant.to_string(), canon.clone());
+                vec![primary_language(key)]
+            };
+
+            synonyms.extend(self.variants(name, &languages, country));
+        }
+
+        // Keep only variants that are not names already
+        let names: BTreeSet<String> = place.name.values().map(|n| n.to_lowercase()).collect();
+        synonyms
+            .into_iter()
+            .filter(|s| !names.contains(s))
+            .collect()
+    }
+
+    /// Each token of `text` with its alternatives, canonical form first
+    fn tokens(&self, text: &str, languages: &[&str], country: bool) -> Vec<(String, Vec<String>)> {
+        let tables: Vec<&SynonymTable> = languages
+            .iter()
+            .filter_map(|l| self.tables.get(&Some(l.to_string())))
+            .chain(self.tables.get(&None))
+            .chain(country.then_some(&self.country_codes))
+            .collect();
+
+        text.split_whitespace()
+            .map(|token| {
+                // strip punctuation
+                let clean_token = token
+                    .trim_matches(|c: char| !c.is_alphanumeric())
+                    .to_lowercase();
+
+                let alternatives = match tables.iter().find_map(|t| t.group(&clean_token)) {
+                    Some(group) => {
+                        let mut alternatives = group.to_vec();
+                        if !alternatives.contains(&clean_token) {
+                            alternatives.push(clean_token.clone());
                         }
+                        alternatives
                     }
-                }
-            }
+                    None => vec![clean_token.clone()],
+                };
+                (clean_token, alternatives)
+            })
+            .filter(|(token, _)| !token.is_empty())
+            .collect()
+    }
+}
+
+/// Language of a synonym file: `de.txt` -> `de`; `streets/usps.txt` is US English
+fn file_language(path: &Path) -> Option<String> {
+    let stem = path.file_stem()?.to_str()?;
+    match stem {
+        "usps" => Some("en".to_string()),
+        _ if stem.len() == 2 && stem.chars().all(|c| c.is_ascii_lowercase()) => {
+            Some(stem.to_string())
         }
-        Ok(())
+        _ => None,
     }
+}
 
-    /// Apply synonyms to a text string.
-    pub fn normalize(&self, text: &str) -> String {
-        let mut result = Vec::new();
-        // Simple whitespace split for now
-        for token in text.split_whitespace() {
-            // strip punctuation
-            let clean_token = token
-                .trim_matches(|c: char| !c.is_alphanumeric())
-                .to_lowercase();
-
-            if let Some(replacement) = self.replacements.get(&clean_token) {
-                result.push(replacement.as_str());
-            } else {
-                result.push(token);
+/// `de-CH` -> `de`
+fn primary_language(key: &str) -> &str {
+    key.split(['-', '_']).next().unwrap_or(key)
+}
+
+fn load_file(table: &mut SynonymTable, path: &Path) -> Result<()> {
+    let content = fs::read_to_string(path)
+        .with_context(|| format!("Failed to read synonym file: {}", path.display()))?;
+
+    // Regexes for parsing
+    // We compile them here for simplicity, or could move to struct/lazy_static if perf critical
+    let comment_regex = Regex::new(r"#.*").unwrap();
+    let whitespace_regex = Regex::new(r"\s+").unwrap();
+    let arrow_regex = Regex::new(r"=>").unwrap();
+    let comma_regex = Regex::new(r",").unwrap();
+
+    let split = |text: &str| -> Vec<String> {
+        comma_regex
+            .split(text)
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+            .collect()
+    };
+
+    for line in content.lines() {
+        // Clean line: remove comments, trim, lowercase
+        let line = comment_regex.replace(line, "");
+        let line = line.trim().to_lowercase();
+
+        if line.is_empty() {
+            continue;
+        }
+
+        // Squash double spaces
+        let line = whitespace_regex.replace_all(&line, " ");
+
+        // Parse
+        if line.contains("=>") {
+            // explicit mapping: left => right, only the left side is replaced
+            let parts: Vec<&str> = arrow_regex.split(&line).collect();
+            if parts.len() == 2 {
+                let lefts = split(parts[0]);
+                let mut members = split(parts[1]);
+
+                // Assume first item on right is canonical
+                if !members.is_empty() {
+                    let lefts: Vec<String> =
+                        lefts.into_iter().filter(|l| *l != members[0]).collect();
+                    members.extend(lefts.iter().cloned());
+                    table.add_group(members, &lefts);
+                }
+            }
+        } else {
+            // equivalent list: "a, b, c", a is canonical
+            let members = split(&line);
+            if members.len() > 1 {
+                table.add_group(members.clone(), &members);
             }
         }
-        result.join(" ")
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{GeoPoint, OsmType};
+
+    fn service() -> SynonymService {
+        let dir = tempfile::tempdir().unwrap();
+        fs::create_dir(dir.path().join("streets")).unwrap();
+        fs::write(
+            dir.path().join("streets/de.txt"),
+            "straße, str, strasse\nplatz, pl\n",
+        )
+        .unwrap();
+        fs::write(dir.path().join("streets/en.txt"), "street, st, str\n").unwrap();
+        fs::write(
+            dir.path().join("custom_street.txt"),
+            "# comment\nbhf => bahnhof\n",
+        )
+        .unwrap();
+
+        let mut service = SynonymService::new();
+        service.load_from_dir(dir.path()).unwrap();
+        service
+    }
+
+    #[test]
+    fn test_variants_by_language() {
+        let service = service();
+        assert_eq!(
+            service.variants("Bahnhof Str.", &["de"], false)[0],
+            "bahnhof straße"
+        );
+        assert_eq!(
+            service.variants("Main Str.", &["en"], false)[0],
+            "main street"
+        );
+        assert_eq!(service.variants("Am Bhf", &[], false), ["am bahnhof"]);
+        assert!(service.variants("Bahnhof", &["de"], false).is_empty());
+        // One-way mapping: the target is not expanded
+        assert!(service.variants("Am Bahnhof", &[], false).is_empty());
+
+        let variants = service.variants("Post Pl.", &["de"], false);
+        assert_eq!(variants[0], "post platz");
+        assert!(variants.len() <= MAX_VARIANTS);
+    }
+
+    #[test]
+    fn test_place_synonyms() {
+        let service = service();
+        let mut place = Place::new(
+            OsmType::Way,
+            1,
+            Layer::Street,
+            GeoPoint {
+                lat: 47.0,
+                lon: 8.0,
+            },
+            "test.osm.pbf",
+        );
+        place.add_name("default", "Zürcher Str.".to_string());
+        place.add_name("de", "Zürcher Str.".to_string());
+        place.add_name("en", "Zurich St".to_string());
+
+        let synonyms = service.place_synonyms(&place);
+        assert!(synonyms.contains(&"zürcher straße".to_string()));
+        assert!(synonyms.contains(&"zurich street".to_string()));
+        // English rules are not applied to the German name
+        assert!(!synonyms.contains(&"zürcher street".to_string()));
+
+        place.add_name("de", "Zürcherstrasse".to_string());
+        assert!(service
+            .place_synonyms(&place)
+            .contains(&"zürcher strasse".to_string()));
     }
 }
@@ -1,5 +1,5 @@
+use crate::elasticsearch::EsClient;
 use anyhow::{Context, Result};
-use cypress::elasticsearch::EsClient;
 use elasticsearch::indices::{IndicesCreateParts, IndicesExistsParts};
 use elasticsearch::SearchParts;
 use serde::{Deserialize, Serialize};
@@ -12,8 +12,8 @@ use osmpbfreader::{Tags, WayId};
 use std::sync::Arc;
 use tracing::info;
 
-use cypress::models::{GeoBbox, GeoPoint, Layer, OsmType, Place};
-use cypress::pip::GeometryResolver;
+use crate::models::{GeoBbox, GeoPoint, Layer, OsmType, Place};
+use crate::pip::{antimeridian_bbox, GeometryResolver};
 
 /// Represents a road way eligible for merging
 #[derive(Debug, Clone)]
@@ -52,7 +52,13 @@ impl WayMerger {
     }
 
     /// Add a road way to be considered for merging
-    pub fn add_road(&mut self, way_id: WayId, tags: Tags, nodes: Vec<i64>, merge_key: Option<String>) {
+    pub fn add_road(
+        &mut self,
+        way_id: WayId,
+        tags: Tags,
+        nodes: Vec<i64>,
+        merge_key: Option<String>,
+    ) {
         // Get the name for grouping
         let key = merge_key.or_else(|| Self::get_merge_key(&tags));
 
@@ -106,7 +112,7 @@ impl WayMerger {
                 let way = ways.remove(0);
                 merged_roads.push(MergedRoad {
                     way_ids: vec![way.way_id],
-                    line_strings: vec![Self::get_linestring_static(&resolver, &way)],
+                    line_strings: vec![way_line(&resolver, &way.nodes)],
                     tags: way.tags,
                 });
                 continue;
@@ -123,7 +129,7 @@ impl WayMerger {
                 let way_ids: Vec<_> = group.iter().map(|w| w.way_id).collect();
                 let line_strings: Vec<_> = group
                     .iter()
-                    .map(|w| Self::get_linestring_static(&resolver, w))
+                    .map(|w| way_line(&resolver, &w.nodes))
                     .collect();
                 let tags = group[0].tags.clone();
 
@@ -197,22 +203,21 @@ impl WayMerger {
 
         false
     }
+}
 
-    /// Convert a RoadWay to a LineString (static version)
-    fn get_linestring_static(resolver: &Arc<GeometryResolver>, way: &RoadWay) -> LineString<f64> {
-        let coords: Vec<Coord<f64>> = way
-            .nodes
-            .iter()
-            .filter_map(|&node_id| {
-                // Access the resolver's get_node_coords method
-                // We need to convert i64 to NodeId
-                let node_id = osmpbfreader::NodeId(node_id);
-                resolver.get_node_coords(node_id)
-            })
-            .collect();
-
-        LineString::new(coords)
-    }
+/// Line of a way through its nodes
+pub fn way_line(resolver: &GeometryResolver, nodes: &[i64]) -> LineString<f64> {
+    let coords: Vec<Coord<f64>> = nodes
+        .iter()
+        .filter_map(|&node_id| {
+            // Access the resolver's get_node_coords method
+            // We need to convert i64 to NodeId
+            let node_id = osmpbfreader::NodeId(node_id);
+            resolver.get_node_coords(node_id)
+        })
+        .collect();
+
+    LineString::new(coords)
 }
 
 impl MergedRoad {
@@ -227,10 +232,11 @@ impl MergedRoad {
             lon: p.x(),
         })?;
 
-        // Calculate bounding box
-        let bbox = multi_line
-            .bounding_rect()
-            .map(|rect| GeoBbox::new(rect.min().x, rect.min().y, rect.max().x, rect.max().y));
+        // Calculate bounding box (antimeridian-aware)
+        let bbox = antimeridian_bbox(multi_line.iter().filter_map(|line| line.bounding_rect()))
+            .map(|(min_lon, min_lat, max_lon, max_lat)| {
+                GeoBbox::new(min_lon, min_lat, max_lon, max_lat)
+            });
 
         // Use the first way ID as the representative
         let osm_id = self.way_ids[0].0;
@@ -0,0 +1,199 @@
+//! OSM input files.
+//!
+//! [`OsmInput`] hides the file format from the ingest pipeline. Every call to
+//! [`OsmInput::objects`] starts a fresh pass over the file, which is what the multi-pass
+//! geometry index and admin extraction need. Supported formats:
+//!
+//! - PBF (`.osm.pbf`, `.pbf`)
+//! - OSM XML (`.osm`, `.xml`, JOSM exports), optionally gzipped (`.osm.gz`)
+//! - OPL (`.opl`), optionally gzipped (`.opl.gz`)
+
+mod opl;
+mod xml;
+
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+use flate2::read::MultiGzDecoder;
+use osmpbfreader::{blobs, OsmObj, OsmPbfReader};
+
+pub use opl::OplObjects;
+pub use xml::XmlObjects;
+
+/// Iterator over the objects of one pass through an input
+pub type OsmObjects = Box<dyn Iterator<Item = Result<OsmObj>> + Send>;
+
+/// File format of an OSM input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputFormat {
+    Pbf,
+    Xml,
+    Opl,
+}
+
+impl InputFormat {
+    /// Detect the format from the file name, ignoring a trailing `.gz`
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let name = path.file_name()?.to_str()?.to_lowercase();
+        let name = name.strip_suffix(".gz").unwrap_or(&name);
+
+        if name.ends_with(".pbf") {
+            Some(Self::Pbf)
+        } else if name.ends_with(".osm") || name.ends_with(".xml") {
+            Some(Self::Xml)
+        } else if name.ends_with(".opl") {
+            Some(Self::Opl)
+        } else {
+            None
+        }
+    }
+}
+
+impl FromStr for InputFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "pbf" => Ok(Self::Pbf),
+            "xml" | "osm" => Ok(Self::Xml),
+            "opl" => Ok(Self::Opl),
+            _ => Err(format!("unknown input format '{}'", s)),
+        }
+    }
+}
+
+/// An OSM data file that can be read any number of times
+#[derive(Debug, Clone)]
+pub struct OsmInput {
+    path: PathBuf,
+    format: InputFormat,
+    gzip: bool,
+}
+
+impl OsmInput {
+    /// Open a file, detecting its format from the extension
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let format = InputFormat::from_path(&path).with_context(|| {
+            format!(
+                "Unknown OSM file format for {} (expected .osm.pbf, .osm, .osm.gz or .opl)",
+                path.display()
+            )
+        })?;
+        Ok(Self::with_format(path, format))
+    }
+
+    /// Open a file with an explicit format
+    pub fn with_format(path: impl Into<PathBuf>, format: InputFormat) -> Self {
+        let path = path.into();
+        let gzip = path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("gz"));
+        Self { path, format, gzip }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn format(&self) -> InputFormat {
+        self.format
+    }
+
+    /// Start a new pass over all objects of the file
+    pub fn objects(&self) -> Result<OsmObjects> {
+        let file = File::open(&self.path)
+            .with_context(|| format!("Failed to open OSM file {}", self.path.display()))?;
+
+        Ok(match self.format {
+            InputFormat::Pbf => Box::new(PbfObjects::new(BufReader::new(file))),
+            InputFormat::Xml => Box::new(XmlObjects::new(self.text_reader(file))),
+            InputFormat::Opl => Box::new(OplObjects::new(self.text_reader(file))),
+        })
+    }
+
+    fn text_reader(&self, file: File) -> Box<dyn BufRead + Send> {
+        if self.gzip {
+            Box::new(BufReader::new(MultiGzDecoder::new(file)))
+        } else {
+            Box::new(BufReader::new(file))
+        }
+    }
+}
+
+/// Owning iterator over the objects of a PBF file
+///
+/// Each block is decoded up front: the decoder's iterator is not `Send`, and a block
+/// holds at most a few thousand objects.
+struct PbfObjects<R> {
+    reader: OsmPbfReader<R>,
+    block: std::vec::IntoIter<osmpbfreader::Result<OsmObj>>,
+}
+
+impl<R: std::io::Read> PbfObjects<R> {
+    fn new(reader: R) -> Self {
+        Self {
+            reader: OsmPbfReader::new(reader),
+            block: Vec::new().into_iter(),
+        }
+    }
+}
+
+impl<R: std::io::Read> Iterator for PbfObjects<R> {
+    type Item = Result<OsmObj>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(obj) = self.block.next() {
+                return Some(obj.map_err(Into::into));
+            }
+            let blob = self.reader.blobs().next()?;
+            self.block = blobs::result_blob_into_iter(blob)
+                .collect::<Vec<_>>()
+                .into_iter();
+        }
+    }
+}
+
+/// Path of a test fixture in `tests/fixtures`
+#[cfg(test)]
+pub(crate) fn fixture(name: &str) -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures")
+        .join(name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_format() {
+        let detect = |p: &str| InputFormat::from_path(Path::new(p));
+        assert_eq!(detect("switzerland-latest.osm.pbf"), Some(InputFormat::Pbf));
+        assert_eq!(detect("export.osm"), Some(InputFormat::Xml));
+        assert_eq!(detect("export.osm.gz"), Some(InputFormat::Xml));
+        assert_eq!(detect("extract.opl"), Some(InputFormat::Opl));
+        assert_eq!(detect("notes.txt"), None);
+    }
+
+    #[test]
+    fn test_xml_and_opl_fixtures_match() {
+        let read = |name: &str| -> Vec<OsmObj> {
+            OsmInput::open(fixture(name))
+                .unwrap()
+                .objects()
+                .unwrap()
+                .collect::<Result<_>>()
+                .unwrap()
+        };
+
+        let xml = read("village.osm");
+        let opl = read("village.opl");
+        assert_eq!(xml, opl);
+        assert!(xml.iter().any(|o| o.is_relation()));
+    }
+}
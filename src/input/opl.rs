@@ -0,0 +1,200 @@
+//! Reader for the OPL text format (one object per line, as written by osmium).
+//!
+//! ```text
+//! n1 v1 x8.5417 y47.3769 Tname=Z%20%rich,amenity=cafe
+//! w2 Thighway=residential Nn1,n3
+//! r3 Ttype=multipolygon Mw2@outer
+//! ```
+//!
+//! Unknown fields (version, changeset, user, ...) are ignored; objects with `dD` are skipped.
+
+use std::io::BufRead;
+
+use anyhow::{bail, Context, Result};
+use osmpbfreader::{Node, NodeId, OsmId, OsmObj, Ref, Relation, RelationId, Tags, Way, WayId};
+
+/// Iterator over the objects of an OPL file
+pub struct OplObjects<R> {
+    lines: std::io::Lines<R>,
+    line_number: usize,
+}
+
+impl<R: BufRead> OplObjects<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            lines: reader.lines(),
+            line_number: 0,
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for OplObjects<R> {
+    type Item = Result<OsmObj>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let line = match self.lines.next()? {
+                Ok(line) => line,
+                Err(e) => return Some(Err(e.into())),
+            };
+            self.line_number += 1;
+
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            match parse_line(line) {
+                Ok(Some(obj)) => return Some(Ok(obj)),
+                Ok(None) => continue,
+                Err(e) => return Some(Err(e.context(format!("OPL line {}", self.line_number)))),
+            }
+        }
+    }
+}
+
+fn parse_line(line: &str) -> Result<Option<OsmObj>> {
+    let mut fields = line.split(' ').filter(|f| !f.is_empty());
+    let head = fields.next().context("Empty line")?;
+    let (kind, id) = split_key(head)?;
+    let id: i64 = id
+        .parse()
+        .with_context(|| format!("Invalid object id '{}'", head))?;
+
+    let mut tags = Tags::new();
+    let mut lon = None;
+    let mut lat = None;
+    let mut nodes = Vec::new();
+    let mut refs = Vec::new();
+
+    for field in fields {
+        let (key, value) = split_key(field)?;
+        match key {
+            'd' if value == "D" => return Ok(None),
+            'T' if !value.is_empty() => {
+                for tag in value.split(',') {
+                    let (k, v) = tag.split_once('=').unwrap_or((tag, ""));
+                    tags.insert(unescape(k)?.into(), unescape(v)?.into());
+                }
+            }
+            'x' if !value.is_empty() => lon = Some(value.parse::<f64>()?),
+            'y' if !value.is_empty() => lat = Some(value.parse::<f64>()?),
+            'N' if !value.is_empty() => {
+                for node in value.split(',') {
+                    let id = node.strip_prefix('n').unwrap_or(node);
+                    nodes.push(NodeId(id.parse()?));
+                }
+            }
+            'M' if !value.is_empty() => {
+                for member in value.split(',') {
+                    let (target, role) = member.split_once('@').unwrap_or((member, ""));
+                    let (kind, id) = split_key(target)?;
+                    let id: i64 = id.parse()?;
+                    let member = match kind {
+                        'n' => OsmId::Node(NodeId(id)),
+                        'w' => OsmId::Way(WayId(id)),
+                        'r' => OsmId::Relation(RelationId(id)),
+                        other => bail!("Unknown member type '{}'", other),
+                    };
+                    refs.push(Ref {
+                        member,
+                        role: unescape(role)?.into(),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Ok(Some(match kind {
+        'n' => {
+            let (Some(lon), Some(lat)) = (lon, lat) else {
+                // Nodes without a location carry nothing we can use
+                return Ok(None);
+            };
+            OsmObj::Node(Node {
+                id: NodeId(id),
+                tags,
+                decimicro_lat: (lat * 1e7).round() as i32,
+                decimicro_lon: (lon * 1e7).round() as i32,
+            })
+        }
+        'w' => OsmObj::Way(Way {
+            id: WayId(id),
+            tags,
+            nodes,
+        }),
+        'r' => OsmObj::Relation(Relation {
+            id: RelationId(id),
+            tags,
+            refs,
+        }),
+        // Changesets and unknown types
+        _ => return Ok(None),
+    }))
+}
+
+/// Split the one-letter key off a field or member: "x8.5" -> ('x', "8.5")
+fn split_key(field: &str) -> Result<(char, &str)> {
+    match field.chars().next() {
+        Some(key) if key.is_ascii_alphabetic() => Ok((key, &field[1..])),
+        _ => bail!("Invalid field '{}'", field),
+    }
+}
+
+/// Decode OPL escapes: `%<hex code point>%`
+fn unescape(value: &str) -> Result<String> {
+    if !value.contains('%') {
+        return Ok(value.to_string());
+    }
+
+    let mut out = String::with_capacity(value.len());
+    let mut parts = value.split('%');
+    out.push_str(parts.next().unwrap_or(""));
+    while let Some(code) = parts.next() {
+        let c = u32::from_str_radix(code, 16)
+            .ok()
+            .and_then(char::from_u32)
+            .with_context(|| format!("Invalid escape %{}% in '{}'", code, value))?;
+        out.push(c);
+        out.push_str(parts.next().unwrap_or(""));
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_lines() {
+        let opl = "n1 v2 dV c3 t2024-01-01T00:00:00Z i4 uuser Tname=Z%fc%rich%20%HB,railway=station x8.5402 y47.3782\n\
+                   n2 v1 dD c3 t2024-01-01T00:00:00Z i4 uuser T x8.5 y47.3\n\
+                   w3 v1 Thighway=residential Nn1,n5\n\
+                   r4 v1 Ttype=multipolygon Mw3@outer,n1@";
+
+        let objs: Vec<_> = OplObjects::new(opl.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap();
+        assert_eq!(objs.len(), 3);
+
+        let node = objs[0].node().unwrap();
+        assert_eq!(node.tags.get("name").unwrap(), "Zürich HB");
+        assert_eq!(node.decimicro_lon, 85402000);
+
+        assert_eq!(objs[1].way().unwrap().nodes, vec![NodeId(1), NodeId(5)]);
+
+        let rel = objs[2].relation().unwrap();
+        assert_eq!(rel.refs.len(), 2);
+        assert_eq!(rel.refs[1].member, OsmId::Node(NodeId(1)));
+        assert_eq!(rel.refs[1].role, "");
+    }
+
+    #[test]
+    fn test_invalid_fields() {
+        for line in ["n1 üx8.5 y47.3", "r1 Mü1@outer", "ü1 x8.5"] {
+            let result: Result<Vec<_>> = OplObjects::new(line.as_bytes()).collect();
+            assert!(result.is_err(), "{}", line);
+        }
+    }
+}
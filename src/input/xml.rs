@@ -0,0 +1,352 @@
+//! Streaming reader for OSM XML (`.osm`, JOSM exports).
+//!
+//! Only the elements needed for ingest are understood: `node`, `way`, `relation`, `tag`,
+//! `nd` and `member`. Objects marked `action="delete"` or `visible="false"` are skipped.
+
+use std::io::BufRead;
+
+use anyhow::{bail, Context, Result};
+use osmpbfreader::{Node, NodeId, OsmId, OsmObj, Ref, Relation, RelationId, Tags, Way, WayId};
+
+/// Iterator over the objects of an OSM XML document
+pub struct XmlObjects<R> {
+    reader: R,
+    buf: Vec<u8>,
+    current: Option<OsmObj>,
+    /// The current object is deleted and will be dropped when it ends
+    skip_current: bool,
+    done: bool,
+}
+
+/// A start, end or empty element with its attributes
+struct Element {
+    name: String,
+    attrs: Vec<(String, String)>,
+    closing: bool,
+    empty: bool,
+}
+
+impl Element {
+    fn attr(&self, key: &str) -> Option<&str> {
+        self.attrs
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    }
+
+    fn required(&self, key: &str) -> Result<&str> {
+        self.attr(key)
+            .with_context(|| format!("<{}> without {} attribute", self.name, key))
+    }
+
+    fn id(&self) -> Result<i64> {
+        self.required("id")?
+            .parse()
+            .with_context(|| format!("Invalid id on <{}>", self.name))
+    }
+
+    fn is_deleted(&self) -> bool {
+        self.attr("action") == Some("delete") || self.attr("visible") == Some("false")
+    }
+}
+
+impl<R: BufRead> XmlObjects<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            buf: Vec::new(),
+            current: None,
+            skip_current: false,
+            done: false,
+        }
+    }
+
+    /// Read the next element, skipping text, comments, declarations and doctypes
+    fn next_element(&mut self) -> Result<Option<Element>> {
+        loop {
+            // Skip to the next '<'
+            self.buf.clear();
+            if self.reader.read_until(b'<', &mut self.buf)? == 0 || !self.buf.ends_with(b"<") {
+                return Ok(None);
+            }
+
+            // Read up to the closing '>' outside of quoted attribute values
+            self.buf.clear();
+            loop {
+                if self.reader.read_until(b'>', &mut self.buf)? == 0 {
+                    bail!("Unexpected end of XML inside a tag");
+                }
+                if self.buf.starts_with(b"!--") {
+                    if self.buf.ends_with(b"-->") {
+                        break;
+                    }
+                } else if !in_quotes(&self.buf) {
+                    break;
+                }
+            }
+
+            let body = std::str::from_utf8(&self.buf[..self.buf.len() - 1])
+                .context("Invalid UTF-8 in XML")?;
+            if body.starts_with('!') || body.starts_with('?') {
+                continue;
+            }
+            return parse_element(body).map(Some);
+        }
+    }
+
+    fn handle(&mut self, element: Element) -> Result<Option<OsmObj>> {
+        if element.closing {
+            return Ok(match element.name.as_str() {
+                "node" | "way" | "relation" => self.finish_current(),
+                _ => None,
+            });
+        }
+
+        let started = match element.name.as_str() {
+            "node" => {
+                let lat: f64 = element.required("lat")?.parse()?;
+                let lon: f64 = element.required("lon")?.parse()?;
+                Some(OsmObj::Node(Node {
+                    id: NodeId(element.id()?),
+                    tags: Tags::new(),
+                    decimicro_lat: (lat * 1e7).round() as i32,
+                    decimicro_lon: (lon * 1e7).round() as i32,
+                }))
+            }
+            "way" => Some(OsmObj::Way(Way {
+                id: WayId(element.id()?),
+                tags: Tags::new(),
+                nodes: Vec::new(),
+            })),
+            "relation" => Some(OsmObj::Relation(Relation {
+                id: RelationId(element.id()?),
+                tags: Tags::new(),
+                refs: Vec::new(),
+            })),
+            "tag" => {
+                let tags = match self.current.as_mut() {
+                    Some(OsmObj::Node(n)) => &mut n.tags,
+                    Some(OsmObj::Way(w)) => &mut w.tags,
+                    Some(OsmObj::Relation(r)) => &mut r.tags,
+                    None => return Ok(None),
+                };
+                tags.insert(element.required("k")?.into(), element.required("v")?.into());
+                None
+            }
+            "nd" => {
+                if let Some(OsmObj::Way(way)) = self.current.as_mut() {
+                    way.nodes.push(NodeId(element.required("ref")?.parse()?));
+                }
+                None
+            }
+            "member" => {
+                if let Some(OsmObj::Relation(rel)) = self.current.as_mut() {
+                    let id: i64 = element.required("ref")?.parse()?;
+                    let member = match element.required("type")? {
+                        "node" => OsmId::Node(NodeId(id)),
+                        "way" => OsmId::Way(WayId(id)),
+                        "relation" => OsmId::Relation(RelationId(id)),
+                        other => bail!("Unknown member type '{}'", other),
+                    };
+                    rel.refs.push(Ref {
+                        member,
+                        role: element.attr("role").unwrap_or("").into(),
+                    });
+                }
+                None
+            }
+            _ => None,
+        };
+
+        if let Some(obj) = started {
+            self.current = Some(obj);
+            self.skip_current = element.is_deleted();
+            if element.empty {
+                return Ok(self.finish_current());
+            }
+        }
+        Ok(None)
+    }
+
+    fn finish_current(&mut self) -> Option<OsmObj> {
+        let obj = self.current.take();
+        if std::mem::take(&mut self.skip_current) {
+            None
+        } else {
+            obj
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for XmlObjects<R> {
+    type Item = Result<OsmObj>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            let element = match self.next_element() {
+                Ok(Some(e)) => e,
+                Ok(None) => {
+                    self.done = true;
+                    return None;
+                }
+                Err(e) => {
+                    self.done = true;
+                    return Some(Err(e));
+                }
+            };
+            match self.handle(element) {
+                Ok(Some(obj)) => return Some(Ok(obj)),
+                Ok(None) => {}
+                Err(e) => return Some(Err(e)),
+            }
+        }
+        None
+    }
+}
+
+/// Whether the tag text read so far ends inside a quoted attribute value
+fn in_quotes(buf: &[u8]) -> bool {
+    let mut quote = None;
+    for &b in buf {
+        match quote {
+            Some(q) if b == q => quote = None,
+            None if b == b'"' || b == b'\'' => quote = Some(b),
+            _ => {}
+        }
+    }
+    quote.is_some()
+}
+
+fn parse_element(body: &str) -> Result<Element> {
+    let body = body.trim();
+    let (closing, body) = match body.strip_prefix('/') {
+        Some(rest) => (true, rest),
+        None => (false, body),
+    };
+    let (empty, body) = match body.strip_suffix('/') {
+        Some(rest) => (true, rest),
+        None => (false, body),
+    };
+
+    let name_end = body.find(|c: char| c.is_whitespace()).unwrap_or(body.len());
+    let name = body[..name_end].to_string();
+
+    let mut attrs = Vec::new();
+    let mut rest = body[name_end..].trim_start();
+    while !rest.is_empty() {
+        let eq = rest
+            .find('=')
+            .with_context(|| format!("Malformed attribute in <{}>", name))?;
+        let key = rest[..eq].trim().to_string();
+        let after = rest[eq + 1..].trim_start();
+        let quote = after
+            .chars()
+            .next()
+            .filter(|c| *c == '"' || *c == '\'')
+            .with_context(|| format!("Unquoted attribute {} in <{}>", key, name))?;
+        let value_end = after[1..]
+            .find(quote)
+            .with_context(|| format!("Unterminated attribute {} in <{}>", key, name))?;
+        attrs.push((key, unescape(&after[1..1 + value_end])));
+        rest = after[value_end + 2..].trim_start();
+    }
+
+    Ok(Element {
+        name,
+        attrs,
+        closing,
+        empty,
+    })
+}
+
+/// Decode XML entities and character references
+fn unescape(value: &str) -> String {
+    if !value.contains('&') {
+        return value.to_string();
+    }
+
+    let mut out = String::with_capacity(value.len());
+    let mut rest = value;
+    while let Some(amp) = rest.find('&') {
+        out.push_str(&rest[..amp]);
+        rest = &rest[amp..];
+
+        let decoded = rest.find(';').and_then(|end| {
+            let entity = &rest[1..end];
+            let c = match entity {
+                "amp" => Some('&'),
+                "lt" => Some('<'),
+                "gt" => Some('>'),
+                "quot" => Some('"'),
+                "apos" => Some('\''),
+                _ => {
+                    let code = if let Some(hex) = entity.strip_prefix("#x") {
+                        u32::from_str_radix(hex, 16).ok()
+                    } else if let Some(dec) = entity.strip_prefix('#') {
+                        dec.parse().ok()
+                    } else {
+                        None
+                    };
+                    code.and_then(char::from_u32)
+                }
+            };
+            c.map(|c| (c, end))
+        });
+
+        match decoded {
+            Some((c, end)) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(xml: &str) -> Vec<OsmObj> {
+        XmlObjects::new(xml.as_bytes())
+            .collect::<Result<_>>()
+            .unwrap()
+    }
+
+    #[test]
+    fn test_josm_export() {
+        let objs = parse(
+            r#"<?xml version='1.0' encoding='UTF-8'?>
+<osm version='0.6' generator='JOSM'>
+  <!-- edited <locally> -->
+  <node id='-101' action='modify' lat='47.3769' lon='8.5417'>
+    <tag k='name' v='Caf&#233; &quot;Z&amp;H&quot;' />
+    <tag k='note' v='a > b' />
+  </node>
+  <node id='-102' lat='47.0' lon='8.0' />
+  <node id='5' action='delete' lat='47.0' lon='8.0'><tag k='name' v='Gone' /></node>
+  <way id='-103'><nd ref='-101' /><nd ref='-102' /><tag k='highway' v='residential' /></way>
+  <relation id='7'><member type='way' ref='-103' role='outer' /></relation>
+</osm>"#,
+        );
+
+        assert_eq!(objs.len(), 4);
+        let node = objs[0].node().unwrap();
+        assert_eq!(node.id, NodeId(-101));
+        assert_eq!(node.decimicro_lat, 473769000);
+        assert_eq!(node.tags.get("name").unwrap(), "Café \"Z&H\"");
+        assert_eq!(node.tags.get("note").unwrap(), "a > b");
+
+        let way = objs[2].way().unwrap();
+        assert_eq!(way.nodes, vec![NodeId(-101), NodeId(-102)]);
+
+        let rel = objs[3].relation().unwrap();
+        assert_eq!(rel.refs[0].member, OsmId::Way(WayId(-103)));
+        assert_eq!(rel.refs[0].role, "outer");
+    }
+}
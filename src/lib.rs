@@ -1,12 +1,18 @@
 //! Cypress - A Rust-based geocoding system with Elasticsearch
 //!
-//! This library provides shared types and modules for the ingest and query binaries.
+//! This library provides shared types and modules for the ingest and query binaries, and the
+//! ingest pipeline itself ([`ingest::run`]).
 
 pub mod discord;
 pub mod elasticsearch;
+pub mod ingest;
+pub mod input;
 pub mod models;
 pub mod pip;
 pub mod scylla;
+pub mod text;
+pub mod transform;
 pub mod wikidata;
 
 pub use models::{AdminLevel, Layer, OsmType, Place};
+pub use transform::{PlaceTransformer, TransformerChain};
@@ -118,6 +118,18 @@ pub struct AdminArea {
     /// ISO Country Code (e.g., "CA" from "CA-ON")
     #[serde(skip_serializing_if = "Option::is_none")]
     pub iso_country_code: Option<String>,
+
+    /// ISO 3166-2 subdivision code (e.g., "CA-ON")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iso_subdivision_code: Option<String>,
+
+    /// ISO 3166-1 alpha-3 code of a country (e.g., "CAN")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub iso_alpha3: Option<String>,
+
+    /// IANA time zone at the area's representative point (e.g., "America/Toronto")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
 }
 
 impl AdminArea {
@@ -130,9 +142,20 @@ impl AdminArea {
             bbox: None,
             abbr: None,
             iso_country_code: None,
+            iso_subdivision_code: None,
+            iso_alpha3: None,
+            timezone: None,
         }
     }
 
+    /// ISO 3166-2 code for subdivisions, ISO 3166-1 alpha-2 code for countries
+    pub fn iso_code(&self) -> Option<&str> {
+        self.iso_subdivision_code.as_deref().or(match self.level {
+            AdminLevel::Country => self.iso_country_code.as_deref(),
+            _ => None,
+        })
+    }
+
     /// Get default name
     pub fn default_name(&self) -> Option<&String> {
         self.name.get("default")
@@ -157,6 +180,14 @@ pub struct AdminEntry {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub abbr: Option<String>,
 
+    /// ISO 3166-1 alpha-2 code, countries only (e.g., "AT")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alpha2: Option<String>,
+
+    /// ISO 3166-1 alpha-3 code, countries only (e.g., "AUT")
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub alpha3: Option<String>,
+
     /// OSM relation ID (used as stable ID)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub id: Option<i64>,
@@ -171,6 +202,10 @@ pub struct AdminEntry {
     /// Use `to_scylla_json()` to include this field for ScyllaDB storage.
     #[serde(skip)]
     pub names: HashMap<String, String>,
+
+    /// IANA time zone (e.g., "Europe/Vienna"), only stored in ScyllaDB
+    #[serde(skip)]
+    pub timezone: Option<String>,
 }
 
 /// ScyllaDB-specific representation that includes the full `names` HashMap.
@@ -180,6 +215,10 @@ pub struct AdminEntryScylla {
     pub name: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub abbr: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alpha2: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alpha3: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub id: Option<i64>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -187,6 +226,8 @@ pub struct AdminEntryScylla {
     /// Full multilingual names preserved for ScyllaDB
     #[serde(skip_serializing_if = "HashMap::is_empty")]
     pub names: HashMap<String, String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
 }
 
 impl AdminEntry {
@@ -195,9 +236,12 @@ impl AdminEntry {
         let scylla_entry = AdminEntryScylla {
             name: self.name.clone(),
             abbr: self.abbr.clone(),
+            alpha2: self.alpha2.clone(),
+            alpha3: self.alpha3.clone(),
             id: self.id,
             bbox: self.bbox.clone(),
             names: self.names.clone(),
+            timezone: self.timezone.clone(),
         };
         serde_json::to_string(&scylla_entry)
     }
@@ -207,9 +251,12 @@ impl AdminEntry {
         Self {
             name: scylla_entry.name,
             abbr: scylla_entry.abbr,
+            alpha2: scylla_entry.alpha2,
+            alpha3: scylla_entry.alpha3,
             id: scylla_entry.id,
             bbox: scylla_entry.bbox,
             names: scylla_entry.names,
+            timezone: scylla_entry.timezone,
         }
     }
 
@@ -222,12 +269,17 @@ impl AdminEntry {
             .or_else(|| area.name.values().next())
             .cloned();
 
+        let country = area.level == AdminLevel::Country;
+
         Self {
             name: default_name,
             abbr: area.abbr.clone(),
+            alpha2: area.iso_country_code.clone().filter(|_| country),
+            alpha3: area.iso_alpha3.clone().filter(|_| country),
             id: Some(area.osm_id),
             bbox: area.bbox.clone(),
             names: area.name.clone(),
+            timezone: area.timezone.clone(),
         }
     }
 }
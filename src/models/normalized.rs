@@ -9,6 +9,9 @@ use super::Place;
 pub struct NormalizedPlace {
     pub source_id: String,
     pub source_file: String,
+    /// All source files containing this place (overlapping extracts)
+    #[serde(default)]
+    pub source_files: Vec<String>,
     pub import_timestamp: chrono::DateTime<chrono::Utc>,
     pub osm_type: OsmType,
     pub osm_id: i64,
@@ -19,6 +22,15 @@ pub struct NormalizedPlace {
     pub name: std::collections::HashMap<String, String>,
     pub phrase: Option<String>,
     pub address: Option<Address>,
+    /// Street document an address lies on
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub street_id: Option<String>,
+    /// IANA time zone, e.g. "Europe/Zurich"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+    /// Elevation in metres
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub elevation: Option<f64>,
     pub center_point: GeoPoint,
     pub bbox: Option<GeoBbox>,
     pub parent: AdminHierarchyIds,
@@ -80,6 +92,7 @@ impl NormalizedPlace {
 
         Self {
             source_id: place.source_id,
+            source_files: vec![place.source_file.clone()],
             source_file: place.source_file,
             import_timestamp: place.import_timestamp,
             osm_type: place.osm_type,
@@ -91,6 +104,9 @@ impl NormalizedPlace {
             name: place.name,
             phrase: place.phrase,
             address: place.address,
+            street_id: place.street_id,
+            timezone: place.timezone,
+            elevation: place.elevation,
             center_point: place.center_point,
             bbox: place.bbox,
             parent,
@@ -3,6 +3,7 @@
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use xxhash_rust::xxh64::Xxh64;
 
 use super::AdminHierarchy;
 
@@ -65,6 +66,9 @@ pub struct GeoPoint {
 }
 
 /// Bounding box envelope
+///
+/// Envelopes crossing the antimeridian keep `minLon > maxLon`; Elasticsearch
+/// splits such envelopes on both sides of the dateline when indexing the geo_shape.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GeoBbox {
     #[serde(rename = "type")]
@@ -79,6 +83,21 @@ impl GeoBbox {
             coordinates: [[min_lon, max_lat], [max_lon, min_lat]],
         }
     }
+
+    /// Whether this envelope wraps across the antimeridian
+    pub fn crosses_antimeridian(&self) -> bool {
+        self.coordinates[0][0] > self.coordinates[1][0]
+    }
+
+    /// Flat `[minLon, minLat, maxLon, maxLat]` representation (GeoJSON bbox order)
+    pub fn to_array(&self) -> [f64; 4] {
+        [
+            self.coordinates[0][0],
+            self.coordinates[1][1],
+            self.coordinates[1][0],
+            self.coordinates[0][1],
+        ]
+    }
 }
 
 /// Address components
@@ -90,6 +109,9 @@ pub struct Address {
     pub street: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub postcode: Option<String>,
+    /// The postcode was inferred from nearby addresses, not tagged
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub postcode_inferred: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub city: Option<String>,
 }
@@ -148,6 +170,18 @@ pub struct Place {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub address: Option<Address>,
 
+    /// Street document an address lies on
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub street_id: Option<String>,
+
+    /// IANA time zone, e.g. "Europe/Zurich"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+
+    /// Elevation in metres, from the `ele` tag or elevation tiles
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub elevation: Option<f64>,
+
     /// Center point for geospatial queries
     pub center_point: GeoPoint,
 
@@ -183,6 +217,9 @@ impl Place {
             name_all: String::new(),
             phrase: None,
             address: None,
+            street_id: None,
+            timezone: None,
+            elevation: None,
             center_point: center,
             bbox: None,
             parent: AdminHierarchy::default(),
@@ -213,16 +250,14 @@ impl Place {
             // Check for invalid bbox where min > max
             // We can't easily fix inverted bbox without knowing which is which,
             // but we can at least clamp the values.
-            // If min > max, we swap them.
+            // If min_lat > max_lat, we swap them. min_lon > max_lon is kept as-is
+            // because it denotes an envelope crossing the antimeridian.
 
-            let mut min_lon = bbox.coordinates[0][0].clamp(-180.0, 180.0);
+            let min_lon = bbox.coordinates[0][0].clamp(-180.0, 180.0);
             let mut max_lat = bbox.coordinates[0][1].clamp(-90.0, 90.0);
-            let mut max_lon = bbox.coordinates[1][0].clamp(-180.0, 180.0);
+            let max_lon = bbox.coordinates[1][0].clamp(-180.0, 180.0);
             let mut min_lat = bbox.coordinates[1][1].clamp(-90.0, 90.0);
 
-            if min_lon > max_lon {
-                std::mem::swap(&mut min_lon, &mut max_lon);
-            }
             if min_lat > max_lat {
                 std::mem::swap(&mut min_lat, &mut max_lat);
             }
@@ -258,4 +293,49 @@ impl Place {
         sorted_names.sort();
         self.name_all = sorted_names.join(" ");
     }
+
+    /// Hash of everything written for this place, ignoring import bookkeeping
+    /// (`source_file`, `import_timestamp`). Re-imports skip places whose hash is unchanged.
+    pub fn content_hash(&self) -> i64 {
+        let mut value = serde_json::to_value(self).unwrap_or_default();
+        if let Some(fields) = value.as_object_mut() {
+            fields.remove("source_file");
+            fields.remove("import_timestamp");
+        }
+        // serde_json objects are sorted by key, so HashMap order does not matter
+        let mut hasher = Xxh64::new(0);
+        hasher.update(value.to_string().as_bytes());
+        hasher.update(self.synonyms.join("\n").as_bytes());
+        hasher.digest() as i64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_hash_ignores_bookkeeping() {
+        let center = GeoPoint {
+            lat: 47.0,
+            lon: 8.0,
+        };
+        let mut a = Place::new(
+            OsmType::Node,
+            1,
+            Layer::Venue,
+            center,
+            "switzerland.osm.pbf",
+        );
+        let mut b = Place::new(OsmType::Node, 1, Layer::Venue, center, "germany.osm.pbf");
+        a.add_name("de", "Basel".to_string());
+        a.add_name("fr", "Bâle".to_string());
+        b.add_name("fr", "Bâle".to_string());
+        b.add_name("de", "Basel".to_string());
+        b.import_timestamp = a.import_timestamp + chrono::Duration::days(7);
+        assert_eq!(a.content_hash(), b.content_hash());
+
+        b.add_name("it", "Basilea".to_string());
+        assert_ne!(a.content_hash(), b.content_hash());
+    }
 }
@@ -1,11 +1,16 @@
 //! Admin boundary extraction from OSM data.
 
-use geo::MultiPolygon;
-use osmpbfreader::{OsmObj, OsmPbfReader};
+use anyhow::{bail, Context};
+use geo::{Coord, LineString, MultiPolygon, Polygon};
+use osmpbfreader::OsmObj;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::Path;
 use tracing::{debug, info};
 
+use crate::input::OsmInput;
 use crate::models::{AdminArea, AdminLevel};
-use crate::pip::geometry::GeometryResolver;
+use crate::pip::geometry::{multipolygon_bbox, GeometryResolver};
 
 /// A single admin boundary polygon with metadata
 #[derive(Debug, Clone)]
@@ -15,29 +20,29 @@ pub struct AdminBoundary {
 }
 
 impl AdminBoundary {
-    /// Get the bounding box of this boundary
+    /// Get the bounding box of this boundary as `(min_lon, min_lat, max_lon, max_lat)`.
+    ///
+    /// For boundaries crossing the antimeridian `min_lon > max_lon`.
     pub fn bbox(&self) -> Option<(f64, f64, f64, f64)> {
-        use geo::BoundingRect;
-        self.geometry
-            .bounding_rect()
-            .map(|rect| (rect.min().x, rect.min().y, rect.max().x, rect.max().y))
+        multipolygon_bbox(&self.geometry)
     }
 }
 
-/// Extract admin boundaries from OSM PBF file
+/// ISO 3166-1 alpha-3 to alpha-2 mapping, one "AUT,AT" line per country
+const ISO3166: &str = include_str!("../../schema/synonyms/country_codes/iso3166.txt");
+
+/// Extract admin boundaries from an OSM file
 ///
 /// Uses the provided GeometryResolver to build geometries.
-pub fn extract_admin_boundaries<R: std::io::Read + std::io::Seek>(
-    reader: &mut OsmPbfReader<R>,
+pub fn extract_admin_boundaries(
+    input: &OsmInput,
     resolver: &GeometryResolver,
 ) -> anyhow::Result<Vec<AdminBoundary>> {
     info!("Extracting admin boundaries...");
 
-    reader.rewind()?;
-
     let mut boundaries = Vec::new();
 
-    for obj in reader.iter() {
+    for obj in input.objects()? {
         let obj = obj?;
 
         // We only care about Relations for admin boundaries (ways are border segments)
@@ -94,32 +99,7 @@ pub fn extract_admin_boundaries<R: std::io::Read + std::io::Seek>(
 
         let mut area = AdminArea::new(id, level);
 
-        for (key, value) in tags.iter() {
-            if key == "name" {
-                area.name.insert("default".to_string(), value.to_string());
-            } else if let Some(lang) = key.strip_prefix("name:") {
-                area.name.insert(lang.to_string(), value.to_string());
-            } else if key == "short_name" || key == "ISO3166-1:alpha2" || key == "ISO3166-1:alpha3"
-            {
-                area.abbr = Some(value.to_string());
-                // Also populate iso_country_code if it's an alpha2 tag
-                if key == "ISO3166-1:alpha2" {
-                    area.iso_country_code = Some(value.to_string());
-                }
-            } else if key == "ISO3166-1" {
-                // Often ISO3166-1 is the 2-letter code
-                if value.len() == 2 {
-                    area.iso_country_code = Some(value.to_string());
-                }
-            } else if key == "ISO3166-2" {
-                // Parse country code from "CA-ON" -> "CA"
-                if let Some((country, _)) = value.split_once('-') {
-                    area.iso_country_code = Some(country.to_string());
-                }
-            } else if key == "wikidata" {
-                area.wikidata_id = Some(value.to_string());
-            }
-        }
+        apply_tags(&mut area, tags);
 
         // Skip if no name
         if area.name.is_empty() {
@@ -128,13 +108,9 @@ pub fn extract_admin_boundaries<R: std::io::Read + std::io::Seek>(
 
         // Resolve geometry
         if let Some(geometry) = resolver.resolve_boundary(&obj) {
-            use geo::BoundingRect;
-            if let Some(rect) = geometry.bounding_rect() {
+            if let Some((min_lon, min_lat, max_lon, max_lat)) = multipolygon_bbox(&geometry) {
                 area.bbox = Some(crate::models::place::GeoBbox::new(
-                    rect.min().x,
-                    rect.min().y,
-                    rect.max().x,
-                    rect.max().y,
+                    min_lon, min_lat, max_lon, max_lat,
                 ));
             }
             boundaries.push(AdminBoundary { area, geometry });
@@ -150,3 +126,268 @@ pub fn extract_admin_boundaries<R: std::io::Read + std::io::Seek>(
 
     Ok(boundaries)
 }
+
+/// Fill names, codes and the Wikidata ID of an admin area from its tags.
+///
+/// Countries get both ISO 3166-1 codes (a missing one is looked up from the other) and
+/// use the alpha-2 code as abbreviation, independent of tag order.
+fn apply_tags(area: &mut AdminArea, tags: &osmpbfreader::Tags) {
+    let mut short_name = None;
+    let mut alpha2 = None;
+    let mut alpha3 = None;
+
+    for (key, value) in tags.iter() {
+        if key == "name" {
+            area.name.insert("default".to_string(), value.to_string());
+        } else if let Some(lang) = key.strip_prefix("name:") {
+            area.name.insert(lang.to_string(), value.to_string());
+        } else if key == "short_name" {
+            short_name = Some(value.to_string());
+        } else if key == "ISO3166-1:alpha2" {
+            alpha2 = Some(value.to_string());
+        } else if key == "ISO3166-1:alpha3" {
+            alpha3 = Some(value.to_string());
+        } else if key == "ISO3166-1" {
+            // Often ISO3166-1 is the 2-letter code
+            if value.len() == 2 && alpha2.is_none() {
+                alpha2 = Some(value.to_string());
+            }
+        } else if key == "ISO3166-2" {
+            // Parse country code from "CA-ON" -> "CA"
+            if let Some((country, _)) = value.split_once('-') {
+                area.iso_country_code = Some(country.to_string());
+            }
+            area.iso_subdivision_code = Some(value.to_string());
+        } else if key == "wikidata" {
+            area.wikidata_id = Some(value.to_string());
+        }
+    }
+
+    // The explicit alpha-2 tag wins over ISO3166-1
+    if let Some(code) = tags.get("ISO3166-1:alpha2") {
+        alpha2 = Some(code.to_string());
+    }
+
+    if area.level == AdminLevel::Country {
+        alpha2 = alpha2.or_else(|| alpha3.as_deref().and_then(country_alpha2));
+        alpha3 = alpha3.or_else(|| alpha2.as_deref().and_then(country_alpha3));
+    }
+
+    area.abbr = alpha2.clone().or(short_name).or(alpha3.clone());
+    if alpha2.is_some() {
+        area.iso_country_code = alpha2;
+    }
+    area.iso_alpha3 = alpha3;
+}
+
+/// "AT" -> "AUT"
+fn country_alpha3(alpha2: &str) -> Option<String> {
+    iso3166_codes()
+        .find(|(_, a2)| a2.eq_ignore_ascii_case(alpha2))
+        .map(|(a3, _)| a3.to_string())
+}
+
+/// "AUT" -> "AT"
+fn country_alpha2(alpha3: &str) -> Option<String> {
+    iso3166_codes()
+        .find(|(a3, _)| a3.eq_ignore_ascii_case(alpha3))
+        .map(|(_, a2)| a2.to_string())
+}
+
+/// (alpha-3, alpha-2) pairs
+fn iso3166_codes() -> impl Iterator<Item = (&'static str, &'static str)> {
+    ISO3166
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .filter_map(|line| line.split_once(','))
+        .map(|(a3, a2)| (a3.trim(), a2.trim()))
+}
+
+const BOUNDARIES_MAGIC: &[u8; 8] = b"CYPADM01";
+
+/// Write extracted boundaries to `path` in a compact binary format.
+///
+/// Used to persist the admin index between runs of a resumable import. The file is
+/// written to a temporary name first so an interrupted save never leaves a truncated file.
+pub fn save_boundaries(path: &Path, boundaries: &[AdminBoundary]) -> anyhow::Result<()> {
+    let tmp = path.with_extension("tmp");
+    let mut out = BufWriter::new(
+        File::create(&tmp).with_context(|| format!("Failed to create {}", tmp.display()))?,
+    );
+
+    out.write_all(BOUNDARIES_MAGIC)?;
+    out.write_all(&(boundaries.len() as u64).to_le_bytes())?;
+
+    for boundary in boundaries {
+        let area = serde_json::to_vec(&boundary.area)?;
+        out.write_all(&(area.len() as u32).to_le_bytes())?;
+        out.write_all(&area)?;
+
+        out.write_all(&(boundary.geometry.0.len() as u32).to_le_bytes())?;
+        for polygon in &boundary.geometry.0 {
+            let rings = std::iter::once(polygon.exterior()).chain(polygon.interiors());
+            out.write_all(&(1 + polygon.interiors().len() as u32).to_le_bytes())?;
+            for ring in rings {
+                out.write_all(&(ring.0.len() as u32).to_le_bytes())?;
+                for c in &ring.0 {
+                    out.write_all(&c.x.to_le_bytes())?;
+                    out.write_all(&c.y.to_le_bytes())?;
+                }
+            }
+        }
+    }
+
+    out.into_inner().map_err(|e| e.into_error())?.sync_all()?;
+    std::fs::rename(&tmp, path)?;
+    Ok(())
+}
+
+/// Read boundaries written by [`save_boundaries`].
+pub fn load_boundaries(path: &Path) -> anyhow::Result<Vec<AdminBoundary>> {
+    let mut input = BufReader::new(
+        File::open(path).with_context(|| format!("Failed to open {}", path.display()))?,
+    );
+
+    let mut magic = [0u8; 8];
+    input.read_exact(&mut magic)?;
+    if &magic != BOUNDARIES_MAGIC {
+        bail!("{} is not an admin boundary file", path.display());
+    }
+
+    let count = read_u64(&mut input)? as usize;
+    let mut boundaries = Vec::with_capacity(count);
+
+    for _ in 0..count {
+        let mut area = vec![0u8; read_u32(&mut input)? as usize];
+        input.read_exact(&mut area)?;
+        let area = serde_json::from_slice(&area)?;
+
+        let polygon_count = read_u32(&mut input)?;
+        let mut polygons = Vec::with_capacity(polygon_count as usize);
+        for _ in 0..polygon_count {
+            let ring_count = read_u32(&mut input)?;
+            let mut rings = Vec::with_capacity(ring_count as usize);
+            for _ in 0..ring_count {
+                let len = read_u32(&mut input)? as usize;
+                let mut coords = Vec::with_capacity(len);
+                for _ in 0..len {
+                    let x = read_f64(&mut input)?;
+                    let y = read_f64(&mut input)?;
+                    coords.push(Coord { x, y });
+                }
+                rings.push(LineString(coords));
+            }
+            let mut rings = rings.into_iter();
+            let exterior = rings.next().unwrap_or_else(|| LineString(Vec::new()));
+            polygons.push(Polygon::new(exterior, rings.collect()));
+        }
+
+        boundaries.push(AdminBoundary {
+            area,
+            geometry: MultiPolygon(polygons),
+        });
+    }
+
+    Ok(boundaries)
+}
+
+fn read_u32(input: &mut impl Read) -> std::io::Result<u32> {
+    let mut buf = [0u8; 4];
+    input.read_exact(&mut buf)?;
+    Ok(u32::from_le_bytes(buf))
+}
+
+fn read_u64(input: &mut impl Read) -> std::io::Result<u64> {
+    let mut buf = [0u8; 8];
+    input.read_exact(&mut buf)?;
+    Ok(u64::from_le_bytes(buf))
+}
+
+fn read_f64(input: &mut impl Read) -> std::io::Result<f64> {
+    let mut buf = [0u8; 8];
+    input.read_exact(&mut buf)?;
+    Ok(f64::from_le_bytes(buf))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use geo::polygon;
+
+    #[test]
+    fn test_country_codes() {
+        let country = |pairs: &[(&str, &str)]| {
+            let mut tags = osmpbfreader::Tags::new();
+            for (k, v) in pairs {
+                tags.insert((*k).into(), (*v).into());
+            }
+            let mut area = AdminArea::new(1, AdminLevel::Country);
+            apply_tags(&mut area, &tags);
+            area
+        };
+
+        // Abbreviation does not depend on which tag comes first
+        let area = country(&[
+            ("ISO3166-1:alpha3", "CHE"),
+            ("short_name", "Schweiz"),
+            ("ISO3166-1:alpha2", "CH"),
+        ]);
+        assert_eq!(area.abbr.as_deref(), Some("CH"));
+        assert_eq!(area.iso_country_code.as_deref(), Some("CH"));
+        assert_eq!(area.iso_alpha3.as_deref(), Some("CHE"));
+
+        // A missing code is looked up from the other one
+        let area = country(&[("ISO3166-1", "AT")]);
+        assert_eq!(area.iso_alpha3.as_deref(), Some("AUT"));
+        let area = country(&[("ISO3166-1:alpha3", "FRA")]);
+        assert_eq!(area.iso_country_code.as_deref(), Some("FR"));
+        assert_eq!(area.abbr.as_deref(), Some("FR"));
+    }
+
+    #[test]
+    fn test_boundaries_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("admins.bin");
+
+        let mut area = AdminArea::new(51701, AdminLevel::Country);
+        area.name
+            .insert("default".to_string(), "Schweiz".to_string());
+        area.iso_country_code = Some("CH".to_string());
+
+        let outer = polygon![
+            exterior: [(x: 0.0, y: 0.0), (x: 10.0, y: 0.0), (x: 10.0, y: 10.0), (x: 0.0, y: 0.0)],
+            interiors: [[(x: 2.0, y: 2.0), (x: 4.0, y: 2.0), (x: 4.0, y: 4.0), (x: 2.0, y: 2.0)]],
+        ];
+        let boundaries = vec![AdminBoundary {
+            area,
+            geometry: MultiPolygon(vec![outer]),
+        }];
+
+        save_boundaries(&path, &boundaries).unwrap();
+        let loaded = load_boundaries(&path).unwrap();
+
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].area.osm_id, 51701);
+        assert_eq!(loaded[0].area.iso_country_code.as_deref(), Some("CH"));
+        assert_eq!(loaded[0].geometry, boundaries[0].geometry);
+    }
+
+    #[test]
+    fn test_extract_from_fixture() {
+        let input = OsmInput::open(crate::input::fixture("village.osm")).unwrap();
+        let resolver =
+            GeometryResolver::build(&input, |tags| tags.contains("boundary", "administrative"))
+                .unwrap();
+
+        let boundaries = extract_admin_boundaries(&input, &resolver).unwrap();
+        assert_eq!(boundaries.len(), 2);
+        assert_eq!(boundaries[0].area.level, AdminLevel::Country);
+        assert_eq!(boundaries[0].area.iso_country_code.as_deref(), Some("CH"));
+        assert_eq!(boundaries[1].area.name["default"], "Zürich");
+
+        let pip = crate::pip::PipService::new(crate::pip::AdminSpatialIndex::build(boundaries));
+        let hierarchy = pip.lookup(8.5417, 47.3769, None);
+        assert_eq!(hierarchy.locality.unwrap().id, Some(1001));
+        assert_eq!(hierarchy.country.unwrap().id, Some(1000));
+    }
+}
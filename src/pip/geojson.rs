@@ -0,0 +1,160 @@
+//! Minimal GeoJSON reading for polygonal data (clip areas, boundary datasets).
+//!
+//! Only `Polygon`, `MultiPolygon` and `GeometryCollection` geometries are understood;
+//! anything else is ignored.
+
+use std::path::Path;
+
+use anyhow::{bail, Context, Result};
+use geo::{Coord, LineString, MultiPolygon, Polygon};
+use serde_json::{Map, Value};
+
+/// A polygonal GeoJSON feature
+#[derive(Debug, Clone)]
+pub struct PolygonFeature {
+    pub properties: Map<String, Value>,
+    pub geometry: MultiPolygon<f64>,
+}
+
+/// Read all polygonal features of a GeoJSON file.
+///
+/// Accepts a `FeatureCollection`, a single `Feature` or a bare geometry (which yields one
+/// feature without properties).
+pub fn read_polygon_features(path: &Path) -> Result<Vec<PolygonFeature>> {
+    let file = std::fs::File::open(path)
+        .with_context(|| format!("Failed to open GeoJSON file {}", path.display()))?;
+    let value: Value = serde_json::from_reader(std::io::BufReader::new(file))
+        .with_context(|| format!("Failed to parse GeoJSON file {}", path.display()))?;
+
+    Ok(polygon_features(&value))
+}
+
+/// Read a GeoJSON file as a single multipolygon made of all its polygonal features
+pub fn read_multipolygon(path: &Path) -> Result<MultiPolygon<f64>> {
+    let polygons: Vec<Polygon<f64>> = read_polygon_features(path)?
+        .into_iter()
+        .flat_map(|f| f.geometry.0)
+        .collect();
+
+    if polygons.is_empty() {
+        bail!("{} contains no polygons", path.display());
+    }
+    Ok(MultiPolygon(polygons))
+}
+
+fn polygon_features(value: &Value) -> Vec<PolygonFeature> {
+    match value.get("type").and_then(Value::as_str) {
+        Some("FeatureCollection") => value
+            .get("features")
+            .and_then(Value::as_array)
+            .map(|features| features.iter().flat_map(polygon_features).collect())
+            .unwrap_or_default(),
+        Some("Feature") => {
+            let properties = value
+                .get("properties")
+                .and_then(Value::as_object)
+                .cloned()
+                .unwrap_or_default();
+            value
+                .get("geometry")
+                .and_then(parse_geometry)
+                .map(|geometry| PolygonFeature {
+                    properties,
+                    geometry,
+                })
+                .into_iter()
+                .collect()
+        }
+        _ => parse_geometry(value)
+            .map(|geometry| PolygonFeature {
+                properties: Map::new(),
+                geometry,
+            })
+            .into_iter()
+            .collect(),
+    }
+}
+
+/// Convert a GeoJSON geometry object to a multipolygon
+pub fn parse_geometry(value: &Value) -> Option<MultiPolygon<f64>> {
+    match value.get("type")?.as_str()? {
+        "Polygon" => Some(MultiPolygon(vec![parse_polygon(
+            value.get("coordinates")?,
+        )?])),
+        "MultiPolygon" => {
+            let polygons = value
+                .get("coordinates")?
+                .as_array()?
+                .iter()
+                .filter_map(parse_polygon)
+                .collect();
+            Some(MultiPolygon(polygons))
+        }
+        "GeometryCollection" => {
+            let polygons: Vec<_> = value
+                .get("geometries")?
+                .as_array()?
+                .iter()
+                .filter_map(parse_geometry)
+                .flat_map(|mp| mp.0)
+                .collect();
+            (!polygons.is_empty()).then_some(MultiPolygon(polygons))
+        }
+        _ => None,
+    }
+}
+
+fn parse_polygon(value: &Value) -> Option<Polygon<f64>> {
+    let mut rings = value.as_array()?.iter().filter_map(parse_ring);
+    let exterior = rings.next()?;
+    Some(Polygon::new(exterior, rings.collect()))
+}
+
+fn parse_ring(value: &Value) -> Option<LineString<f64>> {
+    let coords: Vec<Coord<f64>> = value
+        .as_array()?
+        .iter()
+        .filter_map(|position| {
+            let position = position.as_array()?;
+            Some(Coord {
+                x: position.first()?.as_f64()?,
+                y: position.get(1)?.as_f64()?,
+            })
+        })
+        .collect();
+
+    (coords.len() >= 4).then_some(LineString(coords))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_feature_collection() {
+        let value = json!({
+            "type": "FeatureCollection",
+            "features": [
+                {
+                    "type": "Feature",
+                    "properties": { "tzid": "Europe/Zurich" },
+                    "geometry": {
+                        "type": "Polygon",
+                        "coordinates": [[[0.0, 0.0], [1.0, 0.0], [1.0, 1.0], [0.0, 0.0]]]
+                    }
+                },
+                {
+                    "type": "Feature",
+                    "properties": {},
+                    "geometry": { "type": "Point", "coordinates": [0.0, 0.0] }
+                }
+            ]
+        });
+
+        let features = polygon_features(&value);
+        assert_eq!(features.len(), 1);
+        assert_eq!(features[0].properties["tzid"], "Europe/Zurich");
+        assert_eq!(features[0].geometry.0[0].exterior().0.len(), 4);
+    }
+}
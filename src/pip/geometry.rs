@@ -1,192 +1,204 @@
 use anyhow::Result;
-use geo::{Coord, LineString, MultiPolygon, Polygon};
-use hashbrown::{HashMap, HashSet};
-use memmap2::Mmap;
-use osmpbfreader::{NodeId, OsmObj, OsmPbfReader, RelationId, WayId};
-use std::io::{BufWriter, Read, Seek, Write};
-use tempfile::tempfile;
-use tracing::info;
-
-#[repr(C)]
-#[derive(Debug, Clone, Copy)]
-struct NodeData {
-    id: i64,
-    lon: f64,
-    lat: f64,
-}
+use geo::{
+    Area, BoundingRect, Centroid, Contains, Coord, InteriorPoint, LineString, MultiPolygon, Point,
+    Polygon, Rect,
+};
+use osmpbfreader::{NodeId, OsmObj, RelationId, WayId};
+use std::path::Path;
+use tracing::{info, warn};
+
+use crate::input::OsmInput;
+
+use super::store::{
+    IdLists, IdListsWriter, IdSet, NodeCoords, NodeCoordsWriter, StoreMeta, StoreMode, StoreOptions,
+};
 
 /// Manages geometry resolution for Ways and Relations
 pub struct GeometryResolver {
-    nodes_mmap: Mmap,
-    num_nodes: usize,
-    way_nodes: HashMap<WayId, Vec<NodeId>>,
-    relation_members: HashMap<RelationId, Vec<WayId>>,
+    nodes: NodeCoords,
+    way_nodes: IdLists,
+    relation_members: IdLists,
 }
 
 impl GeometryResolver {
-    /// Build the resolver by scanning the file
-    pub fn build<R: Read + Seek, F>(reader: &mut OsmPbfReader<R>, filter: F) -> Result<Self>
+    /// Build the resolver by scanning the file (in-memory store)
+    pub fn build<F>(input: &OsmInput, filter: F) -> Result<Self>
+    where
+        F: Fn(&osmpbfreader::Tags) -> bool,
+    {
+        Self::build_with_options(input, filter, &StoreOptions::default())
+    }
+
+    /// Build the resolver by scanning the file, using the given storage options
+    pub fn build_with_options<F>(
+        input: &OsmInput,
+        filter: F,
+        options: &StoreOptions,
+    ) -> Result<Self>
     where
         F: Fn(&osmpbfreader::Tags) -> bool,
     {
-        info!("Building geometry index...");
+        info!(
+            "Building geometry index ({:?} store, {:?} node index)...",
+            options.mode, options.node_index
+        );
+
+        let dir = match options.mode {
+            StoreMode::Disk => {
+                let dir = options.dir.as_deref().ok_or_else(|| {
+                    anyhow::anyhow!("A directory is required for the disk geometry store")
+                })?;
+                std::fs::create_dir_all(dir)?;
+                StoreMeta::clear(dir);
+                Some(dir)
+            }
+            StoreMode::Memory => None,
+        };
 
         // Sets of things we need
-        let mut needed_relations = HashSet::new();
-        let mut needed_ways = HashSet::new();
-        let mut needed_nodes = HashSet::new();
+        let mut needed_ways = IdSet::new();
+        let mut needed_nodes = IdSet::new();
 
-        let mut relation_members_map = HashMap::new();
-        let mut way_nodes_map = HashMap::new();
+        let mut relation_members = IdListsWriter::new(options.mode, dir, "relations")?;
+        let mut way_nodes = IdListsWriter::new(options.mode, dir, "ways")?;
+        let mut relation_count = 0;
 
         // Pass 1: Scan for relevant Relations
         info!("Pass 1/3: Identifying relevant relations...");
-        reader.rewind()?;
-        for obj in reader.iter() {
+        for obj in input.objects()? {
             let obj = obj?;
             if let OsmObj::Relation(rel) = obj {
                 if filter(&rel.tags) {
-                    needed_relations.insert(rel.id);
+                    relation_count += 1;
                     let mut ways = Vec::new();
                     for member in &rel.refs {
                         if let osmpbfreader::OsmId::Way(way_id) = member.member {
-                            if member.role == "outer" || member.role == "" {
-                                ways.push(way_id);
-                                needed_ways.insert(way_id);
+                            if member.role == "outer" || member.role.is_empty() {
+                                ways.push(way_id.0);
+                                needed_ways.insert(way_id.0);
                             }
                         }
                     }
-                    relation_members_map.insert(rel.id, ways);
+                    relation_members.push(rel.id.0, ways)?;
                 }
             }
         }
-        info!("Found {} relevant relations", needed_relations.len());
+        info!("Found {} relevant relations", relation_count);
 
         // Pass 2: Ways
         info!("Pass 2/3: Identifying relevant ways...");
-        reader.rewind()?;
-
-        for obj in reader.iter() {
+        let mut way_count = 0;
+        for obj in input.objects()? {
             let obj = obj?;
-            match obj {
-                OsmObj::Way(way) => {
-                    // Include if it's needed by a relation OR matches filter itself
-                    if needed_ways.contains(&way.id) || filter(&way.tags) {
-                        needed_ways.insert(way.id);
-                        way_nodes_map.insert(way.id, way.nodes.clone());
-                        for node in &way.nodes {
-                            needed_nodes.insert(*node);
-                        }
+            if let OsmObj::Way(way) = obj {
+                // Include if it's needed by a relation OR matches filter itself
+                if needed_ways.contains(way.id.0) || filter(&way.tags) {
+                    way_count += 1;
+                    way_nodes.push(way.id.0, way.nodes.iter().map(|n| n.0))?;
+                    for node in &way.nodes {
+                        needed_nodes.insert(node.0);
                     }
                 }
-                _ => {}
             }
         }
 
         info!(
             "Found {} relevant ways (total), referencing {} nodes",
-            needed_ways.len(),
+            way_count,
             needed_nodes.len()
         );
 
         // Pass 3: Store node coordinates
         info!("Pass 3/3: Storing node coordinates...");
-        reader.rewind()?;
-
-        let mut file = tempfile()?;
-        let mut writer = BufWriter::new(&mut file);
+        let mut nodes = NodeCoordsWriter::new(options.node_index, dir)?;
         let mut stored_count = 0;
 
-        let mut sorted = true;
-        let mut last_id = i64::MIN;
-
-        for obj in reader.iter() {
+        for obj in input.objects()? {
             let obj = obj?;
             if let OsmObj::Node(node) = obj {
-                if needed_nodes.contains(&node.id) {
-                    let id = node.id.0;
-                    if id < last_id {
-                        sorted = false;
-                    }
-                    last_id = id;
-
-                    let data = NodeData {
-                        id,
-                        lon: node.lon(),
-                        lat: node.lat(),
-                    };
-
-                    // Safety: NodeData is Repr(C) and contains only plain data types (i64, f64)
-                    // We write the raw bytes directly to the file
-                    let bytes = unsafe {
-                        std::slice::from_raw_parts(
-                            &data as *const NodeData as *const u8,
-                            std::mem::size_of::<NodeData>(),
-                        )
-                    };
-                    writer.write_all(bytes)?;
+                if needed_nodes.contains(node.id.0) {
+                    nodes.push(node.id.0, node.lon(), node.lat())?;
                     stored_count += 1;
                 }
             }
         }
 
-        writer.flush()?;
-        drop(writer); // Drop writer to release borrow on file
-
-        // Memory map the file
-        file.seek(std::io::SeekFrom::Start(0))?;
-        let mut mmap = unsafe { memmap2::MmapMut::map_mut(&file)? };
+        let resolver = Self {
+            nodes: nodes.finish()?,
+            way_nodes: way_nodes.finish()?,
+            relation_members: relation_members.finish()?,
+        };
 
-        // Ensure we have complete records
-        let struct_size = std::mem::size_of::<NodeData>();
-        assert_eq!(
-            mmap.len() % struct_size,
-            0,
-            "File size must be multiple of struct size"
+        info!(
+            "Stored {} node coordinates using {} bytes",
+            stored_count,
+            resolver.nodes.byte_len()
         );
 
-        if !sorted && stored_count > 0 {
-            info!("Node data not sorted, sorting in-place...");
-            let slice: &mut [NodeData] = unsafe {
-                std::slice::from_raw_parts_mut(
-                    mmap.as_mut_ptr() as *mut NodeData,
-                    mmap.len() / struct_size,
-                )
-            };
-            slice.sort_unstable_by_key(|n| n.id);
+        if let Some(dir) = dir {
+            StoreMeta {
+                node_index: options.node_index,
+                fingerprint: options.fingerprint.clone(),
+                nodes: stored_count,
+                ways: resolver.way_nodes.len(),
+                relations: resolver.relation_members.len(),
+            }
+            .save(dir)?;
         }
 
-        let mmap = mmap.make_read_only()?;
+        Ok(resolver)
+    }
+
+    /// Open a complete on-disk store previously built in `dir`.
+    ///
+    /// Returns `None` if there is no complete store, or if it was built from a
+    /// different input than `fingerprint`.
+    pub fn open(dir: &Path, fingerprint: Option<&str>) -> Result<Option<Self>> {
+        let Some(meta) = StoreMeta::load(dir) else {
+            return Ok(None);
+        };
+
+        if fingerprint.is_some() && meta.fingerprint.as_deref() != fingerprint {
+            warn!(
+                "Geometry store in {} was built from a different input, ignoring it",
+                dir.display()
+            );
+            return Ok(None);
+        }
 
         info!(
-            "Stored {} node coordinates using {} bytes",
-            stored_count,
-            mmap.len()
+            "Reusing geometry store in {} ({} nodes, {} ways, {} relations)",
+            dir.display(),
+            meta.nodes,
+            meta.ways,
+            meta.relations
         );
 
-        Ok(Self {
-            nodes_mmap: mmap,
-            num_nodes: stored_count,
-            way_nodes: way_nodes_map,
-            relation_members: relation_members_map,
-        })
+        Ok(Some(Self {
+            nodes: NodeCoords::open(dir, meta.node_index)?,
+            way_nodes: IdLists::open(dir, "ways")?,
+            relation_members: IdLists::open(dir, "relations")?,
+        }))
+    }
+
+    /// Open a matching store from `options.dir` if one exists, otherwise build it
+    pub fn open_or_build<F>(input: &OsmInput, filter: F, options: &StoreOptions) -> Result<Self>
+    where
+        F: Fn(&osmpbfreader::Tags) -> bool,
+    {
+        if options.mode == StoreMode::Disk {
+            if let Some(dir) = options.dir.as_deref() {
+                if let Some(resolver) = Self::open(dir, options.fingerprint.as_deref())? {
+                    return Ok(resolver);
+                }
+            }
+        }
+        Self::build_with_options(input, filter, options)
     }
 
     /// Helper to get node coordinates
     pub fn get_node_coords(&self, node_id: NodeId) -> Option<Coord<f64>> {
-        let slice: &[NodeData] = unsafe {
-            std::slice::from_raw_parts(self.nodes_mmap.as_ptr() as *const NodeData, self.num_nodes)
-        };
-
-        if let Ok(idx) = slice.binary_search_by_key(&node_id.0, |n| n.id) {
-            let node = &slice[idx];
-            Some(Coord {
-                x: node.lon,
-                y: node.lat,
-            })
-        } else {
-            None
-        }
+        self.nodes.get(node_id.0)
     }
 
     /// Resolve geometry for an OSM object (Relation or Way)
@@ -200,15 +212,15 @@ impl GeometryResolver {
 
     /// Resolve geometry for a Relation (Multipolygon)
     pub fn resolve_relation(&self, rel_id: RelationId) -> Option<MultiPolygon<f64>> {
-        let member_ways = self.relation_members.get(&rel_id)?;
+        let member_ways = self.relation_members.get(rel_id.0)?;
 
         let mut rings: Vec<Vec<Coord<f64>>> = Vec::new();
 
         for way_id in member_ways {
-            if let Some(nodes) = self.way_nodes.get(way_id) {
+            if let Some(nodes) = self.way_nodes.get(*way_id) {
                 let coords: Vec<Coord<f64>> = nodes
                     .iter()
-                    .filter_map(|nid| self.get_node_coords(*nid))
+                    .filter_map(|nid| self.get_node_coords(NodeId(*nid)))
                     .collect();
 
                 if coords.len() >= 2 {
@@ -231,11 +243,11 @@ impl GeometryResolver {
 
     /// Resolve geometry for a Way
     pub fn resolve_way(&self, way_id: WayId) -> Option<Polygon<f64>> {
-        let nodes = self.way_nodes.get(&way_id)?;
+        let nodes = self.way_nodes.get(way_id.0)?;
 
         let coords: Vec<Coord<f64>> = nodes
             .iter()
-            .filter_map(|nid| self.get_node_coords(*nid))
+            .filter_map(|nid| self.get_node_coords(NodeId(*nid)))
             .collect();
 
         // Need at least 4 points for a closed polygon (3 points + 1 repeat)
@@ -251,14 +263,170 @@ impl GeometryResolver {
         Some(Polygon::new(LineString::new(coords), vec![]))
     }
 
-    /// Get centroid for a Way
-    pub fn resolve_centroid(&self, way_id: WayId) -> Option<(f64, f64)> {
-        use geo::Centroid;
+    /// Get a representative point for a Way that is guaranteed to lie inside it
+    pub fn resolve_representative_point(&self, way_id: WayId) -> Option<(f64, f64)> {
         let poly = self.resolve_way(way_id)?;
-        poly.centroid().map(|p| (p.x(), p.y()))
+        representative_point(&MultiPolygon::new(vec![poly])).map(|p| (p.x(), p.y()))
     }
 }
 
+/// Pick a point that lies inside a polygonal feature.
+///
+/// The centroid is used when it falls inside the geometry. For concave or multi-part
+/// shapes (C-shaped buildings, crescent parks, archipelagos) the centroid often lies
+/// outside, so we fall back to the centroid of the largest part and finally to a
+/// point-on-surface of the largest part.
+///
+/// Relations are resolved without member roles, so inner rings arrive as parts of their
+/// own; they are turned back into holes first, or the point could land in an enclave.
+pub fn representative_point(geometry: &MultiPolygon<f64>) -> Option<Point<f64>> {
+    let nested = nest_inner_rings(geometry);
+    let geometry = &nested;
+    let centroid = geometry.centroid()?;
+    if geometry.contains(&centroid) {
+        return Some(centroid);
+    }
+
+    let largest = geometry.0.iter().max_by(|a, b| {
+        a.unsigned_area()
+            .partial_cmp(&b.unsigned_area())
+            .unwrap_or(std::cmp::Ordering::Equal)
+    })?;
+
+    if let Some(c) = largest.centroid() {
+        if largest.contains(&c) {
+            return Some(c);
+        }
+    }
+
+    largest
+        .interior_point()
+        .or_else(|| geometry.interior_point())
+        .or(Some(centroid))
+}
+
+/// Turn parts lying inside other parts into holes of the smallest part containing them.
+///
+/// Parts nested an even number of times (islands in a lake in a country) stay outer parts.
+fn nest_inner_rings(geometry: &MultiPolygon<f64>) -> MultiPolygon<f64> {
+    let outlines: Vec<(Polygon<f64>, Option<Rect<f64>>, f64)> = geometry
+        .0
+        .iter()
+        .map(|part| {
+            let outline = Polygon::new(part.exterior().clone(), vec![]);
+            let rect = outline.bounding_rect();
+            let area = outline.unsigned_area();
+            (outline, rect, area)
+        })
+        .collect();
+
+    // Indices of the parts containing each part
+    let containers: Vec<Vec<usize>> = outlines
+        .iter()
+        .enumerate()
+        .map(|(i, (inner, inner_rect, _))| {
+            outlines
+                .iter()
+                .enumerate()
+                .filter(|&(j, (outer, outer_rect, _))| {
+                    j != i
+                        && match (outer_rect, inner_rect) {
+                            (Some(o), Some(r)) => o.contains(r),
+                            _ => false,
+                        }
+                        && outer.contains(inner)
+                })
+                .map(|(j, _)| j)
+                .collect()
+        })
+        .collect();
+
+    if containers.iter().all(Vec::is_empty) {
+        return geometry.clone();
+    }
+
+    let mut parts: Vec<Option<Polygon<f64>>> = geometry.0.iter().cloned().map(Some).collect();
+    for (i, containing) in containers.iter().enumerate() {
+        if containing.len() % 2 == 0 {
+            continue;
+        }
+        let parent = containing
+            .iter()
+            .copied()
+            .min_by(|&a, &b| {
+                outlines[a]
+                    .2
+                    .partial_cmp(&outlines[b].2)
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            })
+            .expect("odd number of containers");
+        let hole = geometry.0[i].exterior().clone();
+        if let Some(parent) = parts[parent].as_mut() {
+            parent.interiors_push(hole);
+        }
+        parts[i] = None;
+    }
+
+    MultiPolygon::new(parts.into_iter().flatten().collect())
+}
+
+/// Compute an antimeridian-aware bounding box for a set of part rectangles.
+///
+/// Returns `(min_lon, min_lat, max_lon, max_lat)`. When the smallest longitude span that
+/// covers all parts wraps across the antimeridian (Russia, Fiji, the Aleutians...), the
+/// result has `min_lon > max_lon`, following the GeoJSON (RFC 7946) convention.
+pub fn antimeridian_bbox<I>(parts: I) -> Option<(f64, f64, f64, f64)>
+where
+    I: IntoIterator<Item = Rect<f64>>,
+{
+    let mut min_lat = f64::INFINITY;
+    let mut max_lat = f64::NEG_INFINITY;
+    let mut intervals: Vec<(f64, f64)> = Vec::new();
+
+    for rect in parts {
+        min_lat = min_lat.min(rect.min().y);
+        max_lat = max_lat.max(rect.max().y);
+        intervals.push((rect.min().x, rect.max().x));
+    }
+
+    if intervals.is_empty() {
+        return None;
+    }
+
+    // Merge overlapping longitude intervals
+    intervals.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(std::cmp::Ordering::Equal));
+    let mut merged: Vec<(f64, f64)> = Vec::with_capacity(intervals.len());
+    for (start, end) in intervals {
+        match merged.last_mut() {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end),
+            _ => merged.push((start, end)),
+        }
+    }
+
+    let first = merged[0];
+    let last = merged[merged.len() - 1];
+
+    // The gap that wraps around the antimeridian
+    let mut largest_gap = (first.0 + 360.0) - last.1;
+    let mut bbox = (first.0, last.1);
+
+    // Any larger gap between parts means the box should wrap instead
+    for pair in merged.windows(2) {
+        let gap = pair[1].0 - pair[0].1;
+        if gap > largest_gap {
+            largest_gap = gap;
+            bbox = (pair[1].0, pair[0].1);
+        }
+    }
+
+    Some((bbox.0, min_lat, bbox.1, max_lat))
+}
+
+/// Antimeridian-aware bounding box of a multipolygon (see [`antimeridian_bbox`])
+pub fn multipolygon_bbox(geometry: &MultiPolygon<f64>) -> Option<(f64, f64, f64, f64)> {
+    antimeridian_bbox(geometry.0.iter().filter_map(|p| p.bounding_rect()))
+}
+
 /// Merge disconnected rings into closed polygons
 pub fn merge_rings_to_polygons(rings: Vec<Vec<Coord<f64>>>) -> Vec<Polygon<f64>> {
     let mut result = Vec::new();
@@ -387,6 +555,126 @@ mod tests {
         assert_eq!(polygons.len(), 1);
     }
 
+    #[test]
+    fn test_representative_point_concave() {
+        // C-shaped polygon whose centroid lies in the notch
+        let ring = LineString::from(vec![
+            (0.0, 0.0),
+            (3.0, 0.0),
+            (3.0, 1.0),
+            (1.0, 1.0),
+            (1.0, 2.0),
+            (3.0, 2.0),
+            (3.0, 3.0),
+            (0.0, 3.0),
+            (0.0, 0.0),
+        ]);
+        let shape = MultiPolygon::new(vec![Polygon::new(ring, vec![])]);
+        assert!(!shape.contains(&shape.centroid().unwrap()));
+
+        let point = representative_point(&shape).unwrap();
+        assert!(shape.contains(&point));
+    }
+
+    #[test]
+    fn test_representative_point_archipelago() {
+        let island = |x: f64, size: f64| {
+            Polygon::new(
+                LineString::from(vec![
+                    (x, 0.0),
+                    (x + size, 0.0),
+                    (x + size, size),
+                    (x, size),
+                    (x, 0.0),
+                ]),
+                vec![],
+            )
+        };
+        let shape = MultiPolygon::new(vec![island(0.0, 1.0), island(10.0, 2.0)]);
+
+        let point = representative_point(&shape).unwrap();
+        assert!(shape.contains(&point));
+        // Should land on the largest island
+        assert!(point.x() >= 10.0);
+    }
+
+    #[test]
+    fn test_representative_point_inner_ring() {
+        let square = |min: f64, max: f64| {
+            Polygon::new(
+                LineString::from(vec![
+                    (min, min),
+                    (max, min),
+                    (max, max),
+                    (min, max),
+                    (min, min),
+                ]),
+                vec![],
+            )
+        };
+        // A municipality around an enclave, resolved as two separate rings
+        let shape = MultiPolygon::new(vec![square(0.0, 4.0), square(1.0, 3.0)]);
+
+        let point = representative_point(&shape).unwrap();
+        assert!(!square(1.0, 3.0).contains(&point));
+        assert!(square(0.0, 4.0).contains(&point));
+
+        // An island in the enclave is land again
+        let shape = MultiPolygon::new(vec![square(0.0, 4.0), square(1.0, 3.0), square(1.5, 2.5)]);
+        let nested = nest_inner_rings(&shape);
+        assert_eq!(nested.0.len(), 2);
+        assert_eq!(nested.0[0].interiors().len(), 1);
+    }
+
+    #[test]
+    fn test_representative_point_convex_uses_centroid() {
+        let ring = LineString::from(vec![
+            (0.0, 0.0),
+            (2.0, 0.0),
+            (2.0, 2.0),
+            (0.0, 2.0),
+            (0.0, 0.0),
+        ]);
+        let shape = MultiPolygon::new(vec![Polygon::new(ring, vec![])]);
+        let point = representative_point(&shape).unwrap();
+        assert_eq!((point.x(), point.y()), (1.0, 1.0));
+    }
+
+    #[test]
+    fn test_antimeridian_bbox_regular() {
+        let rects = vec![
+            Rect::new(Coord { x: 5.9, y: 45.8 }, Coord { x: 10.5, y: 47.8 }),
+            Rect::new(Coord { x: 8.0, y: 46.0 }, Coord { x: 9.0, y: 47.0 }),
+        ];
+        assert_eq!(antimeridian_bbox(rects), Some((5.9, 45.8, 10.5, 47.8)));
+    }
+
+    #[test]
+    fn test_antimeridian_bbox_crossing() {
+        // Fiji-like: parts on both sides of the antimeridian
+        let rects = vec![
+            Rect::new(Coord { x: 177.0, y: -19.0 }, Coord { x: 180.0, y: -16.0 }),
+            Rect::new(
+                Coord {
+                    x: -180.0,
+                    y: -18.0,
+                },
+                Coord {
+                    x: -178.0,
+                    y: -15.0,
+                },
+            ),
+        ];
+        let (min_lon, min_lat, max_lon, max_lat) = antimeridian_bbox(rects).unwrap();
+        assert_eq!((min_lon, max_lon), (177.0, -178.0));
+        assert_eq!((min_lat, max_lat), (-19.0, -15.0));
+    }
+
+    #[test]
+    fn test_antimeridian_bbox_empty() {
+        assert_eq!(antimeridian_bbox(Vec::<Rect<f64>>::new()), None);
+    }
+
     #[test]
     fn test_merge_gap_fails() {
         let p1 = Coord { x: 0.0, y: 0.0 };
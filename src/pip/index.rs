@@ -24,12 +24,31 @@ impl RTreeObject for IndexedBoundary {
 }
 
 impl IndexedBoundary {
-    pub fn new(boundary: AdminBoundary) -> Option<Self> {
-        let (min_x, min_y, max_x, max_y) = boundary.bbox()?;
-        Some(Self {
-            boundary: Arc::new(boundary),
-            envelope: AABB::from_corners([min_x, min_y], [max_x, max_y]),
-        })
+    /// Build the R-tree entries for a boundary.
+    ///
+    /// Boundaries crossing the antimeridian get one entry per side so that their
+    /// envelopes don't span the whole globe.
+    pub fn new(boundary: Arc<AdminBoundary>) -> Vec<Self> {
+        let Some((min_x, min_y, max_x, max_y)) = boundary.bbox() else {
+            return Vec::new();
+        };
+
+        let envelopes = if min_x > max_x {
+            vec![
+                AABB::from_corners([min_x, min_y], [180.0, max_y]),
+                AABB::from_corners([-180.0, min_y], [max_x, max_y]),
+            ]
+        } else {
+            vec![AABB::from_corners([min_x, min_y], [max_x, max_y])]
+        };
+
+        envelopes
+            .into_iter()
+            .map(|envelope| Self {
+                boundary: Arc::clone(&boundary),
+                envelope,
+            })
+            .collect()
     }
 }
 
@@ -38,6 +57,8 @@ pub struct AdminSpatialIndex {
     tree: RTree<IndexedBoundary>,
     /// Boundaries sorted by level for hierarchical lookup
     by_level: Vec<(AdminLevel, Vec<Arc<AdminBoundary>>)>,
+    /// Number of distinct boundaries (the tree may hold several entries per boundary)
+    count: usize,
 }
 
 impl AdminSpatialIndex {
@@ -48,26 +69,33 @@ impl AdminSpatialIndex {
             boundaries.len()
         );
 
-        // Build R-tree
-        let indexed: Vec<IndexedBoundary> = boundaries
-            .into_iter()
-            .filter_map(IndexedBoundary::new)
-            .collect();
-
-        // Group by level
+        // Group by level and build R-tree entries
         let mut by_level: std::collections::BTreeMap<AdminLevel, Vec<Arc<AdminBoundary>>> =
             std::collections::BTreeMap::new();
+        let mut indexed: Vec<IndexedBoundary> = Vec::new();
+        let mut count = 0;
+
+        for boundary in boundaries {
+            let entries = IndexedBoundary::new(Arc::new(boundary));
+            let Some(first) = entries.first() else {
+                continue;
+            };
 
-        for ib in &indexed {
             by_level
-                .entry(ib.boundary.area.level)
+                .entry(first.boundary.area.level)
                 .or_default()
-                .push(Arc::clone(&ib.boundary));
+                .push(Arc::clone(&first.boundary));
+            count += 1;
+            indexed.extend(entries);
         }
 
         let tree = RTree::bulk_load(indexed);
 
-        info!("Spatial index built with {} entries", tree.size());
+        info!(
+            "Spatial index built with {} boundaries ({} entries)",
+            count,
+            tree.size()
+        );
         for (level, bounds) in &by_level {
             info!("  {:?}: {} boundaries", level, bounds.len());
         }
@@ -75,6 +103,7 @@ impl AdminSpatialIndex {
         Self {
             tree,
             by_level: by_level.into_iter().collect(),
+            count,
         }
     }
 
@@ -120,15 +149,15 @@ impl AdminSpatialIndex {
 
     /// Get total number of indexed boundaries
     pub fn len(&self) -> usize {
-        self.tree.size()
+        self.count
     }
 
     pub fn is_empty(&self) -> bool {
-        self.tree.size() == 0
+        self.count == 0
     }
 
     /// Iterate over all indexed boundaries
     pub fn boundaries(&self) -> impl Iterator<Item = &Arc<AdminBoundary>> {
-        self.tree.iter().map(|ib| &ib.boundary)
+        self.by_level.iter().flat_map(|(_, bounds)| bounds.iter())
     }
 }
@@ -4,11 +4,16 @@
 //! using an R-tree spatial index.
 
 mod boundary;
+pub mod geojson;
 pub mod geometry;
 mod index;
 mod service;
+pub mod store;
+mod timezone;
 
-pub use boundary::{extract_admin_boundaries, AdminBoundary};
-pub use geometry::GeometryResolver;
+pub use boundary::{extract_admin_boundaries, load_boundaries, save_boundaries, AdminBoundary};
+pub use geometry::{antimeridian_bbox, multipolygon_bbox, representative_point, GeometryResolver};
 pub use index::AdminSpatialIndex;
 pub use service::PipService;
+pub use store::{file_fingerprint, NodeIndexMode, StoreMode, StoreOptions};
+pub use timezone::TimezoneIndex;
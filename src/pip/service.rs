@@ -186,4 +186,39 @@ mod tests {
             Some("US")
         );
     }
+
+    #[test]
+    fn test_antimeridian_boundary() {
+        use crate::models::AdminArea;
+        use geo::{MultiPolygon, Rect};
+
+        // Two parts on either side of the antimeridian (Chukotka-like)
+        let geometry = MultiPolygon::new(vec![
+            Rect::new(
+                geo::Coord { x: 170.0, y: 64.0 },
+                geo::Coord { x: 180.0, y: 70.0 },
+            )
+            .to_polygon(),
+            Rect::new(
+                geo::Coord { x: -180.0, y: 64.0 },
+                geo::Coord { x: -170.0, y: 68.0 },
+            )
+            .to_polygon(),
+        ]);
+        let boundary = AdminBoundary {
+            area: AdminArea::new(1, AdminLevel::Region),
+            geometry,
+        };
+        assert_eq!(boundary.bbox(), Some((170.0, 64.0, -170.0, 70.0)));
+
+        let index = AdminSpatialIndex::build(vec![boundary]);
+        assert_eq!(index.len(), 1);
+        assert_eq!(index.boundaries().count(), 1);
+
+        let service = PipService::new(index);
+        assert!(service.lookup(175.0, 66.0, None).region.is_some());
+        assert!(service.lookup(-175.0, 66.0, None).region.is_some());
+        // Far outside: the envelope must not span the whole globe
+        assert!(service.lookup(0.0, 66.0, None).region.is_none());
+    }
 }
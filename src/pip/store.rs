@@ -0,0 +1,612 @@
+//! Compact storage for node coordinates and way/relation member lists.
+//!
+//! Coordinates are stored as 32-bit fixed-point values (1e-7 degree precision, the same
+//! precision OSM uses natively). Node lookups go through either a sorted sparse table
+//! (16 bytes per stored node, binary search) or a dense table indexed directly by node
+//! ID (8 bytes per ID slot, backed by a sparse file). Way-node and relation-member lists
+//! are either kept in memory or written to memory-mapped files on disk.
+
+use anyhow::{Context, Result};
+use geo::Coord;
+use hashbrown::HashMap;
+use memmap2::{Mmap, MmapMut};
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use tempfile::tempfile;
+
+/// Fixed-point scale for coordinates
+const COORD_SCALE: f64 = 1e7;
+
+/// Latitude bias for the dense table so that an all-zero slot means "missing"
+const DENSE_LAT_BIAS: i64 = 1_000_000_000;
+
+/// Initial and minimum growth of the ID slots of the dense table (8 MiB)
+const DENSE_GROW_SLOTS: u64 = 1 << 20;
+
+/// Number of bits per page of an [`IdSet`]
+const PAGE_BITS: u64 = 1 << 16;
+const PAGE_WORDS: usize = (PAGE_BITS / 64) as usize;
+
+/// Metadata file marking a complete on-disk store
+const META_FILE: &str = "store.json";
+
+/// Where the geometry store keeps its data
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StoreMode {
+    /// Way and relation lists in RAM, node coordinates in an anonymous temp file
+    #[default]
+    Memory,
+    /// Everything in memory-mapped files under a directory (planet-scale imports)
+    Disk,
+}
+
+impl std::str::FromStr for StoreMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "memory" => Ok(StoreMode::Memory),
+            "disk" => Ok(StoreMode::Disk),
+            _ => Err(format!("unknown geometry store mode: {} (memory|disk)", s)),
+        }
+    }
+}
+
+/// How node coordinates are indexed
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NodeIndexMode {
+    /// Sorted (id, lon, lat) records, 16 bytes per stored node
+    #[default]
+    Sparse,
+    /// Table indexed by node ID, 8 bytes per ID slot (best for planet/continent extracts)
+    Dense,
+}
+
+impl std::str::FromStr for NodeIndexMode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "sparse" => Ok(NodeIndexMode::Sparse),
+            "dense" => Ok(NodeIndexMode::Dense),
+            _ => Err(format!("unknown node index mode: {} (sparse|dense)", s)),
+        }
+    }
+}
+
+/// Options for building a geometry store
+#[derive(Debug, Clone, Default)]
+pub struct StoreOptions {
+    pub mode: StoreMode,
+    pub node_index: NodeIndexMode,
+    /// Directory for disk mode (required when `mode == Disk`)
+    pub dir: Option<PathBuf>,
+    /// Identifies the input the store was built from, so a persisted store can be reused
+    pub fingerprint: Option<String>,
+}
+
+/// Metadata persisted alongside an on-disk store
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct StoreMeta {
+    pub node_index: NodeIndexMode,
+    pub fingerprint: Option<String>,
+    pub nodes: usize,
+    pub ways: usize,
+    pub relations: usize,
+}
+
+impl StoreMeta {
+    pub fn load(dir: &Path) -> Option<Self> {
+        let content = std::fs::read_to_string(dir.join(META_FILE)).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    pub fn save(&self, dir: &Path) -> Result<()> {
+        let path = dir.join(META_FILE);
+        let tmp = dir.join(format!("{}.tmp", META_FILE));
+        std::fs::write(&tmp, serde_json::to_string_pretty(self)?)?;
+        std::fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    /// Remove the completion marker (before rebuilding a store in place)
+    pub fn clear(dir: &Path) {
+        let _ = std::fs::remove_file(dir.join(META_FILE));
+    }
+}
+
+/// Cheap fingerprint (size + mtime) of an input file
+pub fn file_fingerprint(path: &Path) -> Result<String> {
+    let meta =
+        std::fs::metadata(path).with_context(|| format!("Failed to stat {}", path.display()))?;
+    let mtime = meta
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0);
+    Ok(format!("{}-{}", meta.len(), mtime))
+}
+
+fn to_fixed(v: f64) -> i32 {
+    (v * COORD_SCALE).round() as i32
+}
+
+fn from_fixed(v: i32) -> f64 {
+    v as f64 / COORD_SCALE
+}
+
+/// Paged bitmap of OSM IDs.
+///
+/// Uses one bit per ID within 64 Ki-ID pages that are only allocated when touched, so
+/// it stays small for regional extracts and far below a `HashSet` for planet imports.
+#[derive(Default)]
+pub(crate) struct IdSet {
+    pages: HashMap<u64, Box<[u64; PAGE_WORDS]>>,
+    len: usize,
+}
+
+impl IdSet {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn split(id: i64) -> (u64, usize, u64) {
+        let id = id as u64;
+        let page = id / PAGE_BITS;
+        let bit = id % PAGE_BITS;
+        (page, (bit / 64) as usize, 1 << (bit % 64))
+    }
+
+    /// Insert an ID, returning true if it was not present yet
+    pub fn insert(&mut self, id: i64) -> bool {
+        let (page, word, mask) = Self::split(id);
+        let page = self
+            .pages
+            .entry(page)
+            .or_insert_with(|| Box::new([0; PAGE_WORDS]));
+        if page[word] & mask != 0 {
+            return false;
+        }
+        page[word] |= mask;
+        self.len += 1;
+        true
+    }
+
+    pub fn contains(&self, id: i64) -> bool {
+        let (page, word, mask) = Self::split(id);
+        self.pages
+            .get(&page)
+            .map(|p| p[word] & mask != 0)
+            .unwrap_or(false)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct SparseNode {
+    id: i64,
+    lon: i32,
+    lat: i32,
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct DenseNode {
+    lon: i32,
+    /// Latitude plus [`DENSE_LAT_BIAS`]; zero means the slot is empty
+    lat: i32,
+}
+
+/// View a plain-old-data value as bytes
+fn as_bytes<T: Copy>(value: &T) -> &[u8] {
+    // Safety: only used with #[repr(C)] structs of plain integers
+    unsafe { std::slice::from_raw_parts(value as *const T as *const u8, std::mem::size_of::<T>()) }
+}
+
+/// View a memory map as a slice of plain-old-data records
+fn as_records<T: Copy>(mmap: &[u8]) -> &[T] {
+    // Safety: mmaps are page aligned and the files are written as whole records
+    unsafe {
+        std::slice::from_raw_parts(
+            mmap.as_ptr() as *const T,
+            mmap.len() / std::mem::size_of::<T>(),
+        )
+    }
+}
+
+fn create_file(dir: Option<&Path>, name: &str) -> Result<File> {
+    match dir {
+        Some(dir) => OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(true)
+            .open(dir.join(name))
+            .with_context(|| format!("Failed to create {}", dir.join(name).display())),
+        None => Ok(tempfile()?),
+    }
+}
+
+fn map_file(dir: &Path, name: &str) -> Result<Mmap> {
+    let file = File::open(dir.join(name))
+        .with_context(|| format!("Failed to open store file {}", name))?;
+    Ok(unsafe { Mmap::map(&file)? })
+}
+
+/// Node coordinate table
+pub(crate) enum NodeCoords {
+    Sparse(Mmap),
+    Dense(Mmap),
+}
+
+impl NodeCoords {
+    const FILE: &'static str = "nodes.bin";
+
+    pub fn open(dir: &Path, mode: NodeIndexMode) -> Result<Self> {
+        let mmap = map_file(dir, Self::FILE)?;
+        Ok(match mode {
+            NodeIndexMode::Sparse => NodeCoords::Sparse(mmap),
+            NodeIndexMode::Dense => NodeCoords::Dense(mmap),
+        })
+    }
+
+    pub fn get(&self, id: i64) -> Option<Coord<f64>> {
+        match self {
+            NodeCoords::Sparse(mmap) => {
+                let slice: &[SparseNode] = as_records(mmap);
+                let idx = slice.binary_search_by_key(&id, |n| n.id).ok()?;
+                let node = &slice[idx];
+                Some(Coord {
+                    x: from_fixed(node.lon),
+                    y: from_fixed(node.lat),
+                })
+            }
+            NodeCoords::Dense(mmap) => {
+                if id < 0 {
+                    return None;
+                }
+                let slice: &[DenseNode] = as_records(mmap);
+                let node = slice.get(id as usize)?;
+                if node.lat == 0 {
+                    return None;
+                }
+                Some(Coord {
+                    x: from_fixed(node.lon),
+                    y: from_fixed((node.lat as i64 - DENSE_LAT_BIAS) as i32),
+                })
+            }
+        }
+    }
+
+    /// Size of the backing table in bytes
+    pub fn byte_len(&self) -> usize {
+        match self {
+            NodeCoords::Sparse(m) | NodeCoords::Dense(m) => m.len(),
+        }
+    }
+}
+
+/// Incrementally writes the node coordinate table
+pub(crate) enum NodeCoordsWriter {
+    Sparse {
+        file: File,
+        writer: BufWriter<File>,
+        sorted: bool,
+        last_id: i64,
+    },
+    Dense {
+        file: File,
+        mmap: MmapMut,
+        slots: u64,
+    },
+}
+
+impl NodeCoordsWriter {
+    pub fn new(mode: NodeIndexMode, dir: Option<&Path>) -> Result<Self> {
+        let file = create_file(dir, NodeCoords::FILE)?;
+        Ok(match mode {
+            NodeIndexMode::Sparse => NodeCoordsWriter::Sparse {
+                writer: BufWriter::new(file.try_clone()?),
+                file,
+                sorted: true,
+                last_id: i64::MIN,
+            },
+            NodeIndexMode::Dense => {
+                let slots = DENSE_GROW_SLOTS;
+                file.set_len(slots * std::mem::size_of::<DenseNode>() as u64)?;
+                let mmap = unsafe { MmapMut::map_mut(&file)? };
+                NodeCoordsWriter::Dense { file, mmap, slots }
+            }
+        })
+    }
+
+    pub fn push(&mut self, id: i64, lon: f64, lat: f64) -> Result<()> {
+        match self {
+            NodeCoordsWriter::Sparse {
+                writer,
+                sorted,
+                last_id,
+                ..
+            } => {
+                if id < *last_id {
+                    *sorted = false;
+                }
+                *last_id = id;
+                let node = SparseNode {
+                    id,
+                    lon: to_fixed(lon),
+                    lat: to_fixed(lat),
+                };
+                writer.write_all(as_bytes(&node))?;
+            }
+            NodeCoordsWriter::Dense { file, mmap, slots } => {
+                if id < 0 {
+                    return Ok(());
+                }
+                let slot = id as u64;
+                if slot >= *slots {
+                    // Grow the sparse file geometrically, so a planet import remaps it
+                    // only a dozen times; untouched pages never hit the disk. Written
+                    // pages stay in the shared mapping's file, so nothing needs a flush.
+                    *slots = (*slots * 2).max((slot / DENSE_GROW_SLOTS + 1) * DENSE_GROW_SLOTS);
+                    file.set_len(*slots * std::mem::size_of::<DenseNode>() as u64)?;
+                    *mmap = unsafe { MmapMut::map_mut(&*file)? };
+                }
+                let table: &mut [DenseNode] = unsafe {
+                    std::slice::from_raw_parts_mut(
+                        mmap.as_mut_ptr() as *mut DenseNode,
+                        *slots as usize,
+                    )
+                };
+                table[slot as usize] = DenseNode {
+                    lon: to_fixed(lon),
+                    lat: (to_fixed(lat) as i64 + DENSE_LAT_BIAS) as i32,
+                };
+            }
+        }
+        Ok(())
+    }
+
+    pub fn finish(self) -> Result<NodeCoords> {
+        match self {
+            NodeCoordsWriter::Sparse {
+                file,
+                mut writer,
+                sorted,
+                ..
+            } => {
+                writer.flush()?;
+                drop(writer);
+
+                let mut mmap = unsafe { MmapMut::map_mut(&file)? };
+                if !sorted {
+                    tracing::info!("Node data not sorted, sorting in-place...");
+                    let slice: &mut [SparseNode] = unsafe {
+                        std::slice::from_raw_parts_mut(
+                            mmap.as_mut_ptr() as *mut SparseNode,
+                            mmap.len() / std::mem::size_of::<SparseNode>(),
+                        )
+                    };
+                    slice.sort_unstable_by_key(|n| n.id);
+                }
+                mmap.flush()?;
+                Ok(NodeCoords::Sparse(mmap.make_read_only()?))
+            }
+            NodeCoordsWriter::Dense { mmap, .. } => {
+                mmap.flush()?;
+                Ok(NodeCoords::Dense(mmap.make_read_only()?))
+            }
+        }
+    }
+}
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+struct ListEntry {
+    id: i64,
+    offset: u64,
+    len: u64,
+}
+
+/// Mapping from an OSM ID to a list of member IDs (way nodes, relation ways)
+pub(crate) enum IdLists {
+    Memory(HashMap<i64, Vec<i64>>),
+    Mapped { index: Mmap, data: Mmap },
+}
+
+impl IdLists {
+    pub fn open(dir: &Path, name: &str) -> Result<Self> {
+        Ok(IdLists::Mapped {
+            index: map_file(dir, &format!("{}.idx", name))?,
+            data: map_file(dir, &format!("{}.dat", name))?,
+        })
+    }
+
+    pub fn get(&self, id: i64) -> Option<&[i64]> {
+        match self {
+            IdLists::Memory(map) => map.get(&id).map(|v| v.as_slice()),
+            IdLists::Mapped { index, data } => {
+                let entries: &[ListEntry] = as_records(index);
+                let idx = entries.binary_search_by_key(&id, |e| e.id).ok()?;
+                let entry = &entries[idx];
+                let values: &[i64] = as_records(data);
+                values.get(entry.offset as usize..(entry.offset + entry.len) as usize)
+            }
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        match self {
+            IdLists::Memory(map) => map.len(),
+            IdLists::Mapped { index, .. } => index.len() / std::mem::size_of::<ListEntry>(),
+        }
+    }
+}
+
+/// Incrementally writes an [`IdLists`]
+pub(crate) enum IdListsWriter {
+    Memory(HashMap<i64, Vec<i64>>),
+    Mapped {
+        index_file: File,
+        index: BufWriter<File>,
+        data: BufWriter<File>,
+        data_path: PathBuf,
+        offset: u64,
+        sorted: bool,
+        last_id: i64,
+    },
+}
+
+impl IdListsWriter {
+    pub fn new(mode: StoreMode, dir: Option<&Path>, name: &str) -> Result<Self> {
+        match (mode, dir) {
+            (StoreMode::Disk, Some(dir)) => {
+                let index_file = create_file(Some(dir), &format!("{}.idx", name))?;
+                let data_file = create_file(Some(dir), &format!("{}.dat", name))?;
+                Ok(IdListsWriter::Mapped {
+                    index: BufWriter::new(index_file.try_clone()?),
+                    index_file,
+                    data: BufWriter::new(data_file),
+                    data_path: dir.join(format!("{}.dat", name)),
+                    offset: 0,
+                    sorted: true,
+                    last_id: i64::MIN,
+                })
+            }
+            _ => Ok(IdListsWriter::Memory(HashMap::new())),
+        }
+    }
+
+    pub fn push(&mut self, id: i64, values: impl IntoIterator<Item = i64>) -> Result<()> {
+        match self {
+            IdListsWriter::Memory(map) => {
+                map.insert(id, values.into_iter().collect());
+            }
+            IdListsWriter::Mapped {
+                index,
+                data,
+                offset,
+                sorted,
+                last_id,
+                ..
+            } => {
+                if id < *last_id {
+                    *sorted = false;
+                }
+                *last_id = id;
+                let start = *offset;
+                for value in values {
+                    data.write_all(&value.to_ne_bytes())?;
+                    *offset += 1;
+                }
+                let entry = ListEntry {
+                    id,
+                    offset: start,
+                    len: *offset - start,
+                };
+                index.write_all(as_bytes(&entry))?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn finish(self) -> Result<IdLists> {
+        match self {
+            IdListsWriter::Memory(map) => Ok(IdLists::Memory(map)),
+            IdListsWriter::Mapped {
+                index_file,
+                mut index,
+                mut data,
+                data_path,
+                sorted,
+                ..
+            } => {
+                index.flush()?;
+                drop(index);
+                data.flush()?;
+                drop(data);
+
+                let mut index_map = unsafe { MmapMut::map_mut(&index_file)? };
+                if !sorted {
+                    let entries: &mut [ListEntry] = unsafe {
+                        std::slice::from_raw_parts_mut(
+                            index_map.as_mut_ptr() as *mut ListEntry,
+                            index_map.len() / std::mem::size_of::<ListEntry>(),
+                        )
+                    };
+                    entries.sort_unstable_by_key(|e| e.id);
+                }
+                index_map.flush()?;
+
+                let data_file = File::open(&data_path)?;
+                Ok(IdLists::Mapped {
+                    index: index_map.make_read_only()?,
+                    data: unsafe { Mmap::map(&data_file)? },
+                })
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_id_set() {
+        let mut set = IdSet::new();
+        assert!(set.insert(1));
+        assert!(!set.insert(1));
+        assert!(set.insert(12_000_000_000));
+        assert!(set.contains(1));
+        assert!(set.contains(12_000_000_000));
+        assert!(!set.contains(2));
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn test_node_coords_roundtrip() {
+        for mode in [NodeIndexMode::Sparse, NodeIndexMode::Dense] {
+            let mut writer = NodeCoordsWriter::new(mode, None).unwrap();
+            writer.push(5, 8.5417, 47.3769).unwrap();
+            writer.push(3, -0.0, 0.0).unwrap();
+            writer.push(1 << 20, 7.0, 46.0).unwrap();
+            writer.push(3_000_000, 179.9999999, -89.9999999).unwrap();
+            let coords = writer.finish().unwrap();
+
+            let c = coords.get(5).unwrap();
+            assert!((c.x - 8.5417).abs() < 1e-7 && (c.y - 47.3769).abs() < 1e-7);
+            // Null Island is a valid coordinate, not an empty slot
+            assert_eq!(coords.get(3), Some(Coord { x: 0.0, y: 0.0 }));
+            assert_eq!(coords.get(1 << 20), Some(Coord { x: 7.0, y: 46.0 }));
+            assert!(coords.get(3_000_000).is_some());
+            assert_eq!(coords.get(4), None);
+        }
+    }
+
+    #[test]
+    fn test_id_lists_disk_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut writer = IdListsWriter::new(StoreMode::Disk, Some(dir.path()), "ways").unwrap();
+        writer.push(20, [1, 2, 3]).unwrap();
+        writer.push(10, [4, 5]).unwrap();
+        let lists = writer.finish().unwrap();
+
+        assert_eq!(lists.len(), 2);
+        assert_eq!(lists.get(10), Some(&[4, 5][..]));
+        assert_eq!(lists.get(20), Some(&[1, 2, 3][..]));
+        assert_eq!(lists.get(30), None);
+
+        let reopened = IdLists::open(dir.path(), "ways").unwrap();
+        assert_eq!(reopened.get(20), Some(&[1, 2, 3][..]));
+    }
+}
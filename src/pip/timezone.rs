@@ -0,0 +1,127 @@
+//! Time zone lookup from timezone-boundary-builder polygons.
+//!
+//! The release GeoJSON (`combined.geojson` or `combined-with-oceans.geojson`) holds one
+//! feature per IANA time zone with a `tzid` property.
+
+use std::path::Path;
+
+use anyhow::{bail, Result};
+use geo::{BoundingRect, Contains, MultiPolygon, Point};
+use rstar::{RTree, RTreeObject, AABB};
+use tracing::info;
+
+use super::geojson::read_polygon_features;
+
+/// One polygon of a time zone
+struct IndexedZone {
+    tzid: usize,
+    geometry: MultiPolygon<f64>,
+    envelope: AABB<[f64; 2]>,
+}
+
+impl RTreeObject for IndexedZone {
+    type Envelope = AABB<[f64; 2]>;
+
+    fn envelope(&self) -> Self::Envelope {
+        self.envelope
+    }
+}
+
+/// Spatial index of time zone polygons
+pub struct TimezoneIndex {
+    tzids: Vec<String>,
+    tree: RTree<IndexedZone>,
+}
+
+impl TimezoneIndex {
+    /// Load the time zones of a timezone-boundary-builder GeoJSON file
+    pub fn load(path: &Path) -> Result<Self> {
+        info!("Loading time zones from {}", path.display());
+        let mut tzids = Vec::new();
+        let mut zones = Vec::new();
+
+        for feature in read_polygon_features(path)? {
+            let Some(tzid) = feature.properties.get("tzid").and_then(|v| v.as_str()) else {
+                continue;
+            };
+            let index = tzids.len();
+            tzids.push(tzid.to_string());
+
+            // Index each polygon on its own, zones spread over several islands would
+            // otherwise get huge envelopes
+            for polygon in feature.geometry {
+                let Some(rect) = polygon.bounding_rect() else {
+                    continue;
+                };
+                zones.push(IndexedZone {
+                    tzid: index,
+                    geometry: MultiPolygon(vec![polygon]),
+                    envelope: AABB::from_corners(rect.min().into(), rect.max().into()),
+                });
+            }
+        }
+
+        if zones.is_empty() {
+            bail!(
+                "{} contains no time zones (features with a tzid)",
+                path.display()
+            );
+        }
+        info!("Loaded {} time zones", tzids.len());
+
+        Ok(Self {
+            tzids,
+            tree: RTree::bulk_load(zones),
+        })
+    }
+
+    /// IANA time zone at a point, e.g. "Europe/Zurich"
+    pub fn lookup(&self, lon: f64, lat: f64) -> Option<&str> {
+        let point = Point::new(lon, lat);
+        self.tree
+            .locate_in_envelope_intersecting(&AABB::from_point([lon, lat]))
+            .find(|zone| zone.geometry.contains(&point))
+            .map(|zone| self.tzids[zone.tzid].as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_lookup() {
+        let mut file = tempfile::NamedTempFile::new().unwrap();
+        write!(
+            file,
+            r#"{{
+                "type": "FeatureCollection",
+                "features": [
+                    {{
+                        "type": "Feature",
+                        "properties": {{ "tzid": "Europe/Zurich" }},
+                        "geometry": {{
+                            "type": "Polygon",
+                            "coordinates": [[[6, 45.8], [10.5, 45.8], [10.5, 47.8], [6, 47.8], [6, 45.8]]]
+                        }}
+                    }},
+                    {{
+                        "type": "Feature",
+                        "properties": {{ "tzid": "Europe/Vienna" }},
+                        "geometry": {{
+                            "type": "Polygon",
+                            "coordinates": [[[10.5, 46.4], [17.2, 46.4], [17.2, 49], [10.5, 49], [10.5, 46.4]]]
+                        }}
+                    }}
+                ]
+            }}"#
+        )
+        .unwrap();
+
+        let index = TimezoneIndex::load(file.path()).unwrap();
+        assert_eq!(index.lookup(8.54, 47.37), Some("Europe/Zurich"));
+        assert_eq!(index.lookup(16.37, 48.21), Some("Europe/Vienna"));
+        assert_eq!(index.lookup(2.35, 48.86), None);
+    }
+}
@@ -305,6 +305,8 @@ struct SearchResponseV2 {
 }
 
 /// Parse bbox string "minLon,minLat,maxLon,maxLat"
+///
+/// minLon > maxLon is allowed and denotes a box crossing the antimeridian.
 fn parse_bbox(bbox: &Option<String>) -> Option<[f64; 4]> {
     bbox.as_ref().and_then(|s| {
         let parts: Vec<f64> = s.split(',').filter_map(|p| p.trim().parse().ok()).collect();
@@ -9,6 +9,7 @@ use cypress::models::normalized::NormalizedPlace;
 use cypress::models::place::Layer;
 use cypress::models::AdminEntry;
 use cypress::scylla::ScyllaClient;
+use cypress::text::{cjk, housenumber, phonetic, street};
 use regex::Regex;
 use std::sync::OnceLock;
 
@@ -63,8 +64,17 @@ pub struct Properties {
     pub street: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub postcode: Option<String>,
+    /// The postcode was inferred from nearby addresses
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub postcode_inferred: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub country: Option<String>,
+    /// ISO 3166-1 alpha-2 code of the country
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub country_code: Option<String>,
+    /// ISO 3166-1 alpha-3 code of the country
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub country_a: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub region: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -73,8 +83,14 @@ pub struct Properties {
     pub locality: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub neighbourhood: Option<String>,
+    /// IANA time zone, e.g. "Europe/Zurich"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub categories: Vec<String>,
+    /// Bounding box as [minLon, minLat, maxLon, maxLat] (minLon > maxLon when crossing the antimeridian)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bbox: Option<[f64; 4]>,
     pub confidence: f64,
 }
 
@@ -91,8 +107,17 @@ pub struct PropertiesV2 {
     pub street: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub postcode: Option<String>,
+    /// The postcode was inferred from nearby addresses
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub postcode_inferred: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub country: Option<String>,
+    /// ISO 3166-1 alpha-2 code of the country
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub country_code: Option<String>,
+    /// ISO 3166-1 alpha-3 code of the country
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub country_a: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub country_names: Option<HashMap<String, String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -111,8 +136,17 @@ pub struct PropertiesV2 {
     pub neighbourhood: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub neighbourhood_names: Option<HashMap<String, String>>,
+    /// IANA time zone, e.g. "Europe/Zurich"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+    /// Elevation in metres
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub elevation: Option<f64>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub categories: Vec<String>,
+    /// Bounding box as [minLon, minLat, maxLon, maxLat] (minLon > maxLon when crossing the antimeridian)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bbox: Option<[f64; 4]>,
     pub confidence: f64,
 }
 
@@ -295,6 +329,7 @@ async fn execute_search_internal(
     let mut body = build_search_query(&params, autocomplete);
 
     // Add bounding box filter
+    // A bbox with minLon > maxLon crosses the antimeridian; ES handles such boxes natively.
     if let Some(bbox) = params.bbox {
         let filter = json!({
             "geo_bounding_box": {
@@ -604,6 +639,17 @@ fn resolve_admin_names(
         .and_then(|id_str| map.get(id_str).map(|entry| entry.names.clone()))
 }
 
+/// ISO code of the country, also for the country itself
+fn resolve_country_code(
+    id: &Option<String>,
+    map: &HashMap<String, AdminEntry>,
+    code: impl Fn(&AdminEntry) -> &Option<String>,
+) -> Option<String> {
+    id.as_ref()
+        .and_then(|id_str| map.get(id_str))
+        .and_then(|entry| code(entry).clone())
+}
+
 /// Convert a Place model to SearchResult
 fn place_to_search_result(
     place: NormalizedPlace,
@@ -644,12 +690,17 @@ fn place_to_search_result(
             housenumber: place.address.as_ref().and_then(|a| a.housenumber.clone()),
             street: place.address.as_ref().and_then(|a| a.street.clone()),
             postcode: place.address.as_ref().and_then(|a| a.postcode.clone()),
+            postcode_inferred: place.address.as_ref().is_some_and(|a| a.postcode_inferred),
             country: resolve_if_larger(Layer::Country, &place.parent.country),
+            country_code: resolve_country_code(&place.parent.country, admin_map, |c| &c.alpha2),
+            country_a: resolve_country_code(&place.parent.country, admin_map, |c| &c.alpha3),
             region: resolve_if_larger(Layer::Region, &place.parent.region),
             county: resolve_if_larger(Layer::County, &place.parent.county),
             locality: resolve_if_larger(Layer::Locality, &place.parent.locality),
             neighbourhood: resolve_if_larger(Layer::Neighbourhood, &place.parent.neighbourhood),
+            timezone: place.timezone,
             categories: place.categories,
+            bbox: place.bbox.as_ref().map(|b| b.to_array()),
             confidence: score,
         },
     })
@@ -703,7 +754,10 @@ fn place_to_search_result_v2(
             housenumber: place.address.as_ref().and_then(|a| a.housenumber.clone()),
             street: place.address.as_ref().and_then(|a| a.street.clone()),
             postcode: place.address.as_ref().and_then(|a| a.postcode.clone()),
+            postcode_inferred: place.address.as_ref().is_some_and(|a| a.postcode_inferred),
             country: resolve_if_larger(Layer::Country, &place.parent.country),
+            country_code: resolve_country_code(&place.parent.country, admin_map, |c| &c.alpha2),
+            country_a: resolve_country_code(&place.parent.country, admin_map, |c| &c.alpha3),
             country_names: resolve_names_if_larger(Layer::Country, &place.parent.country),
             region: resolve_if_larger(Layer::Region, &place.parent.region),
             region_names: resolve_names_if_larger(Layer::Region, &place.parent.region),
@@ -716,7 +770,10 @@ fn place_to_search_result_v2(
                 Layer::Neighbourhood,
                 &place.parent.neighbourhood,
             ),
+            timezone: place.timezone,
+            elevation: place.elevation,
             categories: place.categories,
+            bbox: place.bbox.as_ref().map(|b| b.to_array()),
             confidence: score,
         },
     })
@@ -739,30 +796,49 @@ fn get_layer_rank(layer: Layer) -> u8 {
 }
 
 fn build_search_query(params: &SearchParams, autocomplete: bool) -> serde_json::Value {
+    // Compound street names are indexed with their suffix split off as well, so the query
+    // also matches in that form ("Bahnhofstr. 10" -> "bahnhof strasse 10")
+    let languages = street_languages(params);
+    let mut alternatives = text_queries(&params.text, NAME_FIELDS, &languages);
+    if let Some(address) = address_query(&params.text, &languages) {
+        alternatives.push(address);
+    }
+    // CJK names are indexed as characters and character pairs for partial matches
+    if let Some(ngrams) = cjk::query_ngrams(&params.text) {
+        alternatives.push(json!({
+            "match": {
+                "name_cjk": {
+                    "query": ngrams,
+                    "operator": "and"
+                }
+            }
+        }));
+    }
+
+    if let Some(phonetic) = phonetic_query(&params.text) {
+        alternatives.push(phonetic);
+    }
+
+    let text_match = if alternatives.len() == 1 {
+        alternatives.remove(0)
+    } else {
+        json!({
+            "bool": {
+                "should": alternatives,
+                "minimum_should_match": 1
+            }
+        })
+    };
+
+    search_body(text_match, params.size)
+}
+
+/// Request body for places matching `text_match`, ranked by importance
+fn search_body(text_match: serde_json::Value, size: usize) -> serde_json::Value {
     json!({
         "query": {
             "bool": {
-                "must": [
-                    {
-                        "multi_match": {
-                            "query": &params.text,
-                            "fields": [
-                                "name_all",
-                                "parent.country.name",
-                                "parent.macro_region.name",
-                                "parent.region.name",
-                                "parent.macro_county.name",
-                                "parent.county.name",
-                                "parent.local_admin.name",
-                                "parent.locality.name",
-                                "parent.borough.name",
-                                "parent.neighbourhood.name"
-                            ],
-                            "type": "cross_fields",
-                            "operator": "and"
-                        }
-                    }
-                ],
+                "must": [text_match],
                 "should": [
                     {
                         "rank_feature": {
@@ -773,11 +849,201 @@ fn build_search_query(params: &SearchParams, autocomplete: bool) -> serde_json::
                 ]
             }
         },
-        "size": params.size,
+        "size": size,
         "stored_fields": ["_id"]
     })
 }
 
+/// Transliterations ("Moskva" for "Москва") weigh less than the native names
+const NAME_FIELDS: &[&str] = &[
+    "name_all",
+    "name_transliterated^0.5",
+    "parent.country.name",
+    "parent.country.alpha2",
+    "parent.country.alpha3",
+    "parent.macro_region.name",
+    "parent.region.name",
+    "parent.macro_county.name",
+    "parent.county.name",
+    "parent.local_admin.name",
+    "parent.locality.name",
+    "parent.borough.name",
+    "parent.neighbourhood.name",
+];
+
+const ADDRESS_FIELDS: &[&str] = &[
+    "address.street",
+    "parent.country.name",
+    "parent.country.alpha2",
+    "parent.country.alpha3",
+    "parent.macro_region.name",
+    "parent.region.name",
+    "parent.macro_county.name",
+    "parent.county.name",
+    "parent.local_admin.name",
+    "parent.locality.name",
+    "parent.borough.name",
+    "parent.neighbourhood.name",
+];
+
+/// Boost of an exact house number match over the nearest-number fallback
+const HOUSENUMBER_EXACT_BOOST: f64 = 5.0;
+
+/// House number distance at which the fallback score halves
+const HOUSENUMBER_DECAY_SCALE: u32 = 10;
+
+/// Languages whose street types are split off query words: the requested language, or
+/// all of them without one
+fn street_languages(params: &SearchParams) -> Vec<&str> {
+    match params.lang.as_deref() {
+        Some(lang) => vec![lang],
+        None => street::LANGUAGES.to_vec(),
+    }
+}
+
+/// Match all terms of `text` across `fields`: the text itself, then its street variants
+/// in `languages`
+fn text_queries(text: &str, fields: &[&str], languages: &[&str]) -> Vec<serde_json::Value> {
+    std::iter::once(text.to_string())
+        .chain(street::variants(text, languages))
+        .map(|text| {
+            json!({
+                "multi_match": {
+                    "query": text,
+                    "fields": fields,
+                    "type": "cross_fields",
+                    "operator": "and"
+                }
+            })
+        })
+        .collect()
+}
+
+/// Weight of sound-alike matches, so they only decide when nothing matches as typed
+const PHONETIC_BOOST: f64 = 0.2;
+
+/// Names whose words sound like the query words ("Zurick" for "Zürich")
+fn phonetic_query(text: &str) -> Option<serde_json::Value> {
+    let words = phonetic::query_keys(text);
+    if words.is_empty() {
+        return None;
+    }
+
+    let must: Vec<serde_json::Value> = words
+        .into_iter()
+        .map(|keys| json!({ "terms": { "name_phonetic": keys } }))
+        .collect();
+    Some(json!({
+        "bool": {
+            "must": must,
+            "boost": PHONETIC_BOOST
+        }
+    }))
+}
+
+/// Addresses on the street named by the rest of the query, exact house number first and
+/// then the nearest numbers. `None` if the query has no house number.
+fn address_query(text: &str, languages: &[&str]) -> Option<serde_json::Value> {
+    let (number, rest) = split_housenumber(text)?;
+
+    let mut should = vec![json!({
+        "term": {
+            "address.housenumber": {
+                "value": number.canonical,
+                "boost": HOUSENUMBER_EXACT_BOOST
+            }
+        }
+    })];
+    if let Some(n) = number.number {
+        should.push(json!({
+            "function_score": {
+                "query": { "exists": { "field": "address.housenumber_number" } },
+                "functions": [
+                    {
+                        "gauss": {
+                            "address.housenumber_number": {
+                                "origin": n,
+                                "scale": HOUSENUMBER_DECAY_SCALE,
+                                "decay": 0.5
+                            }
+                        }
+                    }
+                ],
+                "boost_mode": "replace"
+            }
+        }));
+    }
+
+    Some(json!({
+        "bool": {
+            "must": [
+                {
+                    "bool": {
+                        "should": text_queries(&rest, ADDRESS_FIELDS, languages),
+                        "minimum_should_match": 1
+                    }
+                }
+            ],
+            "should": should
+        }
+    }))
+}
+
+/// Take the house number out of a query: "Bahnhofstrasse 12 A Zürich" -> ("12a",
+/// "Bahnhofstrasse Zürich"). Plain numbers of four or more digits are taken for postcodes
+/// where they stand next to the locality: besides a shorter house number ("Bahnhofstrasse
+/// 12, 8001 Zürich"), at the end of the query ("Zürich 8001") or before its last word
+/// ("8001 Zürich"). Elsewhere they are house numbers ("1600 Pennsylvania Avenue").
+fn split_housenumber(text: &str) -> Option<(housenumber::HouseNumber, String)> {
+    let tokens: Vec<&str> = text.split_whitespace().collect();
+
+    // Numbers with an optional short suffix, and whether they could be postcodes
+    let candidates: Vec<(usize, bool)> = tokens
+        .iter()
+        .enumerate()
+        .filter_map(|(i, token)| {
+            let token = token.trim_end_matches(',').to_lowercase();
+            let digits = token.chars().take_while(|c| c.is_ascii_digit()).count();
+            let suffix = &token[digits..];
+            let is_number =
+                digits > 0 && suffix.len() <= 4 && !["st", "nd", "rd", "th"].contains(&suffix);
+            is_number.then_some((i, digits >= 4 && suffix.is_empty()))
+        })
+        .collect();
+
+    let start = match candidates
+        .iter()
+        .find(|&&(_, postcode_like)| !postcode_like)
+    {
+        Some(&(i, _)) => i,
+        None => candidates
+            .iter()
+            .map(|&(i, _)| i)
+            .find(|&i| i + 2 < tokens.len())?,
+    };
+
+    // "12 A", "12 bis"
+    let mut end = start + 1;
+    if let Some(next) = tokens.get(end) {
+        let next = next.trim_end_matches(',').to_lowercase();
+        let letter = next.chars().count() == 1 && next.chars().all(char::is_alphabetic);
+        if letter || ["bis", "ter", "quater"].contains(&next.as_str()) {
+            end += 1;
+        }
+    }
+
+    let number = housenumber::normalize(tokens[start..end].join(" ").trim_end_matches(','))?;
+    let rest: Vec<&str> = tokens[..start]
+        .iter()
+        .chain(&tokens[end..])
+        .copied()
+        .collect();
+    if rest.is_empty() {
+        return None;
+    }
+    Some((number, rest.join(" ")))
+}
+
 fn haversine_distance_km(p1: (f64, f64), p2: (f64, f64)) -> f64 {
     let (lat1, lon1) = p1;
     let (lat2, lon2) = p2;
@@ -814,9 +1080,12 @@ mod tests {
         let country_entry = AdminEntry {
             name: Some("United Kingdom".to_string()),
             abbr: Some("UK".to_string()),
+            alpha2: Some("GB".to_string()),
+            alpha3: Some("GBR".to_string()),
             id: Some(1),
             bbox: None,
             names: country_names.clone(),
+            timezone: None,
         };
 
         let mut admin_map = HashMap::new();
@@ -825,6 +1094,7 @@ mod tests {
         let mut place = NormalizedPlace {
             source_id: "test:1".to_string(),
             source_file: "test.osm".to_string(),
+            source_files: vec!["test.osm".to_string()],
             import_timestamp: chrono::Utc::now(),
             osm_type: OsmType::Node,
             osm_id: 123,
@@ -835,6 +1105,9 @@ mod tests {
             name: names.clone(),
             phrase: None,
             address: None,
+            street_id: None,
+            timezone: None,
+            elevation: None,
             center_point: GeoPoint {
                 lon: 0.1,
                 lat: 51.5,
@@ -856,6 +1129,8 @@ mod tests {
         let c_names = result.properties.country_names.unwrap();
         assert_eq!(c_names.get("default").unwrap(), "United Kingdom");
         assert_eq!(c_names.get("de").unwrap(), "Vereinigtes Königreich");
+        assert_eq!(result.properties.country_code.as_deref(), Some("GB"));
+        assert_eq!(result.properties.country_a.as_deref(), Some("GBR"));
 
         // Verify that other fields (like region) would also work if populated (mocking logic verification)
         // Since we mocked country, we know the logic is generic.
@@ -896,6 +1171,7 @@ mod tests {
         let mut place = NormalizedPlace {
             source_id: "test:2".to_string(),
             source_file: "test.osm".to_string(),
+            source_files: vec!["test.osm".to_string()],
             import_timestamp: chrono::Utc::now(),
             osm_type: OsmType::Node,
             osm_id: 456,
@@ -906,6 +1182,9 @@ mod tests {
             name: names,
             phrase: None,
             address: None,
+            street_id: None,
+            timezone: None,
+            elevation: None,
             center_point: GeoPoint {
                 lon: 2.35,
                 lat: 48.85,
@@ -983,11 +1262,170 @@ mod tests {
         assert!(query_json.contains("multi_match"));
         assert!(query_json.contains("Kings Cross London"));
         assert!(query_json.contains("name_all"));
+        assert!(query_json.contains("name_transliterated^0.5"));
         assert!(query_json.contains("parent.country.name"));
         assert!(query_json.contains("parent.locality.name"));
+        assert!(query_json.contains("parent.country.alpha2"));
+        assert!(query_json.contains("parent.country.alpha3"));
         assert!(query_json.contains("cross_fields"));
     }
 
+    #[test]
+    fn test_build_search_query_compound_street() {
+        let params = SearchParams {
+            text: "Bahnhofstr. 10 Zürich".to_string(),
+            lang: None,
+            bbox: None,
+            focus_lat: None,
+            focus_lon: None,
+            focus_weight: None,
+            layers: None,
+            size: 10,
+        };
+
+        let query = build_search_query(&params, false);
+        let should = query["query"]["bool"]["must"][0]["bool"]["should"]
+            .as_array()
+            .unwrap();
+        let texts: Vec<&str> = should
+            .iter()
+            .filter_map(|q| q["multi_match"]["query"].as_str())
+            .collect();
+        assert_eq!(texts[0], "Bahnhofstr. 10 Zürich");
+        assert!(texts.contains(&"bahnhof strasse 10 zürich"));
+
+        // Addresses: the street without the house number, in every spelling
+        let address = should
+            .iter()
+            .find(|q| q.to_string().contains("address.housenumber"))
+            .unwrap();
+        let streets = address["bool"]["must"][0]["bool"]["should"].to_string();
+        assert!(streets.contains("bahnhof strasse zürich"));
+        assert!(streets.contains("address.street"));
+        assert_eq!(
+            address["bool"]["should"][0]["term"]["address.housenumber"]["value"],
+            "10"
+        );
+    }
+
+    #[test]
+    fn test_build_search_query_cjk() {
+        let params = SearchParams {
+            text: "臺北車站".to_string(),
+            lang: None,
+            bbox: None,
+            focus_lat: None,
+            focus_lon: None,
+            focus_weight: None,
+            layers: None,
+            size: 10,
+        };
+
+        let query = build_search_query(&params, false);
+        let should = &query["query"]["bool"]["must"][0]["bool"]["should"];
+        assert_eq!(should[1]["match"]["name_cjk"]["query"], "台北 北车 车站");
+    }
+
+    #[test]
+    fn test_build_search_query_phonetic() {
+        let params = SearchParams {
+            text: "Zurick".to_string(),
+            lang: None,
+            bbox: None,
+            focus_lat: None,
+            focus_lon: None,
+            focus_weight: None,
+            layers: None,
+            size: 10,
+        };
+
+        let query = build_search_query(&params, false);
+        let phonetic = &query["query"]["bool"]["must"][0]["bool"]["should"][1]["bool"];
+        assert_eq!(phonetic["boost"], PHONETIC_BOOST);
+        assert_eq!(
+            phonetic["must"][0]["terms"]["name_phonetic"],
+            json!(["k874", "mSRK"])
+        );
+    }
+
+    #[test]
+    fn test_split_housenumber() {
+        let split =
+            |text: &str| split_housenumber(text).map(|(number, rest)| (number.canonical, rest));
+        assert_eq!(
+            split("Bahnhofstrasse 12 A, Zürich"),
+            Some(("12a".to_string(), "Bahnhofstrasse Zürich".to_string()))
+        );
+        assert_eq!(
+            split("10 Downing Street"),
+            Some(("10".to_string(), "Downing Street".to_string()))
+        );
+        assert_eq!(
+            split("Rue de Rivoli 12 bis"),
+            Some(("12bis".to_string(), "Rue de Rivoli".to_string()))
+        );
+        // Long numbers by position: house number before the street, postcode next to
+        // the locality
+        assert_eq!(
+            split("1600 Pennsylvania Avenue Washington 20500"),
+            Some((
+                "1600".to_string(),
+                "Pennsylvania Avenue Washington 20500".to_string()
+            ))
+        );
+        assert_eq!(
+            split("Avenida Corrientes 1234 Buenos Aires"),
+            Some((
+                "1234".to_string(),
+                "Avenida Corrientes Buenos Aires".to_string()
+            ))
+        );
+        assert_eq!(
+            split("8001 Zürich Bahnhofstrasse 12"),
+            Some(("12".to_string(), "8001 Zürich Bahnhofstrasse".to_string()))
+        );
+        assert_eq!(
+            split("Bahnhofstrasse 12, 8001 Zürich"),
+            Some(("12".to_string(), "Bahnhofstrasse 8001 Zürich".to_string()))
+        );
+        // Postcodes, ordinals and bare numbers are not house numbers
+        assert_eq!(split("8001 Zürich"), None);
+        assert_eq!(split("Zürich 8001"), None);
+        assert_eq!(split("5th Avenue"), None);
+        assert_eq!(split("12"), None);
+    }
+
+    #[test]
+    fn test_place_to_search_result_v2_antimeridian_bbox() {
+        let place = NormalizedPlace {
+            source_id: "relation/571747".to_string(),
+            source_file: "test.osm".to_string(),
+            source_files: vec!["test.osm".to_string()],
+            import_timestamp: chrono::Utc::now(),
+            osm_type: OsmType::Relation,
+            osm_id: 571747,
+            wikidata_id: None,
+            importance: Some(1.0),
+            layer: Layer::Country,
+            categories: vec![],
+            name: HashMap::from([("default".to_string(), "Fiji".to_string())]),
+            phrase: None,
+            address: None,
+            street_id: None,
+            timezone: None,
+            elevation: None,
+            center_point: GeoPoint {
+                lon: 178.0,
+                lat: -17.8,
+            },
+            bbox: Some(cypress::models::GeoBbox::new(176.8, -21.1, -178.2, -12.4)),
+            parent: AdminHierarchyIds::default(),
+        };
+
+        let result = place_to_search_result_v2(place, 1.0, &None, &HashMap::new()).unwrap();
+        assert_eq!(result.properties.bbox, Some([176.8, -21.1, -178.2, -12.4]));
+    }
+
     #[test]
     fn test_place_to_search_result_v2_hierarchy_filtering() {
         let mut names = HashMap::new();
@@ -1021,6 +1459,7 @@ mod tests {
         let mut place = NormalizedPlace {
             source_id: "test:region".to_string(),
             source_file: "test.osm".to_string(),
+            source_files: vec!["test.osm".to_string()],
             import_timestamp: chrono::Utc::now(),
             osm_type: OsmType::Relation,
             osm_id: 111,
@@ -1031,6 +1470,9 @@ mod tests {
             name: names,
             phrase: None,
             address: None,
+            street_id: None,
+            timezone: None,
+            elevation: None,
             center_point: GeoPoint {
                 lon: 2.0,
                 lat: 41.0,
@@ -3,9 +3,13 @@ use scylla::client::session::Session;
 use scylla::client::session_builder::SessionBuilder;
 // use scylla::IntoTypedRows; - Removed broken import
 use scylla::response::query_result::QueryResult;
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
 use tracing::info;
 
+/// Partition keys per `IN` restriction; Scylla rejects more than 100 by default
+const MAX_IN_KEYS: usize = 100;
+
 #[derive(Clone)]
 pub struct ScyllaClient {
     session: Arc<Session>,
@@ -63,14 +67,178 @@ impl ScyllaClient {
             )
             .await?;
 
+        // Hash of the last written content, to skip rewriting unchanged places
+        self.add_column_if_missing("places", "content_hash", "bigint")
+            .await?;
+
+        // Source files (regional extracts) that contain each place, with the import that
+        // last saw it in that file. Extracts overlap at borders, so a place is only
+        // removed once no source file contains it anymore.
+        self.session
+            .query_unpaged(
+                "CREATE TABLE IF NOT EXISTS cypress.place_source_imports (
+                    id text PRIMARY KEY,
+                    files map<text, bigint>
+                )",
+                &[],
+            )
+            .await?;
+
+        Ok(())
+    }
+
+    /// Add a column to a table created by an older version
+    async fn add_column_if_missing(&self, table: &str, column: &str, typ: &str) -> Result<()> {
+        let result = self
+            .session
+            .query_unpaged(
+                "SELECT column_name FROM system_schema.columns
+                 WHERE keyspace_name = 'cypress' AND table_name = ? AND column_name = ?",
+                (table, column),
+            )
+            .await?;
+
+        let exists = result
+            .into_rows_result()
+            .map(|rows| rows.rows_num() > 0)
+            .unwrap_or(false);
+        if !exists {
+            info!("Adding column {}.{}", table, column);
+            self.session
+                .query_unpaged(
+                    format!("ALTER TABLE cypress.{} ADD {} {}", table, column, typ),
+                    &[],
+                )
+                .await?;
+        }
         Ok(())
     }
 
-    pub async fn upsert_place(&self, id: &str, data: &str) -> Result<()> {
+    pub async fn upsert_place(&self, id: &str, data: &str, content_hash: i64) -> Result<()> {
         self.session
             .query_unpaged(
-                "INSERT INTO cypress.places (id, data) VALUES (?, ?)",
-                (id, data),
+                "INSERT INTO cypress.places (id, data, content_hash) VALUES (?, ?, ?)",
+                (id, data, content_hash),
+            )
+            .await?;
+        Ok(())
+    }
+
+    /// Content hashes of the places among `ids` that have one
+    pub async fn get_place_hashes(&self, ids: &[&str]) -> Result<HashMap<String, i64>> {
+        let chunks = ids.chunks(MAX_IN_KEYS).map(|chunk| async move {
+            let result = self
+                .session
+                .query_unpaged(
+                    "SELECT id, content_hash FROM cypress.places WHERE id IN ?",
+                    (chunk,),
+                )
+                .await?;
+            let mut hashes = Vec::new();
+            if let Ok(rows_result) = result.into_rows_result() {
+                for row in rows_result.rows::<(String, Option<i64>)>()? {
+                    if let (id, Some(hash)) = row? {
+                        hashes.push((id, hash));
+                    }
+                }
+            }
+            Ok::<_, anyhow::Error>(hashes)
+        });
+
+        let chunks = futures::future::try_join_all(chunks).await?;
+        Ok(chunks.into_iter().flatten().collect())
+    }
+
+    /// Record that import `import_id` of `source_file` contains the places `ids`
+    pub async fn mark_places_seen(
+        &self,
+        ids: &[&str],
+        source_file: &str,
+        import_id: i64,
+    ) -> Result<()> {
+        let chunks = ids.chunks(MAX_IN_KEYS).map(|chunk| {
+            self.session.query_unpaged(
+                "UPDATE cypress.place_source_imports SET files[?] = ? WHERE id IN ?",
+                (source_file, import_id, chunk),
+            )
+        });
+        futures::future::try_join_all(chunks).await?;
+        Ok(())
+    }
+
+    /// Remove source files from a place; returns the source files still containing it
+    pub async fn remove_place_sources(
+        &self,
+        id: &str,
+        source_files: &[String],
+    ) -> Result<HashMap<String, i64>> {
+        let source_files: HashSet<&String> = source_files.iter().collect();
+        self.session
+            .query_unpaged(
+                "UPDATE cypress.place_source_imports SET files = files - ? WHERE id = ?",
+                (source_files, id),
+            )
+            .await?;
+        self.get_place_sources(id).await
+    }
+
+    /// Source files containing a place, with the import that last saw it in each
+    pub async fn get_place_sources(&self, id: &str) -> Result<HashMap<String, i64>> {
+        let result = self
+            .session
+            .query_unpaged(
+                "SELECT files FROM cypress.place_source_imports WHERE id = ?",
+                (id,),
+            )
+            .await?;
+
+        if let Ok(rows_result) = result.into_rows_result() {
+            if let Some((files,)) =
+                rows_result.maybe_first_row::<(Option<HashMap<String, i64>>,)>()?
+            {
+                return Ok(files.unwrap_or_default());
+            }
+        }
+
+        Ok(HashMap::new())
+    }
+
+    /// Source files of the places among `ids`, see [`Self::get_place_sources`]
+    pub async fn get_places_sources(
+        &self,
+        ids: &[&str],
+    ) -> Result<HashMap<String, HashMap<String, i64>>> {
+        let chunks = ids.chunks(MAX_IN_KEYS).map(|chunk| async move {
+            let result = self
+                .session
+                .query_unpaged(
+                    "SELECT id, files FROM cypress.place_source_imports WHERE id IN ?",
+                    (chunk,),
+                )
+                .await?;
+            let mut sources = Vec::new();
+            if let Ok(rows_result) = result.into_rows_result() {
+                for row in rows_result.rows::<(String, Option<HashMap<String, i64>>)>()? {
+                    let (id, files) = row?;
+                    sources.push((id, files.unwrap_or_default()));
+                }
+            }
+            Ok::<_, anyhow::Error>(sources)
+        });
+
+        let chunks = futures::future::try_join_all(chunks).await?;
+        Ok(chunks.into_iter().flatten().collect())
+    }
+
+    /// Delete a place and its source files
+    pub async fn delete_place(&self, id: &str) -> Result<()> {
+        self.session
+            .query_unpaged("DELETE FROM cypress.places WHERE id = ?", (id,))
+            .await?;
+        self.session
+            .query_unpaged(
+                "DELETE FROM cypress.place_source_imports WHERE id = ?",
+                (id,),
             )
             .await?;
         Ok(())
@@ -101,12 +269,9 @@ impl ScyllaClient {
         Ok(None)
     }
 
-    pub async fn get_admin_areas(
-        &self,
-        ids: &[String],
-    ) -> Result<std::collections::HashMap<String, String>> {
+    pub async fn get_admin_areas(&self, ids: &[String]) -> Result<HashMap<String, String>> {
         if ids.is_empty() {
-            return Ok(std::collections::HashMap::new());
+            return Ok(HashMap::new());
         }
 
         // Prepare query with IN clause
@@ -118,7 +283,7 @@ impl ScyllaClient {
         );
 
         let result = self.session.query_unpaged(query, ids).await?;
-        let mut map = std::collections::HashMap::new();
+        let mut map = HashMap::new();
 
         if let Ok(rows_result) = result.into_rows_result() {
             for row_res in rows_result.rows::<(String, String)>()? {
@@ -0,0 +1,184 @@
+//! Chinese, Japanese and Korean names.
+//!
+//! CJK names are written without spaces, so the Elasticsearch tokenizer sees "東京都庁"
+//! as a single token that only matches exactly. [`ngrams`] breaks CJK text into single
+//! characters and overlapping pairs for partial matching, after folding Traditional
+//! Chinese characters to Simplified ones (`schema/cjk/traditional_simplified.txt`) so
+//! 臺北 and 台北 match each other. [`reading`] gives a romanized reading of Chinese
+//! (pinyin) and Korean (Revised Romanization) names; Japanese kanji have no single
+//! reading, their romaji come from `name:ja-Latn` tags.
+
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+use any_ascii::any_ascii;
+
+use super::is_cjk;
+
+const TRADITIONAL_SIMPLIFIED: &str = include_str!("../../schema/cjk/traditional_simplified.txt");
+
+fn simplified() -> &'static HashMap<char, char> {
+    static TABLE: OnceLock<HashMap<char, char>> = OnceLock::new();
+    TABLE.get_or_init(|| {
+        TRADITIONAL_SIMPLIFIED
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .filter_map(|line| {
+                let mut chars = line.split_whitespace().filter_map(|c| c.chars().next());
+                Some((chars.next()?, chars.next()?))
+            })
+            .collect()
+    })
+}
+
+/// Whether `text` contains any CJK character
+pub fn contains_cjk(text: &str) -> bool {
+    text.chars().any(is_cjk)
+}
+
+/// Traditional Chinese characters replaced by their Simplified form
+pub fn fold(text: &str) -> String {
+    let table = simplified();
+    text.chars().map(|c| *table.get(&c).unwrap_or(&c)).collect()
+}
+
+/// Index terms of a name: single characters and overlapping pairs of each run of CJK
+/// characters ("东京都" -> "东 京 都 东京 京都"), other words lowercased. `None` without
+/// CJK characters.
+pub fn ngrams(text: &str) -> Option<String> {
+    terms(text, true)
+}
+
+/// Query terms: pairs of each run of CJK characters, or the character alone in a run of
+/// one, so every term of a query also occurs in the [`ngrams`] of a matching name
+pub fn query_ngrams(text: &str) -> Option<String> {
+    terms(text, false)
+}
+
+fn terms(text: &str, unigrams: bool) -> Option<String> {
+    if !contains_cjk(text) {
+        return None;
+    }
+
+    let mut terms: Vec<String> = Vec::new();
+    for word in fold(text).split_whitespace() {
+        for (is_run, part) in runs(word) {
+            if !is_run {
+                let part: String = part
+                    .chars()
+                    .filter(|c| c.is_alphanumeric())
+                    .flat_map(char::to_lowercase)
+                    .collect();
+                if !part.is_empty() {
+                    terms.push(part);
+                }
+                continue;
+            }
+
+            let chars: Vec<char> = part.chars().collect();
+            if unigrams || chars.len() == 1 {
+                terms.extend(chars.iter().map(char::to_string));
+            }
+            terms.extend(chars.windows(2).map(|pair| pair.iter().collect::<String>()));
+        }
+    }
+    Some(terms.join(" "))
+}
+
+/// Split a word into runs of CJK and other characters
+fn runs(word: &str) -> Vec<(bool, String)> {
+    let mut runs: Vec<(bool, String)> = Vec::new();
+    for c in word.chars() {
+        let cjk = is_cjk(c);
+        match runs.last_mut() {
+            Some((run_cjk, run)) if *run_cjk == cjk => run.push(c),
+            _ => runs.push((cjk, c.to_string())),
+        }
+    }
+    runs
+}
+
+/// Romanized reading of a Chinese or Korean name: the syllables, adjacent syllables
+/// joined and each word joined ("北京市" -> "bei jing shi beijing jingshi beijingshi"),
+/// so both "Beijing" and "Bei Jing" match. Han characters are read as Chinese unless
+/// the name is Japanese or Korean or contains kana.
+pub fn reading(text: &str, language: Option<&str>) -> Option<String> {
+    let hangul = text.chars().any(is_hangul);
+    let han = text.chars().any(is_han);
+    let kana = text.chars().any(is_kana);
+    let chinese = han && !kana && !matches!(language, Some("ja") | Some("ko"));
+    if !hangul && !chinese {
+        return None;
+    }
+
+    let mut terms: Vec<String> = Vec::new();
+    for word in text.split_whitespace() {
+        let syllables: Vec<String> = word
+            .chars()
+            .filter(|c| c.is_alphanumeric())
+            .map(|c| {
+                if is_hangul(c) || (chinese && is_han(c)) {
+                    any_ascii(&c.to_string()).to_lowercase()
+                } else {
+                    c.to_lowercase().to_string()
+                }
+            })
+            .filter(|s| !s.is_empty())
+            .collect();
+        if syllables.is_empty() {
+            continue;
+        }
+
+        terms.extend(syllables.iter().cloned());
+        if syllables.len() > 2 {
+            terms.extend(syllables.windows(2).map(|pair| pair.concat()));
+        }
+        if syllables.len() > 1 {
+            terms.push(syllables.concat());
+        }
+    }
+    (!terms.is_empty()).then(|| terms.join(" "))
+}
+
+fn is_han(c: char) -> bool {
+    matches!(c,
+        '\u{3400}'..='\u{4DBF}' | '\u{4E00}'..='\u{9FFF}' | '\u{F900}'..='\u{FAFF}'
+        | '\u{20000}'..='\u{2FA1F}'
+    )
+}
+
+fn is_kana(c: char) -> bool {
+    matches!(c, '\u{3040}'..='\u{30FF}' | '\u{FF66}'..='\u{FF9F}')
+}
+
+fn is_hangul(c: char) -> bool {
+    matches!(c, '\u{1100}'..='\u{11FF}' | '\u{3130}'..='\u{318F}' | '\u{AC00}'..='\u{D7AF}')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ngrams() {
+        assert_eq!(ngrams("東京都").as_deref(), Some("东 京 都 东京 京都"));
+        assert_eq!(query_ngrams("東京").as_deref(), Some("东京"));
+        assert_eq!(query_ngrams("京").as_deref(), Some("京"));
+        // Traditional and Simplified names match each other
+        assert_eq!(ngrams("臺北"), ngrams("台北"));
+        assert_eq!(ngrams("JR新宿駅").as_deref(), Some("jr 新 宿 駅 新宿 宿駅"));
+        assert!(ngrams("Zürich").is_none());
+    }
+
+    #[test]
+    fn test_reading() {
+        assert_eq!(
+            reading("北京市", Some("zh")).as_deref(),
+            Some("bei jing shi beijing jingshi beijingshi")
+        );
+        assert_eq!(reading("서울", None).as_deref(), Some("seo ul seoul"));
+        // Japanese kanji have no Chinese reading
+        assert!(reading("東京", Some("ja")).is_none());
+        assert!(reading("とうきょう", None).is_none());
+    }
+}
@@ -0,0 +1,124 @@
+//! House number normalization.
+//!
+//! `addr:housenumber` is free text: "12a", "12 A", "12-14", "12/1", "12 bis", "12;14".
+//! [`normalize`] gives one number a canonical spelling ("12 A" -> "12a") and its numeric
+//! part; [`expand`] also splits lists and ranges into the numbers they cover, so "12-14"
+//! is found when searching for 14.
+
+/// Longest range that is expanded into single numbers; longer ones keep their ends
+const MAX_RANGE: u32 = 20;
+
+/// A normalized house number
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HouseNumber {
+    /// Lowercase, without whitespace: "12a", "12/1", "12bis", "12-14"
+    pub canonical: String,
+    /// Leading number, for ranking by distance: 12
+    pub number: Option<u32>,
+}
+
+/// Normalize a single house number. `None` if it has no digits.
+pub fn normalize(raw: &str) -> Option<HouseNumber> {
+    let mut canonical: String = raw
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .flat_map(char::to_lowercase)
+        .collect();
+
+    // "12-a" is "12a", unlike the range "12-14"
+    if let Some(pos) = canonical.find('-') {
+        if canonical[pos + 1..].starts_with(|c: char| c.is_alphabetic()) {
+            canonical.remove(pos);
+        }
+    }
+
+    if !canonical.chars().any(|c| c.is_ascii_digit()) {
+        return None;
+    }
+
+    let number = leading_number(&canonical);
+    Some(HouseNumber { canonical, number })
+}
+
+/// All searchable values of a house number tag: each list entry ("12;14", "12,14"), and
+/// for ranges the range itself plus the numbers it covers. Ranges with both ends even
+/// or both odd cover one side of the street ("12-16" is 12, 14, 16).
+pub fn expand(raw: &str) -> Vec<HouseNumber> {
+    let mut values: Vec<HouseNumber> = Vec::new();
+    let mut push = |value: HouseNumber| {
+        if !values.contains(&value) {
+            values.push(value);
+        }
+    };
+
+    for part in raw.split([';', ',']) {
+        let Some(value) = normalize(part) else {
+            continue;
+        };
+
+        if let Some((from, to)) = range(&value.canonical) {
+            push(value);
+            let step = if from % 2 == to % 2 { 2 } else { 1 };
+            let numbers: Vec<u32> = if to - from <= MAX_RANGE {
+                (from..=to).step_by(step).collect()
+            } else {
+                vec![from, to]
+            };
+            for number in numbers {
+                push(HouseNumber {
+                    canonical: number.to_string(),
+                    number: Some(number),
+                });
+            }
+        } else {
+            push(value);
+        }
+    }
+    values
+}
+
+/// "12-14" -> (12, 14)
+fn range(canonical: &str) -> Option<(u32, u32)> {
+    let (from, to) = canonical.split_once('-')?;
+    let (from, to): (u32, u32) = (from.parse().ok()?, to.parse().ok()?);
+    (from < to).then_some((from, to))
+}
+
+fn leading_number(canonical: &str) -> Option<u32> {
+    let digits: String = canonical
+        .chars()
+        .take_while(|c| c.is_ascii_digit())
+        .collect();
+    digits.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn canonical(raw: &str) -> Vec<String> {
+        expand(raw).into_iter().map(|v| v.canonical).collect()
+    }
+
+    #[test]
+    fn test_normalize() {
+        let value = normalize("12 A").unwrap();
+        assert_eq!(value.canonical, "12a");
+        assert_eq!(value.number, Some(12));
+        assert_eq!(normalize("12-a").unwrap().canonical, "12a");
+        assert_eq!(normalize("12 bis").unwrap().canonical, "12bis");
+        assert_eq!(normalize("12 / 1").unwrap().canonical, "12/1");
+        assert_eq!(normalize("A12").unwrap().number, None);
+        assert!(normalize("Haus").is_none());
+    }
+
+    #[test]
+    fn test_expand() {
+        assert_eq!(canonical("12a"), ["12a"]);
+        assert_eq!(canonical("12;14"), ["12", "14"]);
+        assert_eq!(canonical("12-16"), ["12-16", "12", "14", "16"]);
+        assert_eq!(canonical("1-3, 2"), ["1-3", "1", "3", "2"]);
+        assert_eq!(canonical("12-13"), ["12-13", "12", "13"]);
+        assert_eq!(canonical("2-200"), ["2-200", "2", "200"]);
+    }
+}
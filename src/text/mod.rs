@@ -0,0 +1,50 @@
+//! Text normalization shared by the ingest and query binaries.
+//!
+//! Whatever is applied to names while indexing has to be applied to query text the same
+//! way, so both sides use the functions in here.
+
+pub mod cjk;
+pub mod housenumber;
+pub mod phonetic;
+pub mod street;
+pub mod transliterate;
+
+use std::collections::HashMap;
+
+/// Language of the name stored under `key` in a place's names (`uk`, `alt_name:sr-Latn`,
+/// `default`). The untagged name is in the language it is also tagged in.
+pub fn name_language<'a>(names: &'a HashMap<String, String>, key: &'a str) -> Option<&'a str> {
+    key_language(key).or_else(|| {
+        let name = names.get(key)?;
+        names
+            .iter()
+            .filter(|(k, n)| *k != key && *n == name)
+            .find_map(|(k, _)| key_language(k))
+    })
+}
+
+/// `uk` -> `uk`, `alt_name:sr-Latn` -> `sr`, `default` and `alt_name` have none
+fn key_language(key: &str) -> Option<&str> {
+    let language = match key.rsplit_once(':') {
+        Some((_, language)) => language,
+        None if key == "default" || key.contains('_') => return None,
+        None => key,
+    };
+    let language = language.split(['-', '_']).next()?;
+    (language.len() <= 3 && language.chars().all(|c| c.is_ascii_lowercase())).then_some(language)
+}
+
+/// Chinese, Japanese and Korean characters: Han, kana, bopomofo and Hangul
+pub(crate) fn is_cjk(c: char) -> bool {
+    matches!(c,
+        '\u{1100}'..='\u{11FF}'
+        | '\u{2E80}'..='\u{2FDF}'
+        | '\u{3040}'..='\u{31FF}'
+        | '\u{3400}'..='\u{4DBF}'
+        | '\u{4E00}'..='\u{9FFF}'
+        | '\u{AC00}'..='\u{D7AF}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{FF66}'..='\u{FF9F}'
+        | '\u{20000}'..='\u{2FA1F}'
+    )
+}
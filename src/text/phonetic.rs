@@ -0,0 +1,296 @@
+//! Phonetic keys for names.
+//!
+//! Names are often typed the way they sound ("Zurick", "Shtutgart"). Each word of a
+//! Latin-script name gets a phonetic key: Kölner Phonetik for German names, Metaphone
+//! for all others. Keys are prefixed with their algorithm (`k874`, `mSRX`), so both can
+//! live in one field. A query does not know the language of the name it looks for, so
+//! [`query_keys`] gives each query word a key from both algorithms.
+
+use std::collections::{BTreeSet, HashMap};
+
+use any_ascii::any_ascii;
+
+use super::name_language;
+
+/// Shorter words ("am", "de", "st") carry no useful sound
+const MIN_WORD_CHARS: usize = 3;
+
+/// Languages whose names use Kölner Phonetik
+const COLOGNE_LANGUAGES: &[&str] = &["de"];
+
+/// Phonetic keys of the words of a name in `language`
+pub fn keys(text: &str, language: Option<&str>) -> Vec<String> {
+    let cologne = language.is_some_and(|l| COLOGNE_LANGUAGES.contains(&l));
+    let keys: BTreeSet<String> = words(text)
+        .iter()
+        .filter_map(|word| {
+            if cologne {
+                cologne_key(word)
+            } else {
+                metaphone_key(word)
+            }
+        })
+        .collect();
+    keys.into_iter().collect()
+}
+
+/// Phonetic keys of all names of a place, each in its own language
+pub fn names(names: &HashMap<String, String>) -> Vec<String> {
+    let keys: BTreeSet<String> = names
+        .iter()
+        .flat_map(|(key, name)| self::keys(name, name_language(names, key)))
+        .collect();
+    keys.into_iter().collect()
+}
+
+/// Keys of each word of a query, from every algorithm
+pub fn query_keys(text: &str) -> Vec<Vec<String>> {
+    words(text)
+        .iter()
+        .map(|word| {
+            [cologne_key(word), metaphone_key(word)]
+                .into_iter()
+                .flatten()
+                .collect::<Vec<_>>()
+        })
+        .filter(|keys| !keys.is_empty())
+        .collect()
+}
+
+fn cologne_key(word: &str) -> Option<String> {
+    let code = cologne(word);
+    (!code.is_empty()).then(|| format!("k{}", code))
+}
+
+fn metaphone_key(word: &str) -> Option<String> {
+    let code = metaphone(word);
+    (!code.is_empty()).then(|| format!("m{}", code))
+}
+
+/// Uppercase ASCII words of a Latin-script text ("Zürich" -> "ZURICH"); words in other
+/// scripts are skipped
+fn words(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| {
+            word.chars()
+                .all(|c| c.is_ascii_alphabetic() || is_latin_letter(c))
+        })
+        .map(|word| any_ascii(word).to_ascii_uppercase())
+        .filter(|word| {
+            word.len() >= MIN_WORD_CHARS && word.chars().all(|c| c.is_ascii_alphabetic())
+        })
+        .collect()
+}
+
+fn is_latin_letter(c: char) -> bool {
+    c.is_alphabetic() && matches!(c, '\u{00C0}'..='\u{024F}' | '\u{1E00}'..='\u{1EFF}')
+}
+
+/// Kölner Phonetik of an uppercase ASCII word: "STUTTGART" -> "822472"
+pub fn cologne(word: &str) -> String {
+    let chars: Vec<char> = word.chars().collect();
+    let mut codes: Vec<char> = Vec::new();
+
+    for (i, &c) in chars.iter().enumerate() {
+        let prev = i.checked_sub(1).map(|p| chars[p]);
+        let next = chars.get(i + 1).copied();
+        let next_in = |set: &str| next.is_some_and(|n| set.contains(n));
+        let prev_in = |set: &str| prev.is_some_and(|p| set.contains(p));
+
+        let code: &str = match c {
+            'A' | 'E' | 'I' | 'J' | 'O' | 'U' | 'Y' => "0",
+            'H' => "",
+            'B' => "1",
+            'P' => {
+                if next_in("H") {
+                    "3"
+                } else {
+                    "1"
+                }
+            }
+            'D' | 'T' => {
+                if next_in("CSZ") {
+                    "8"
+                } else {
+                    "2"
+                }
+            }
+            'F' | 'V' | 'W' => "3",
+            'G' | 'K' | 'Q' => "4",
+            'C' => {
+                let hard = if i == 0 {
+                    next_in("AHKLOQRUX")
+                } else {
+                    next_in("AHKOQUX") && !prev_in("SZ")
+                };
+                if hard {
+                    "4"
+                } else {
+                    "8"
+                }
+            }
+            'X' => {
+                if prev_in("CKQ") {
+                    "8"
+                } else {
+                    "48"
+                }
+            }
+            'L' => "5",
+            'M' | 'N' => "6",
+            'R' => "7",
+            'S' | 'Z' => "8",
+            _ => "",
+        };
+        codes.extend(code.chars());
+    }
+
+    codes.dedup();
+    codes
+        .iter()
+        .enumerate()
+        .filter(|&(i, &c)| i == 0 || c != '0')
+        .map(|(_, &c)| c)
+        .collect()
+}
+
+/// Metaphone of an uppercase ASCII word: "ZURICH" -> "SRX", "THOMAS" -> "0MS"
+pub fn metaphone(word: &str) -> String {
+    let mut chars: Vec<char> = word.chars().collect();
+
+    // Silent or changed initial letters
+    match chars.as_slice() {
+        ['A', 'E', ..] | ['G', 'N', ..] | ['K', 'N', ..] | ['P', 'N', ..] | ['W', 'R', ..] => {
+            chars.remove(0);
+        }
+        ['X', ..] => chars[0] = 'S',
+        ['W', 'H', ..] => {
+            chars.remove(1);
+        }
+        _ => {}
+    }
+
+    let is_vowel = |c: char| "AEIOU".contains(c);
+    let at = |i: usize| chars.get(i).copied();
+    let mut key = String::new();
+
+    for (i, &c) in chars.iter().enumerate() {
+        let prev = i.checked_sub(1).and_then(at);
+        let next = at(i + 1);
+        let after = at(i + 2);
+        let next_is = |set: &str| next.is_some_and(|n| set.contains(n));
+
+        // Doubled letters sound once, except C
+        if prev == Some(c) && c != 'C' {
+            continue;
+        }
+
+        match c {
+            // Vowels only sound at the start
+            'A' | 'E' | 'I' | 'O' | 'U' if i == 0 => key.push(c),
+            // Silent in a final "MB"
+            'B' if !(prev == Some('M') && next.is_none()) => key.push('B'),
+            'C' => {
+                if next == Some('I') && after == Some('A') {
+                    key.push('X');
+                } else if next == Some('H') {
+                    key.push(if prev == Some('S') { 'K' } else { 'X' });
+                } else if next_is("IEY") {
+                    if prev != Some('S') {
+                        key.push('S');
+                    }
+                } else {
+                    key.push('K');
+                }
+            }
+            'D' => {
+                if next == Some('G') && after.is_some_and(|a| "EIY".contains(a)) {
+                    key.push('J');
+                } else {
+                    key.push('T');
+                }
+            }
+            'G' => {
+                let silent_gh = next == Some('H') && !after.is_some_and(is_vowel);
+                let silent_gn = next == Some('N') && (after.is_none() || i + 4 == chars.len());
+                if silent_gh || silent_gn {
+                    continue;
+                }
+                if next_is("IEY") && prev != Some('G') {
+                    key.push('J');
+                } else {
+                    key.push('K');
+                }
+            }
+            'H' => {
+                let after_modifier = prev.is_some_and(|p| "CSPTG".contains(p));
+                if !after_modifier && next.is_some_and(is_vowel) {
+                    key.push('H');
+                }
+            }
+            'K' if prev != Some('C') => key.push('K'),
+            'P' => key.push(if next == Some('H') { 'F' } else { 'P' }),
+            'Q' => key.push('K'),
+            'S' => {
+                if next == Some('H')
+                    || (next == Some('I') && after.is_some_and(|a| "AO".contains(a)))
+                {
+                    key.push('X');
+                } else {
+                    key.push('S');
+                }
+            }
+            'T' => {
+                if next == Some('I') && after.is_some_and(|a| "AO".contains(a)) {
+                    key.push('X');
+                } else if next == Some('H') {
+                    key.push('0');
+                } else if !(next == Some('C') && after == Some('H')) {
+                    key.push('T');
+                }
+            }
+            'V' => key.push('F'),
+            'W' | 'Y' if next.is_some_and(is_vowel) => key.push(c),
+            'X' => key.push_str("KS"),
+            'Z' => key.push('S'),
+            'F' | 'J' | 'L' | 'M' | 'N' | 'R' => key.push(c),
+            _ => {}
+        }
+    }
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cologne() {
+        assert_eq!(cologne("STUTTGART"), "822472");
+        assert_eq!(cologne("SHTUTGART"), cologne("STUTTGART"));
+        assert_eq!(cologne("ZURICK"), cologne("ZURICH"));
+        assert_eq!(cologne("MUELLER"), cologne("MULLER"));
+    }
+
+    #[test]
+    fn test_metaphone() {
+        assert_eq!(metaphone("ZURICH"), "SRX");
+        assert_eq!(metaphone("THOMAS"), "0MS");
+        assert_eq!(metaphone("KNIGHT"), "NT");
+        assert_eq!(metaphone("GOTHENBORG"), metaphone("GOTHENBURG"));
+        assert_eq!(metaphone("PHILADELPHIA"), metaphone("FILADELFIA"));
+    }
+
+    #[test]
+    fn test_keys() {
+        assert_eq!(keys("Zürich", Some("de")), ["k874"]);
+        assert_eq!(keys("Gothenburg", Some("en")), ["mK0NBRK"]);
+        // Short words and other scripts have no key
+        assert!(keys("Am See", Some("de")).len() == 1);
+        assert!(keys("Москва", None).is_empty());
+
+        let query = query_keys("Zurick");
+        assert_eq!(query, [vec!["k874".to_string(), "mSRK".to_string()]]);
+        assert!(query[0].contains(&keys("Zürich", Some("de"))[0]));
+    }
+}
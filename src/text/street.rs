@@ -0,0 +1,315 @@
+//! Street type suffixes of compound street names.
+//!
+//! German, Dutch and Nordic street names glue the street type to the name
+//! ("Bahnhofstraße", "Kerkstraat", "Storgatan"), but the same street is also written
+//! apart ("Bahnhof Strasse") or abbreviated ("Bahnhofstr."). [`variants`] splits the
+//! suffix off and spells it one way, so all of these share the form "bahnhof strasse".
+//! The Elasticsearch street analyzer only knows English street types.
+//!
+//! Suffixes only apply to names in their language, so "Highgate" is not taken for a
+//! Norwegian "gate" and "Bahnhofstr." not for a Dutch "straat".
+
+use std::collections::HashMap;
+
+use super::name_language;
+
+/// Minimum length of the name part of a compound, so short words that merely end like
+/// a street type ("Arkade") are left alone
+const MIN_STEM_CHARS: usize = 3;
+
+/// Upper bound on variants per text (ambiguous abbreviations multiply)
+const MAX_VARIANTS: usize = 4;
+
+/// Street type spellings, the canonical form they stand for and its language. Longer
+/// spellings come before their prefixes ("gatan" before "gata"); "str." is German or Dutch.
+const SUFFIXES: &[(&str, &str, &str)] = &[
+    // German
+    ("strasse", "strasse", "de"),
+    ("straße", "strasse", "de"),
+    ("str", "strasse", "de"),
+    ("str", "straat", "nl"),
+    ("gasse", "gasse", "de"),
+    ("platz", "platz", "de"),
+    ("allee", "allee", "de"),
+    ("damm", "damm", "de"),
+    ("ufer", "ufer", "de"),
+    ("steig", "steig", "de"),
+    ("pfad", "pfad", "de"),
+    ("weg", "weg", "de"),
+    // Dutch
+    ("straat", "straat", "nl"),
+    ("laan", "laan", "nl"),
+    ("plein", "plein", "nl"),
+    ("gracht", "gracht", "nl"),
+    ("singel", "singel", "nl"),
+    ("kade", "kade", "nl"),
+    ("dijk", "dijk", "nl"),
+    // Swedish
+    ("gatan", "gatan", "sv"),
+    ("gata", "gata", "sv"),
+    ("vägen", "vägen", "sv"),
+    ("väg", "väg", "sv"),
+    ("torget", "torget", "sv"),
+    ("gränd", "gränd", "sv"),
+    // Danish and Norwegian
+    ("vejen", "vejen", "da"),
+    ("vej", "vej", "da"),
+    ("veien", "veien", "no"),
+    ("vei", "vei", "no"),
+    ("gaden", "gaden", "da"),
+    ("gade", "gade", "da"),
+    ("gate", "gate", "no"),
+    ("plads", "plads", "da"),
+    ("torv", "torv", "da"),
+    ("torv", "torv", "no"),
+];
+
+/// Languages with compound street names, for text in an unknown language
+pub const LANGUAGES: &[&str] = &["de", "nl", "sv", "da", "no"];
+
+/// Languages with compound street names spoken in a country (ISO 3166-1 alpha-2)
+pub fn country_languages(alpha2: &str) -> &'static [&'static str] {
+    match alpha2.to_ascii_uppercase().as_str() {
+        "DE" | "AT" | "CH" | "LI" | "LU" => &["de"],
+        "NL" | "SR" => &["nl"],
+        "BE" => &["nl", "de"],
+        "SE" | "FI" | "AX" => &["sv"],
+        "DK" | "FO" | "GL" => &["da"],
+        "NO" | "SJ" => &["no"],
+        _ => &[],
+    }
+}
+
+/// Languages of a place's names whose street types apply to them: the languages the
+/// names are tagged (or inferred) in, or those of the place's country if none is known
+pub fn name_languages<'a>(
+    names: &'a HashMap<String, String>,
+    country: Option<&str>,
+) -> Vec<&'a str> {
+    let mut languages: Vec<&str> = names
+        .keys()
+        .filter_map(|key| name_language(names, key))
+        .collect();
+    if languages.is_empty() {
+        languages.extend(country.map(country_languages).unwrap_or_default());
+    }
+    languages.sort_unstable();
+    languages.dedup();
+    languages
+}
+
+/// Lowercase forms of `text` with the street type suffixes of `languages` split off and
+/// spelled the canonical way: "Bahnhofstr. 10" gives "bahnhof strasse 10" in German.
+/// Empty if `text` has no street type.
+pub fn variants(text: &str, languages: &[&str]) -> Vec<String> {
+    let tokens: Vec<(String, Vec<String>)> = text
+        .split_whitespace()
+        .map(|token| {
+            let clean = token
+                .trim_matches(|c: char| !c.is_alphanumeric())
+                .to_lowercase();
+            let alternatives =
+                split_compound(&clean, languages).unwrap_or_else(|| vec![clean.clone()]);
+            (clean, alternatives)
+        })
+        .filter(|(clean, _)| !clean.is_empty())
+        .collect();
+
+    let original: Vec<&str> = tokens.iter().map(|(clean, _)| clean.as_str()).collect();
+    let original = original.join(" ");
+
+    let mut variants = vec![String::new()];
+    for (_, alternatives) in &tokens {
+        variants = variants
+            .iter()
+            .flat_map(|prefix| {
+                alternatives.iter().map(move |token| {
+                    if prefix.is_empty() {
+                        token.clone()
+                    } else {
+                        format!("{} {}", prefix, token)
+                    }
+                })
+            })
+            .take(MAX_VARIANTS)
+            .collect();
+    }
+
+    variants.retain(|v| !v.is_empty() && *v != original);
+    variants
+}
+
+/// Comparable forms of a street name: the name and its [`variants`] in any language with
+/// only letters and digits ("Bahnhofstr." -> "bahnhofstr", "bahnhofstrasse",
+/// "bahnhofstraat"). Both sides of a comparison get the same forms, so the language does
+/// not matter here.
+pub fn keys(name: &str) -> Vec<String> {
+    let key = |form: &str| -> String {
+        form.chars()
+            .filter(|c| c.is_alphanumeric())
+            .flat_map(char::to_lowercase)
+            .collect()
+    };
+
+    let mut keys = vec![key(name)];
+    for variant in variants(name, LANGUAGES) {
+        let variant = key(&variant);
+        if !keys.contains(&variant) {
+            keys.push(variant);
+        }
+    }
+    keys.retain(|k| !k.is_empty());
+    keys
+}
+
+/// Whether two street [`keys`] name the same street, allowing one typo per five
+/// characters and at most two
+pub fn keys_match(a: &str, b: &str) -> bool {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let allowed = (a.len().min(b.len()) / 5).min(2);
+    if a.len().abs_diff(b.len()) > allowed {
+        return false;
+    }
+
+    // Levenshtein distance, one row at a time
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.iter().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous + usize::from(ca != cb);
+            previous = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(previous + 1);
+        }
+    }
+    row[b.len()] <= allowed
+}
+
+/// A lowercase token with a street type of `languages`, split into name and canonical
+/// street type. A token that is only a street type is spelled the canonical way.
+fn split_compound(token: &str, languages: &[&str]) -> Option<Vec<String>> {
+    let suffixes = || {
+        SUFFIXES
+            .iter()
+            .filter(|(_, _, language)| speaks(languages, language))
+    };
+    suffixes().find_map(|&(spelling, _, _)| {
+        let stem = token.strip_suffix(spelling)?.trim_end_matches('-');
+        let valid = stem.is_empty()
+            || (stem.chars().count() >= MIN_STEM_CHARS
+                && stem.chars().last().is_some_and(char::is_alphabetic));
+        if !valid {
+            return None;
+        }
+        let mut forms: Vec<String> = suffixes()
+            .filter(|(other, _, _)| *other == spelling)
+            .map(|(_, canonical, _)| {
+                if stem.is_empty() {
+                    canonical.to_string()
+                } else {
+                    format!("{} {}", stem, canonical)
+                }
+            })
+            .collect();
+        forms.dedup();
+        Some(forms)
+    })
+}
+
+/// Whether `language` is among `languages` (`de-CH` is German, Bokmål and Nynorsk are
+/// Norwegian)
+fn speaks(languages: &[&str], language: &str) -> bool {
+    languages.iter().any(|l| {
+        match l.split(['-', '_']).next().unwrap_or(l) {
+            "nb" | "nn" => "no",
+            primary => primary,
+        }
+        .eq_ignore_ascii_case(language)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spellings_share_a_variant() {
+        let spellings = [
+            "Bahnhofstr. 10",
+            "Bahnhof Strasse 10",
+            "Bahnhofstraße 10",
+            "Bahnhofstrasse 10",
+            "Bahnhof-Straße 10",
+        ];
+        for spelling in spellings {
+            // The text itself is searched too
+            let mut forms = variants(spelling, &["de"]);
+            forms.push(spelling.to_lowercase());
+            assert!(
+                forms.contains(&"bahnhof strasse 10".to_string()),
+                "{}",
+                spelling
+            );
+        }
+
+        assert_eq!(variants("Kerkstraat", &["nl"]), ["kerk straat"]);
+        assert_eq!(variants("Storgatan 3", &["sv"]), ["stor gatan 3"]);
+        assert_eq!(variants("Vesterbrogade", &["da"]), ["vesterbro gade"]);
+        assert_eq!(variants("Kirkegate", &["nb"]), ["kirke gate"]);
+    }
+
+    #[test]
+    fn test_suffixes_of_other_languages() {
+        assert_eq!(
+            variants("Bahnhofstr. 10", &["de-CH"]),
+            ["bahnhof strasse 10"]
+        );
+        assert_eq!(variants("Kerkstr.", &["nl"]), ["kerk straat"]);
+        assert!(variants("Highgate", &["en"]).is_empty());
+        assert!(variants("Colgate", &["de", "nl"]).is_empty());
+        assert_eq!(
+            variants("Bahnhofstr.", LANGUAGES),
+            ["bahnhof strasse", "bahnhof straat"]
+        );
+    }
+
+    #[test]
+    fn test_name_languages() {
+        let names = |pairs: &[(&str, &str)]| -> HashMap<String, String> {
+            pairs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect()
+        };
+        let tagged = names(&[("default", "Highgate"), ("en", "Highgate")]);
+        assert_eq!(name_languages(&tagged, Some("NO")), ["en"]);
+        let untagged = names(&[("default", "Kirkegata")]);
+        assert_eq!(name_languages(&untagged, Some("NO")), ["no"]);
+        assert!(name_languages(&untagged, None).is_empty());
+    }
+
+    #[test]
+    fn test_keys_match() {
+        let same = |a: &str, b: &str| {
+            keys(a)
+                .iter()
+                .any(|x| keys(b).iter().any(|y| keys_match(x, y)))
+        };
+        assert!(same("Bahnhofstr", "Bahnhofstrasse"));
+        assert!(same("Bahnhof-Strasse", "Bahnhofstraße"));
+        // Typos
+        assert!(same("Bahnhofstrase", "Bahnhofstrasse"));
+        assert!(same("Kerkstrat", "Kerkstraat"));
+        assert!(!same("Bahnhofplatz", "Bahnhofstrasse"));
+        assert!(!same("Main St", "Oak St"));
+    }
+
+    #[test]
+    fn test_no_street_type() {
+        assert!(variants("Zürich", LANGUAGES).is_empty());
+        assert!(variants("bahnhof strasse", LANGUAGES).is_empty());
+        assert!(variants("Arkade", LANGUAGES).is_empty());
+        assert!(variants("", LANGUAGES).is_empty());
+    }
+}
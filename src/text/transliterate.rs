@@ -0,0 +1,135 @@
+//! Latin transliteration of names in other scripts.
+//!
+//! Many places in Cyrillic, Greek, Georgian, Armenian, Hebrew or Arabic script have no
+//! `name:en` or `name:*-Latn`, but users type "Moskva" or "Thessaloniki". [`latin`]
+//! gives the Latin spelling of such a name. Hebrew and Arabic are written without most
+//! vowels, so their transliteration is only a consonant skeleton. Chinese and Korean
+//! names get their romanized reading from [`cjk::reading`].
+
+use std::collections::{BTreeSet, HashMap};
+
+use any_ascii::any_ascii;
+
+use super::{cjk, is_cjk, name_language};
+
+/// Letters spelled differently in a language than in the default (Russian-based)
+/// transliteration: Ukrainian "Київ" is "Kyiv", not "Kiiv"
+const LANGUAGE_LETTERS: &[(&str, &[(char, &str)])] = &[
+    (
+        "uk",
+        &[
+            ('и', "y"),
+            ('И', "Y"),
+            ('г', "h"),
+            ('Г', "H"),
+            ('ґ', "g"),
+            ('Ґ', "G"),
+            ('є', "ie"),
+            ('Є', "Ie"),
+            ('ї', "i"),
+            ('Ї', "I"),
+            ('й', "i"),
+            ('Й', "I"),
+        ],
+    ),
+    ("bg", &[('щ', "sht"), ('Щ', "Sht"), ('ъ', "a"), ('Ъ', "A")]),
+];
+
+/// Latin spelling of a name in a non-Latin script, `None` if there is nothing to
+/// transliterate. `language` selects language-specific spellings.
+pub fn latin(text: &str, language: Option<&str>) -> Option<String> {
+    if !text
+        .chars()
+        .any(|c| c.is_alphabetic() && !is_latin(c) && !is_cjk(c))
+    {
+        return None;
+    }
+
+    let letters = language.and_then(|language| {
+        LANGUAGE_LETTERS
+            .iter()
+            .find(|(l, _)| *l == language)
+            .map(|(_, letters)| *letters)
+    });
+    let text: String = match letters {
+        Some(letters) => text
+            .chars()
+            .map(|c| match letters.iter().find(|(from, _)| *from == c) {
+                Some((_, to)) => to.to_string(),
+                None => c.to_string(),
+            })
+            .collect(),
+        None => text.to_string(),
+    };
+
+    let latin: String = any_ascii(&text)
+        .chars()
+        .filter(|c| c.is_alphanumeric() || c.is_whitespace() || *c == '-')
+        .collect();
+    let latin = latin.split_whitespace().collect::<Vec<_>>().join(" ");
+    (!latin.is_empty()).then_some(latin)
+}
+
+/// Transliterations and CJK readings of all names of a place that are not names already. Names are
+/// keyed by language (`uk`, `alt_name:uk`, `default`); the untagged name uses the
+/// language it is also tagged in.
+pub fn names(names: &HashMap<String, String>) -> Vec<String> {
+    let existing: BTreeSet<String> = names.values().map(|n| n.to_lowercase()).collect();
+
+    let mut latin_names = BTreeSet::new();
+    for (key, name) in names {
+        let language = name_language(names, key);
+        for latin_name in [latin(name, language), cjk::reading(name, language)]
+            .into_iter()
+            .flatten()
+        {
+            if !existing.contains(&latin_name.to_lowercase()) {
+                latin_names.insert(latin_name);
+            }
+        }
+    }
+    latin_names.into_iter().collect()
+}
+
+/// Basic Latin, Latin-1 and the Latin Extended blocks
+fn is_latin(c: char) -> bool {
+    c.is_ascii() || ('\u{00C0}'..='\u{024F}').contains(&c) || ('\u{1E00}'..='\u{1EFF}').contains(&c)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_latin() {
+        assert_eq!(latin("Москва", Some("ru")).as_deref(), Some("Moskva"));
+        assert_eq!(latin("Київ", Some("uk")).as_deref(), Some("Kyiv"));
+        assert_eq!(latin("Θεσσαλονίκη", None).as_deref(), Some("Thessaloniki"));
+        assert_eq!(latin("Београд", Some("sr")).as_deref(), Some("Beograd"));
+        assert_eq!(latin("თბილისი", None).as_deref(), Some("tbilisi"));
+        assert!(latin("Zürich", None).is_none());
+        assert!(latin("東京", None).is_none());
+    }
+
+    #[test]
+    fn test_names() {
+        let names: HashMap<String, String> = [
+            ("default", "Київ"),
+            ("uk", "Київ"),
+            ("ru", "Киев"),
+            ("en", "Kyiv"),
+        ]
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+
+        // "Kyiv" is already a name
+        assert_eq!(super::names(&names), ["Kiev"]);
+
+        let names: HashMap<String, String> = [("default", "臺北"), ("zh", "臺北")]
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        assert_eq!(super::names(&names), ["tai bei taibei"]);
+    }
+}
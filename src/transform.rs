@@ -0,0 +1,144 @@
+//! Custom per-place processing hooks for the ingest pipeline.
+//!
+//! A [`PlaceTransformer`] sees every [`Place`] after tag extraction and the PIP admin
+//! lookup, before it is written to ScyllaDB and Elasticsearch. It can modify the place,
+//! drop it (return nothing) or split it into several places. Fields derived from the
+//! location, address and names (postcode inference, street links, name languages, ...) are
+//! filled in afterwards, and a moved place gets its admin hierarchy looked up again.
+//!
+//! ```
+//! use cypress::transform::TransformerChain;
+//!
+//! let chain = TransformerChain::new()
+//!     // Drop a brand
+//!     .with(|place: cypress::Place| {
+//!         if place.name.get("default").is_some_and(|n| n == "Acme") {
+//!             vec![]
+//!         } else {
+//!             vec![place]
+//!         }
+//!     });
+//! assert_eq!(chain.len(), 1);
+//! ```
+
+use crate::models::Place;
+
+/// A step applied to each place during ingest
+pub trait PlaceTransformer: Send + Sync {
+    /// Transform one place into zero, one or several places
+    fn transform(&self, place: Place) -> Vec<Place>;
+}
+
+impl<F> PlaceTransformer for F
+where
+    F: Fn(Place) -> Vec<Place> + Send + Sync,
+{
+    fn transform(&self, place: Place) -> Vec<Place> {
+        self(place)
+    }
+}
+
+/// Transformers run in registration order; each one sees the output of the previous
+#[derive(Default)]
+pub struct TransformerChain {
+    transformers: Vec<Box<dyn PlaceTransformer>>,
+}
+
+impl TransformerChain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Append a transformer to the chain
+    pub fn register(&mut self, transformer: impl PlaceTransformer + 'static) {
+        self.transformers.push(Box::new(transformer));
+    }
+
+    /// Builder-style [`register`](Self::register)
+    pub fn with(mut self, transformer: impl PlaceTransformer + 'static) -> Self {
+        self.register(transformer);
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.transformers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.transformers.is_empty()
+    }
+
+    /// Run a place through every transformer
+    pub fn apply(&self, place: Place) -> Vec<Place> {
+        let mut places = vec![place];
+        for transformer in &self.transformers {
+            places = places
+                .into_iter()
+                .flat_map(|p| transformer.transform(p))
+                .collect();
+            if places.is_empty() {
+                break;
+            }
+        }
+        places
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{GeoPoint, Layer, OsmType};
+
+    fn place(name: &str) -> Place {
+        let mut place = Place::new(
+            OsmType::Node,
+            1,
+            Layer::Venue,
+            GeoPoint {
+                lat: 47.0,
+                lon: 8.0,
+            },
+            "test.osm.pbf",
+        );
+        place.add_name("default", name.to_string());
+        place
+    }
+
+    #[test]
+    fn test_chain_modifies_drops_and_splits() {
+        let chain = TransformerChain::new()
+            // Split "A;B" into two places
+            .with(|place: Place| {
+                let name = place.name["default"].clone();
+                name.split(';')
+                    .enumerate()
+                    .map(|(i, part)| {
+                        let mut p = place.clone();
+                        p.source_id = format!("{}-{}", place.source_id, i);
+                        p.add_name("default", part.to_string());
+                        p
+                    })
+                    .collect()
+            })
+            // Drop "B"
+            .with(|place: Place| {
+                if place.name["default"] == "B" {
+                    vec![]
+                } else {
+                    vec![place]
+                }
+            })
+            // Boost everything left
+            .with(|mut place: Place| {
+                place.importance = Some(0.9);
+                vec![place]
+            });
+
+        let out = chain.apply(place("A;B;C"));
+        let names: Vec<_> = out.iter().map(|p| p.name["default"].as_str()).collect();
+        assert_eq!(names, ["A", "C"]);
+        assert!(out.iter().all(|p| p.importance == Some(0.9)));
+
+        assert_eq!(TransformerChain::new().apply(place("A")).len(), 1);
+    }
+}